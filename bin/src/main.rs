@@ -24,15 +24,19 @@ async fn main() {
             "calc" => plugins::calc::main().await,
             "desktop-entries" => plugins::desktop_entries::main().await,
             "find" => plugins::find::main().await,
+            "mpris" => plugins::mpris::main().await,
+            "network" => plugins::network::main().await,
             "files" => plugins::files::main().await,
             "pop-launcher" => service::main().await,
             "pop-shell" => plugins::pop_shell::main().await,
             "pulse" => plugins::pulse::main().await,
             "recent" => plugins::recent::main().await,
             "scripts" => plugins::scripts::main().await,
+            "session" => plugins::session::main().await,
             "terminal" => plugins::terminal::main().await,
             "web" => plugins::web::main().await,
             "cosmic-toplevel" => plugins::cosmic_toplevel::main().await,
+            "wlr-toplevel" => plugins::wlr_toplevel::main().await,
             unknown => {
                 eprintln!("unknown cmd: {}", unknown);
             }
@@ -70,6 +70,7 @@ impl App {
         let options = vec![ContextOption {
             id: 0,
             name: "Qalculate! Manual".into(),
+            ..Default::default()
         }];
 
         crate::send(&mut self.out, PluginResponse::Context { id: 0, options }).await;
@@ -1,14 +1,16 @@
 mod toplevel_handler;
 
 use cctk::cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1::State;
-use cctk::wayland_client::Proxy;
+use cctk::wayland_client::protocol::wl_output::WlOutput;
+use cctk::wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1;
 use cctk::{sctk::reexports::calloop, toplevel_info::ToplevelInfo};
 use fde::DesktopEntry;
 use freedesktop_desktop_entry as fde;
-use toplevel_handler::ToplevelUpdate;
+use toplevel_handler::{Capabilities, ToplevelUpdate, WorkspaceInfo};
 use tracing::{debug, error, info, warn};
 
 use crate::desktop_entries::utils::{get_description, is_session_cosmic};
+use crate::handle_table::{self, HandleTable};
 use crate::send;
 use futures::{
     StreamExt,
@@ -16,14 +18,22 @@ use futures::{
     future::{Either, select},
 };
 use pop_launcher::{
-    IconSource, PluginResponse, PluginSearchResult, Request, async_stdin, async_stdout,
-    json_input_stream,
+    ContextOption, IconSource, PluginResponse, PluginSearchResult, Request, async_stdin,
+    async_stdout, json_input_stream,
 };
 use std::borrow::Cow;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use self::toplevel_handler::{ToplevelAction, toplevel_handler};
 
+const MINIMIZE: u32 = 0;
+const MAXIMIZE: u32 = 1;
+const FULLSCREEN: u32 = 2;
+const STICKY: u32 = 3;
+/// Context options from this value onward move the window to the workspace
+/// at `context - MOVE_TO_WORKSPACE` in `App::offered_workspaces`.
+const MOVE_TO_WORKSPACE: u32 = 16;
+
 pub async fn main() {
     let mut tx = async_stdout();
 
@@ -49,12 +59,16 @@ pub async fn main() {
                             debug!("activating {id}");
                             app.activate(id);
                         }
+                        Request::ActivateContext { id, context } => {
+                            app.activate_context(id, context);
+                        }
+                        Request::Context(id) => app.context(id).await,
                         Request::Quit(id) => app.quit(id),
                         Request::Search(query) => {
                             debug!("searching {query}");
                             app.search(&query).await;
-                            // clear the ids to ignore, as all just sent are valid
-                            app.ids_to_ignore.clear();
+                            // the ids of windows removed before this search are no longer shown
+                            app.handles.recycle();
                         }
                         Request::Exit => break,
                         _ => (),
@@ -71,6 +85,7 @@ pub async fn main() {
                 for update in updates {
                     match update {
                         ToplevelUpdate::Info(info) => {
+                            app.handles.insert(info.foreign_toplevel.clone());
                             if let Some(pos) = app
                                 .toplevels
                                 .iter()
@@ -87,18 +102,32 @@ pub async fn main() {
                             }
                         }
                         ToplevelUpdate::Remove(foreign_toplevel) => {
+                            app.handles.remove(&foreign_toplevel);
+
                             if let Some(pos) = app
                                 .toplevels
                                 .iter()
                                 .position(|t| t.foreign_toplevel == foreign_toplevel)
                             {
                                 app.toplevels.remove(pos);
-                                // ignore requests for this id until after the next search
-                                app.ids_to_ignore.push(foreign_toplevel.id().protocol_id());
                             } else {
                                 warn!("no toplevel to remove");
                             }
                         }
+                        ToplevelUpdate::Capabilities(capabilities) => {
+                            app.capabilities = capabilities;
+                        }
+                        ToplevelUpdate::Output(output, name) => {
+                            if let Some(entry) = app.outputs.iter_mut().find(|(o, _)| *o == output)
+                            {
+                                entry.1 = name;
+                            } else {
+                                app.outputs.push((output, name));
+                            }
+                        }
+                        ToplevelUpdate::Workspaces(workspaces) => {
+                            app.workspaces = workspaces;
+                        }
                     }
                 }
             }
@@ -110,8 +139,14 @@ pub async fn main() {
 struct App<W> {
     locales: Vec<String>,
     desktop_entries: Vec<DesktopEntry>,
-    ids_to_ignore: Vec<u32>,
+    handles: HandleTable<ExtForeignToplevelHandleV1>,
     toplevels: Vec<Box<ToplevelInfo>>,
+    capabilities: Capabilities,
+    outputs: Vec<(WlOutput, String)>,
+    workspaces: Vec<WorkspaceInfo>,
+    /// Workspaces which the last context menu offered to move the window to,
+    /// as the workspaces may change while it is shown.
+    offered_workspaces: Vec<WorkspaceInfo>,
     calloop_tx: calloop::channel::Sender<ToplevelAction>,
     tx: W,
 }
@@ -133,8 +168,12 @@ impl<W: AsyncWrite + Unpin> App<W> {
             Self {
                 locales,
                 desktop_entries,
-                ids_to_ignore: Vec::new(),
+                handles: HandleTable::default(),
                 toplevels: Vec::new(),
+                capabilities: Capabilities::default(),
+                outputs: Vec::new(),
+                workspaces: Vec::new(),
+                offered_workspaces: Vec::new(),
                 calloop_tx,
                 tx,
             },
@@ -142,38 +181,161 @@ impl<W: AsyncWrite + Unpin> App<W> {
         )
     }
 
+    fn toplevel(&self, id: u32) -> Option<&ToplevelInfo> {
+        let handle = self.handles.get(id)?;
+
+        self.toplevels
+            .iter()
+            .find(|t| t.foreign_toplevel == *handle)
+            .map(Box::as_ref)
+    }
+
     fn activate(&mut self, id: u32) {
         info!("requested to activate: {id}");
-        if self.ids_to_ignore.contains(&id) {
-            return;
-        }
-        if let Some(handle) = self.toplevels.iter().find_map(|t| {
-            if t.foreign_toplevel.id().protocol_id() == id {
-                Some(t.foreign_toplevel.clone())
-            } else {
-                None
-            }
-        }) {
+        if let Some(info) = self.toplevel(id) {
             info!("activating: {id}");
+            let handle = info.foreign_toplevel.clone();
             let _res = self.calloop_tx.send(ToplevelAction::Activate(handle));
         }
     }
 
-    fn quit(&mut self, id: u32) {
-        if self.ids_to_ignore.contains(&id) {
+    fn activate_context(&mut self, id: u32, context: u32) {
+        let Some(info) = self.toplevel(id) else {
             return;
-        }
-        if let Some(handle) = self.toplevels.iter().find_map(|t| {
-            if t.foreign_toplevel.id().protocol_id() == id {
-                Some(t.foreign_toplevel.clone())
-            } else {
-                None
+        };
+
+        let handle = info.foreign_toplevel.clone();
+        let unless = |state: State| !info.state.contains(&state);
+
+        let action = match context {
+            MINIMIZE => ToplevelAction::Minimize(handle, unless(State::Minimized)),
+            MAXIMIZE => ToplevelAction::Maximize(handle, unless(State::Maximized)),
+            FULLSCREEN => ToplevelAction::Fullscreen(handle, unless(State::Fullscreen)),
+            STICKY => ToplevelAction::Sticky(handle, unless(State::Sticky)),
+            _ => {
+                let Some(workspace) = context
+                    .checked_sub(MOVE_TO_WORKSPACE)
+                    .and_then(|index| self.offered_workspaces.get(index as usize))
+                else {
+                    return;
+                };
+
+                // Keep the window on its output if the workspace is shown there.
+                let Some(output) = workspace
+                    .outputs
+                    .iter()
+                    .find(|output| info.output.contains(*output))
+                    .or_else(|| workspace.outputs.first())
+                else {
+                    return;
+                };
+
+                ToplevelAction::MoveToWorkspace(handle, workspace.handle.clone(), output.clone())
+            }
+        };
+
+        let _res = self.calloop_tx.send(action);
+    }
+
+    async fn context(&mut self, id: u32) {
+        let Some(info) = self.toplevel(id) else {
+            return;
+        };
+
+        let capabilities = self.capabilities;
+        let mut options = Vec::new();
+        let mut offered_workspaces = Vec::new();
+
+        let mut toggle = |supported: bool, context: u32, state: State, names: [&str; 2]| {
+            if supported {
+                options.push(ContextOption {
+                    id: context,
+                    name: names[usize::from(info.state.contains(&state))].to_owned(),
+                    ..Default::default()
+                });
             }
-        }) {
+        };
+
+        toggle(
+            capabilities.minimize,
+            MINIMIZE,
+            State::Minimized,
+            ["Minimize", "Unminimize"],
+        );
+
+        toggle(
+            capabilities.maximize,
+            MAXIMIZE,
+            State::Maximized,
+            ["Maximize", "Unmaximize"],
+        );
+
+        toggle(
+            capabilities.fullscreen,
+            FULLSCREEN,
+            State::Fullscreen,
+            ["Fullscreen", "Leave Fullscreen"],
+        );
+
+        toggle(
+            capabilities.sticky,
+            STICKY,
+            State::Sticky,
+            ["Show on All Workspaces", "Show on One Workspace"],
+        );
+
+        if capabilities.move_to_workspace {
+            for workspace in &self.workspaces {
+                let on_output = info.output.is_empty()
+                    || workspace
+                        .outputs
+                        .iter()
+                        .any(|output| info.output.contains(output));
+
+                if on_output && !info.workspace.contains(&workspace.handle) {
+                    options.push(ContextOption {
+                        id: MOVE_TO_WORKSPACE + offered_workspaces.len() as u32,
+                        name: format!("Move to Workspace {}", workspace.name),
+                        ..Default::default()
+                    });
+
+                    offered_workspaces.push(workspace.clone());
+                }
+            }
+        }
+
+        self.offered_workspaces = offered_workspaces;
+
+        if !options.is_empty() {
+            send(&mut self.tx, PluginResponse::Context { id, options }).await;
+        }
+    }
+
+    fn quit(&mut self, id: u32) {
+        if let Some(info) = self.toplevel(id) {
+            let handle = info.foreign_toplevel.clone();
             let _res = self.calloop_tx.send(ToplevelAction::Close(handle));
         }
     }
 
+    /// Names of the workspaces which the window is shown on.
+    fn workspace_names<'a>(&'a self, info: &ToplevelInfo) -> Vec<&'a str> {
+        self.workspaces
+            .iter()
+            .filter(|workspace| info.workspace.contains(&workspace.handle))
+            .map(|workspace| workspace.name.as_str())
+            .collect()
+    }
+
+    /// Names of the outputs which the window is shown on.
+    fn output_names<'a>(&'a self, info: &ToplevelInfo) -> Vec<&'a str> {
+        self.outputs
+            .iter()
+            .filter(|(output, _)| info.output.contains(output))
+            .map(|(_, name)| name.as_str())
+            .collect()
+    }
+
     async fn search(&mut self, query: &str) {
         fn contains_pattern(needle: &str, haystack: &[&str]) -> bool {
             let needle = needle.to_ascii_lowercase();
@@ -181,12 +343,19 @@ impl<W: AsyncWrite + Unpin> App<W> {
         }
 
         let query = query.to_ascii_lowercase();
-        let haystack = query.split_ascii_whitespace().collect::<Vec<&str>>();
+        let (haystack, workspace_filter) = parse_query(&query);
 
         for info in &self.toplevels {
-            let retain = query.is_empty()
+            let workspaces = self.workspace_names(info);
+
+            let retain = (haystack.is_empty()
                 || contains_pattern(&info.app_id, &haystack)
-                || contains_pattern(&info.title, &haystack);
+                || contains_pattern(&info.title, &haystack))
+                && workspace_filter.is_none_or(|filter| {
+                    workspaces
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(filter))
+                });
 
             if !retain {
                 continue;
@@ -204,13 +373,31 @@ impl<W: AsyncWrite + Unpin> App<W> {
                 Cow::Borrowed("application-x-executable")
             };
 
+            let mut description = info.title.clone();
+
+            if !workspaces.is_empty() {
+                description.push_str(" · Workspace ");
+                description.push_str(&workspaces.join(", "));
+            }
+
+            let outputs = self.output_names(info);
+
+            if !outputs.is_empty() {
+                description.push_str(" · ");
+                description.push_str(&outputs.join(", "));
+            }
+
+            let Some(key) = self.handles.key(&info.foreign_toplevel) else {
+                continue;
+            };
+
             let response = PluginResponse::Append(PluginSearchResult {
-                // XXX protocol id may be re-used later
-                id: info.foreign_toplevel.id().protocol_id(),
-                window: Some((0, info.foreign_toplevel.id().protocol_id())),
-                description: info.title.clone(),
+                id: handle_table::result_id(key),
+                window: Some(key),
+                description,
                 name: get_description(&entry, &self.locales),
                 icon: Some(IconSource::Name(icon_name)),
+                app_id: Some(entry.appid.clone()),
                 ..Default::default()
             });
 
@@ -218,6 +405,40 @@ impl<W: AsyncWrite + Unpin> App<W> {
         }
 
         send(&mut self.tx, PluginResponse::Finished).await;
-        let _ = self.tx.flush();
+        let _ = self.tx.flush().await;
+    }
+}
+
+/// Splits a query into its words and its `ws:NAME` filter, which limits the
+/// results to windows on that workspace.
+fn parse_query(query: &str) -> (Vec<&str>, Option<&str>) {
+    let mut workspace = None;
+
+    let words = query
+        .split_ascii_whitespace()
+        .filter(|word| match word.strip_prefix("ws:") {
+            Some(name) => {
+                workspace = Some(name).filter(|name| !name.is_empty());
+                false
+            }
+            None => true,
+        })
+        .collect();
+
+    (words, workspace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_query;
+
+    #[test]
+    fn workspace_filter() {
+        assert_eq!(parse_query("firefox"), (vec!["firefox"], None));
+        assert_eq!(parse_query("firefox ws:2"), (vec!["firefox"], Some("2")));
+        assert_eq!(parse_query("ws:web term"), (vec!["term"], Some("web")));
+        assert_eq!(parse_query("ws:1 ws:2"), (vec![], Some("2")));
+        assert_eq!(parse_query("ws: term"), (vec!["term"], None));
+        assert_eq!(parse_query("  "), (vec![], None));
     }
 }
@@ -4,13 +4,20 @@ use cctk::{
     cosmic_protocols,
     toplevel_info::{ToplevelInfo, ToplevelInfoHandler, ToplevelInfoState},
     toplevel_management::{ToplevelManagerHandler, ToplevelManagerState},
-    wayland_client::{self, WEnum},
-    wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    wayland_client::{self, Proxy, WEnum},
+    wayland_protocols::ext::{
+        foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+        workspace::v1::client::ext_workspace_handle_v1::ExtWorkspaceHandleV1,
+    },
+    workspace::{WorkspaceHandler, WorkspaceState},
 };
 use sctk::{
     self,
+    output::{OutputHandler, OutputState},
     reexports::{
-        calloop, calloop_wayland_source::WaylandSource, client::protocol::wl_seat::WlSeat,
+        calloop,
+        calloop_wayland_source::WaylandSource,
+        client::protocol::{wl_output::WlOutput, wl_seat::WlSeat},
     },
     seat::{SeatHandler, SeatState},
 };
@@ -24,15 +31,46 @@ use sctk::registry::{ProvidesRegistryState, RegistryState};
 use tracing::warn;
 use wayland_client::{Connection, QueueHandle, globals::registry_queue_init};
 
+use zcosmic_toplevel_manager_v1::ZcosmicToplelevelManagementCapabilitiesV1 as ManagerCapability;
+
+/// The first version of the toplevel manager with `move_to_ext_workspace`.
+const MOVE_TO_EXT_WORKSPACE_SINCE: u32 = 4;
+
 #[derive(Debug, Clone)]
 pub enum ToplevelAction {
     Activate(ExtForeignToplevelHandleV1),
     Close(ExtForeignToplevelHandleV1),
+    Fullscreen(ExtForeignToplevelHandleV1, bool),
+    Maximize(ExtForeignToplevelHandleV1, bool),
+    Minimize(ExtForeignToplevelHandleV1, bool),
+    MoveToWorkspace(ExtForeignToplevelHandleV1, ExtWorkspaceHandleV1, WlOutput),
+    Sticky(ExtForeignToplevelHandleV1, bool),
 }
 
 pub enum ToplevelUpdate {
     Info(ToplevelInfo),
     Remove(ExtForeignToplevelHandleV1),
+    Capabilities(Capabilities),
+    Output(WlOutput, String),
+    Workspaces(Vec<WorkspaceInfo>),
+}
+
+/// Window actions which the compositor supports, beyond activate and close.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capabilities {
+    pub fullscreen: bool,
+    pub maximize: bool,
+    pub minimize: bool,
+    pub move_to_workspace: bool,
+    pub sticky: bool,
+}
+
+/// A workspace, in the order the compositor lays them out on its outputs.
+#[derive(Debug, Clone)]
+pub struct WorkspaceInfo {
+    pub handle: ExtWorkspaceHandleV1,
+    pub name: String,
+    pub outputs: Vec<WlOutput>,
 }
 
 struct AppData {
@@ -42,6 +80,8 @@ struct AppData {
     toplevel_info_state: ToplevelInfoState,
     toplevel_manager_state: ToplevelManagerState,
     seat_state: SeatState,
+    output_state: OutputState,
+    workspace_state: WorkspaceState,
     pending_update: HashSet<ExtForeignToplevelHandleV1>,
 }
 
@@ -55,6 +95,51 @@ impl AppData {
             .cosmic_toplevel
             .as_ref()
     }
+
+    fn apply(&self, action: ToplevelAction) {
+        let manager = &self.toplevel_manager_state.manager;
+
+        let handle = match &action {
+            ToplevelAction::Activate(handle)
+            | ToplevelAction::Close(handle)
+            | ToplevelAction::Fullscreen(handle, _)
+            | ToplevelAction::Maximize(handle, _)
+            | ToplevelAction::Minimize(handle, _)
+            | ToplevelAction::MoveToWorkspace(handle, ..)
+            | ToplevelAction::Sticky(handle, _) => handle,
+        };
+
+        let Some(cosmic_toplevel) = self.cosmic_toplevel_for_foreign(handle) else {
+            return;
+        };
+
+        match action {
+            ToplevelAction::Activate(_) => {
+                // TODO Ashley how to choose the seat in a multi-seat setup?
+                for s in self.seat_state.seats() {
+                    manager.activate(cosmic_toplevel, &s);
+                }
+            }
+            ToplevelAction::Close(_) => manager.close(cosmic_toplevel),
+            ToplevelAction::Fullscreen(_, true) => manager.set_fullscreen(cosmic_toplevel, None),
+            ToplevelAction::Fullscreen(_, false) => manager.unset_fullscreen(cosmic_toplevel),
+            ToplevelAction::Maximize(_, true) => manager.set_maximized(cosmic_toplevel),
+            ToplevelAction::Maximize(_, false) => manager.unset_maximized(cosmic_toplevel),
+            ToplevelAction::Minimize(_, true) => manager.set_minimized(cosmic_toplevel),
+            ToplevelAction::Minimize(_, false) => manager.unset_minimized(cosmic_toplevel),
+            ToplevelAction::MoveToWorkspace(_, workspace, output) => {
+                manager.move_to_ext_workspace(cosmic_toplevel, &workspace, &output);
+            }
+            ToplevelAction::Sticky(_, true) => manager.set_sticky(cosmic_toplevel),
+            ToplevelAction::Sticky(_, false) => manager.unset_sticky(cosmic_toplevel),
+        }
+    }
+
+    fn send(&self, update: ToplevelUpdate) {
+        if let Err(err) = self.tx.unbounded_send(vec![update]) {
+            warn!("{err}");
+        }
+    }
 }
 
 impl ProvidesRegistryState for AppData {
@@ -93,6 +178,52 @@ impl SeatHandler for AppData {
     fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlSeat) {}
 }
 
+impl OutputHandler for AppData {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, conn: &Connection, qh: &QueueHandle<Self>, output: WlOutput) {
+        self.update_output(conn, qh, output);
+    }
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, output: WlOutput) {
+        if let Some(name) = self.output_state.info(&output).and_then(|info| info.name) {
+            self.send(ToplevelUpdate::Output(output, name));
+        }
+    }
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlOutput) {}
+}
+
+impl WorkspaceHandler for AppData {
+    fn workspace_state(&mut self) -> &mut WorkspaceState {
+        &mut self.workspace_state
+    }
+
+    fn done(&mut self) {
+        let mut workspaces = Vec::new();
+
+        for group in self.workspace_state.workspace_groups() {
+            let mut group_workspaces = group
+                .workspaces
+                .iter()
+                .filter_map(|handle| self.workspace_state.workspace_info(handle))
+                .collect::<Vec<_>>();
+
+            group_workspaces.sort_by(|a, b| a.coordinates.cmp(&b.coordinates));
+
+            workspaces.extend(group_workspaces.into_iter().map(|workspace| WorkspaceInfo {
+                handle: workspace.handle.clone(),
+                name: workspace.name.clone(),
+                outputs: group.outputs.clone(),
+            }));
+        }
+
+        self.send(ToplevelUpdate::Workspaces(workspaces));
+    }
+}
+
 impl ToplevelManagerHandler for AppData {
     fn toplevel_manager_state(&mut self) -> &mut cctk::toplevel_management::ToplevelManagerState {
         &mut self.toplevel_manager_state
@@ -102,8 +233,20 @@ impl ToplevelManagerHandler for AppData {
         &mut self,
         _: &Connection,
         _: &QueueHandle<Self>,
-        _: Vec<WEnum<zcosmic_toplevel_manager_v1::ZcosmicToplelevelManagementCapabilitiesV1>>,
+        capabilities: Vec<WEnum<ManagerCapability>>,
     ) {
+        let supports = |capability| capabilities.contains(&WEnum::Value(capability));
+
+        let capabilities = Capabilities {
+            fullscreen: supports(ManagerCapability::Fullscreen),
+            maximize: supports(ManagerCapability::Maximize),
+            minimize: supports(ManagerCapability::Minimize),
+            move_to_workspace: supports(ManagerCapability::MoveToWorkspace)
+                && self.toplevel_manager_state.manager.version() >= MOVE_TO_EXT_WORKSPACE_SINCE,
+            sticky: supports(ManagerCapability::Sticky),
+        };
+
+        self.send(ToplevelUpdate::Capabilities(capabilities));
     }
 }
 
@@ -169,23 +312,7 @@ pub(crate) fn toplevel_handler(
     handle.insert_source(wayland_source, |_, q, state| q.dispatch_pending(state))?;
 
     let _ = handle.insert_source(rx, |event, _, state| match event {
-        calloop::channel::Event::Msg(req) => match req {
-            ToplevelAction::Activate(handle) => {
-                let manager = &state.toplevel_manager_state.manager;
-                // TODO Ashley how to choose the seat in a multi-seat setup?
-                if let Some(cosmic_toplevel) = state.cosmic_toplevel_for_foreign(&handle) {
-                    for s in state.seat_state.seats() {
-                        manager.activate(cosmic_toplevel, &s);
-                    }
-                }
-            }
-            ToplevelAction::Close(handle) => {
-                let manager = &state.toplevel_manager_state.manager;
-                if let Some(cosmic_toplevel) = state.cosmic_toplevel_for_foreign(&handle) {
-                    manager.close(cosmic_toplevel);
-                }
-            }
-        },
+        calloop::channel::Event::Msg(req) => state.apply(req),
         calloop::channel::Event::Closed => {
             state.exit = true;
         }
@@ -196,6 +323,8 @@ pub(crate) fn toplevel_handler(
         exit: false,
         tx,
         seat_state: SeatState::new(&globals, &qh),
+        output_state: OutputState::new(&globals, &qh),
+        workspace_state: WorkspaceState::new(&registry_state, &qh),
         toplevel_info_state: ToplevelInfoState::new(&registry_state, &qh),
         toplevel_manager_state: ToplevelManagerState::new(&registry_state, &qh),
         registry_state,
@@ -211,6 +340,8 @@ pub(crate) fn toplevel_handler(
 }
 
 sctk::delegate_seat!(AppData);
+sctk::delegate_output!(AppData);
 sctk::delegate_registry!(AppData);
 cctk::delegate_toplevel_info!(AppData);
 cctk::delegate_toplevel_manager!(AppData);
+cctk::delegate_workspace!(AppData);
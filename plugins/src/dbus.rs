@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Method calls shared by the plugins which talk to services on D-Bus.
+
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::HashMap;
+use zbus::Connection;
+use zvariant::{OwnedValue, Type};
+
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+
+/// Calls a method, and deserializes the body of its reply.
+pub async fn call<A, R>(
+    connection: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    method: &str,
+    args: &A,
+) -> zbus::Result<R>
+where
+    A: Serialize + Type,
+    R: DeserializeOwned + Type,
+{
+    connection
+        .call_method(Some(destination), path, Some(interface), method, args)
+        .await?
+        .body()
+        .deserialize()
+}
+
+/// Fetches every property of an interface.
+pub async fn get_all(
+    connection: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+) -> zbus::Result<HashMap<String, OwnedValue>> {
+    call(
+        connection,
+        destination,
+        path,
+        PROPERTIES,
+        "GetAll",
+        &(interface,),
+    )
+    .await
+}
+
+/// Tests which serve mock services on a private bus need `dbus-daemon`, so
+/// they are ignored by default and run with `cargo test -- --ignored`.
+#[cfg(test)]
+pub mod testing {
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::{Child, Command};
+    use zbus::{Connection, connection::Builder};
+
+    /// A session bus private to a test, which is shut down once dropped.
+    pub struct PrivateBus {
+        address: String,
+        _daemon: Child,
+    }
+
+    impl PrivateBus {
+        pub async fn new() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .expect("dbus-daemon is not installed");
+
+            let stdout = daemon.stdout.take().unwrap();
+            let mut address = String::new();
+            BufReader::new(stdout)
+                .read_line(&mut address)
+                .await
+                .unwrap();
+
+            Self {
+                address: address.trim().to_owned(),
+                _daemon: daemon,
+            }
+        }
+
+        /// A builder for a connection to the bus, for serving mock services.
+        pub fn builder(&self) -> Builder<'static> {
+            Builder::address(self.address.as_str()).unwrap()
+        }
+
+        /// A connection to the bus, for the plugin under test.
+        pub async fn connect(&self) -> Connection {
+            self.builder().build().await.unwrap()
+        }
+    }
+}
@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Discovery of AppImages, whose desktop entries are embedded within them.
+
+use super::squashfs;
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Marks an AppImage whose extraction failed, so that it is not retried
+/// until the AppImage changes.
+const FAILED: &str = ".failed";
+
+/// Where the desktop entries extracted from AppImages are stored.
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".cache/pop-launcher/appimages"))
+}
+
+/// Desktop entries of the AppImages within the given directories, which are
+/// extracted from AppImages that are new or have changed since last time.
+///
+/// Entries are read from the images embedded in the AppImages, which are
+/// never run, and this blocks while reading them. Extracted entries of
+/// AppImages which no longer exist are removed from the cache.
+pub fn discover(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let Some(cache) = cache_dir() else {
+        return Vec::new();
+    };
+
+    let mut entries = Vec::new();
+    let mut discovered = HashSet::new();
+
+    for dir in dirs {
+        let Ok(read_dir) = dir.read_dir() else {
+            continue;
+        };
+
+        for appimage in read_dir.filter_map(Result::ok).map(|entry| entry.path()) {
+            if !appimage
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("appimage"))
+                || !is_executable(&appimage)
+            {
+                continue;
+            }
+
+            let Some(stem) = appimage.file_stem() else {
+                continue;
+            };
+
+            discovered.insert(stem.to_owned());
+            let dest = cache.join(stem);
+
+            if newer_than(&dest.join(FAILED), &appimage) {
+                continue;
+            }
+
+            let entry = find_desktop_file(&dest)
+                .filter(|entry| newer_than(entry, &appimage))
+                .or_else(|| extract(&appimage, &dest));
+
+            match entry {
+                Some(entry) => entries.push(entry),
+                None => {
+                    tracing::error!(
+                        "failed to extract desktop entry from {}",
+                        appimage.display()
+                    );
+
+                    let _ = fs::write(dest.join(FAILED), "");
+                }
+            }
+        }
+    }
+
+    prune(&cache, &discovered);
+
+    entries
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// Whether the file exists and was modified after the AppImage.
+fn newer_than(file: &Path, appimage: &Path) -> bool {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+
+    match (modified(file), modified(appimage)) {
+        (Some(file), Some(appimage)) => file >= appimage,
+        _ => false,
+    }
+}
+
+/// Removes what was extracted from AppImages which were not discovered.
+fn prune(cache: &Path, discovered: &HashSet<OsString>) {
+    let Ok(read_dir) = cache.read_dir() else {
+        return;
+    };
+
+    for entry in read_dir.filter_map(Result::ok) {
+        if !discovered.contains(&entry.file_name()) {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+fn find_desktop_file(dir: &Path) -> Option<PathBuf> {
+    dir.read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+}
+
+/// Extracts the desktop entry and icon at the root of the AppImage's image,
+/// and rewrites the entry to launch the AppImage.
+fn extract(appimage: &Path, dest: &Path) -> Option<PathBuf> {
+    let _ = fs::remove_dir_all(dest);
+    fs::create_dir_all(dest).ok()?;
+
+    let image = match squashfs::Image::open_appimage(appimage) {
+        Ok(image) => image,
+        Err(why) => {
+            tracing::debug!("cannot read the image of {}: {}", appimage.display(), why);
+            return None;
+        }
+    };
+
+    // Names are joined to the destination, so they must not contain paths.
+    let name = image
+        .root_names()
+        .ok()?
+        .into_iter()
+        .find(|name| name.ends_with(".desktop") && !name.contains('/'))?;
+
+    let input = String::from_utf8(image.read(&name).ok()?).ok()?;
+
+    let icon = input
+        .lines()
+        .find_map(|line| line.strip_prefix("Icon="))
+        .map(str::trim)
+        .filter(|icon| !icon.is_empty() && !icon.contains('/'))
+        .and_then(|icon| {
+            ["png", "svg"].into_iter().find_map(|ext| {
+                let name = [icon, ".", ext].concat();
+                let target = dest.join(&name);
+                fs::write(&target, image.read(&name).ok()?).ok()?;
+                Some(target)
+            })
+        });
+
+    let path = dest.join(&name);
+    fs::write(&path, rewrite(&input, appimage, icon.as_deref())).ok()?;
+
+    Some(path)
+}
+
+/// Points the Exec keys of the embedded entry at the AppImage, and its icon
+/// at the extracted icon.
+fn rewrite(input: &str, appimage: &Path, icon: Option<&Path>) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for line in input.lines() {
+        if line.starts_with("TryExec=") {
+            continue;
+        }
+
+        if let Some(exec) = line.strip_prefix("Exec=") {
+            let args = exec.trim().split_once(' ').map_or("", |(_, args)| args);
+            output.push_str("Exec=");
+            output.push_str(&quote(&appimage.to_string_lossy()));
+            if !args.is_empty() {
+                output.push(' ');
+                output.push_str(args);
+            }
+        } else if let Some(icon) = icon
+            && line.starts_with("Icon=")
+        {
+            output.push_str("Icon=");
+            output.push_str(&icon.to_string_lossy());
+        } else {
+            output.push_str(line);
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Quotes an argument of an Exec key, whose backslashes are escaped twice.
+fn quote(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' => {
+                quoted.push_str("\\\\");
+                quoted.push(c);
+            }
+            '\\' => quoted.push_str("\\\\\\\\"),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop_entries::squashfs::tests::{Node, appimage};
+
+    #[test]
+    fn entries_are_extracted_without_running() {
+        let dir =
+            std::env::temp_dir().join(format!("pop-launcher-appimage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("Editor.AppImage");
+        let dest = dir.join("cache/Editor");
+
+        let entry = "[Desktop Entry]\n\
+                     Name=Editor\n\
+                     TryExec=editor\n\
+                     Exec=editor %F\n\
+                     Icon=editor\n";
+
+        fs::write(
+            &path,
+            appimage(vec![
+                Node::Symlink("editor.desktop", "usr/share/applications/editor.desktop"),
+                Node::File("editor.png", b"\x89PNG"),
+                Node::Directory(
+                    "usr",
+                    vec![Node::Directory(
+                        "share",
+                        vec![Node::Directory(
+                            "applications",
+                            vec![Node::File("editor.desktop", entry.as_bytes())],
+                        )],
+                    )],
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let extracted = extract(&path, &dest);
+        let icon = dest.join("editor.png");
+        let written = extracted
+            .as_ref()
+            .map(|path| fs::read_to_string(path).unwrap());
+        let icon_data = fs::read(&icon).ok();
+
+        // Files which are not AppImages are not extracted, nor run.
+        let script = dir.join("Script.AppImage");
+        fs::write(&script, "#!/bin/sh\ntouch ran\n").unwrap();
+        let not_extracted = extract(&script, &dir.join("cache/Script"));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(extracted, Some(dest.join("editor.desktop")));
+        assert_eq!(
+            written.unwrap(),
+            format!(
+                "[Desktop Entry]\nName=Editor\nExec=\"{}\" %F\nIcon={}\n",
+                path.display(),
+                icon.display()
+            )
+        );
+        assert_eq!(icon_data.as_deref(), Some(&b"\x89PNG"[..]));
+        assert_eq!(not_extracted, None);
+    }
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// How an application was packaged.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Packaging {
+    Native,
+    Flatpak,
+    Snap,
+    Nix,
+    AppImage,
+}
+
+fn default_packaging() -> Vec<Packaging> {
+    vec![
+        Packaging::Native,
+        Packaging::Flatpak,
+        Packaging::Snap,
+        Packaging::Nix,
+        Packaging::AppImage,
+    ]
+}
+
+fn default_appimage_dirs() -> Vec<String> {
+    vec![String::from("~/Applications")]
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Config {
+    /// When an application is installed more than once, the variant whose
+    /// packaging comes first is shown. Unlisted packaging comes last.
+    #[serde(default = "default_packaging")]
+    pub packaging: Vec<Packaging>,
+
+    /// Directories which AppImages are discovered in.
+    #[serde(default = "default_appimage_dirs")]
+    pub appimage_dirs: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            packaging: default_packaging(),
+            appimage_dirs: default_appimage_dirs(),
+        }
+    }
+}
+
+impl Config {
+    /// Position of the packaging in the user's preference, where lower is preferred.
+    pub fn rank(&self, packaging: Packaging) -> usize {
+        self.packaging
+            .iter()
+            .position(|&p| p == packaging)
+            .unwrap_or(self.packaging.len())
+    }
+
+    pub fn appimage_dirs(&self) -> Vec<PathBuf> {
+        self.appimage_dirs
+            .iter()
+            .filter_map(|dir| match dir.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+                None => Some(PathBuf::from(dir)),
+            })
+            .collect()
+    }
+}
+
+pub fn load() -> Config {
+    pop_launcher::config::load("desktop_entries")
+}
@@ -3,14 +3,24 @@
 
 use crate::*;
 
+use config::{Config, Packaging};
 use freedesktop_desktop_entry as fde;
-use freedesktop_desktop_entry::DesktopEntry;
+use freedesktop_desktop_entry::{DesktopEntry, PathSource};
 use futures::StreamExt;
 use pop_launcher::*;
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, hash_map};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::io::AsyncWrite;
-use utils::{get_description, is_session_cosmic};
+use utils::{get_description, is_session_cosmic, source_name};
 
+mod appimage;
+mod config;
+mod packagekit;
+mod squashfs;
 pub(crate) mod utils;
 
 pub async fn main() {
@@ -43,43 +53,100 @@ const EXCLUSIONS: &[&str] = &["GNOME Shell", "Initial Setup"];
 struct App<W> {
     current_desktop: Option<Vec<String>>,
     is_desktop_cosmic: bool,
-    desktop_entries: Vec<DesktopEntry>,
+    desktop_entries: Vec<Arc<DesktopEntry>>,
+    /// Other installed variants of each desktop entry, by the index of the entry.
+    variants: Vec<Vec<Arc<DesktopEntry>>>,
+    /// Desktop actions by the index of their entry, and their ID.
+    desktop_actions: Vec<(usize, String)>,
+    /// Parsed desktop entries, which are reused when reloading.
+    entry_cache: EntryCache,
+    /// Changes to the desktop entry directories since the last reload.
+    changes: Option<flume::Receiver<HashSet<PathBuf>>>,
     locales: Vec<String>,
+    config: Config,
     tx: W,
-    gpus: Option<Vec<switcheroo_control::Gpu>>,
+    gpus: Option<Vec<launch::Gpu>>,
+    /// Entry whose uninstallation awaits confirmation from a follow-up context menu.
+    confirming: Option<u32>,
 }
 
 impl<W: AsyncWrite + Unpin> App<W> {
     fn new(tx: W) -> Self {
+        let config = config::load();
+        let watched = fde::default_paths().chain(config.appimage_dirs());
+
         Self {
             current_desktop: fde::current_desktop(),
             is_desktop_cosmic: is_session_cosmic(),
             desktop_entries: Vec::new(),
+            variants: Vec::new(),
+            desktop_actions: Vec::new(),
+            entry_cache: HashMap::new(),
+            changes: crate::watcher::watch(watched),
             locales: fde::get_languages_from_env(),
+            config,
             tx,
             gpus: None,
+            confirming: None,
         }
     }
 
     async fn reload(&mut self) {
+        self.reload_entries().await;
+        self.gpus = launch::gpus().await;
+    }
+
+    /// Reloads the desktop entries if their directories have changed since
+    /// the last reload, parsing only the entries which were changed.
+    async fn reload_changed(&mut self) {
+        let Some(changes) = self.changes.as_ref() else {
+            return;
+        };
+
+        if changes.try_iter().count() == 0 {
+            return;
+        }
+
+        self.reload_entries().await;
+    }
+
+    async fn reload_entries(&mut self) {
         self.desktop_entries.clear();
 
-        let mut deduplicator = std::collections::HashSet::new();
+        let mut deduplicator = HashSet::new();
         let locales = fde::get_languages_from_env();
 
+        // Entries which are no longer found are dropped from the cache.
+        let mut previous = std::mem::take(&mut self.entry_cache);
+
+        // AppImages are re-extracted when changed, so their entries are never cached.
+        let appimage_dirs = self.config.appimage_dirs();
+        let appimages = tokio::task::spawn_blocking(move || appimage::discover(&appimage_dirs))
+            .await
+            .unwrap_or_default();
+
         let paths = fde::Iter::new(fde::default_paths());
 
-        let desktop_entries = paths
-            .flat_map(|path| DesktopEntry::from_path(path, Some(&locales)))
+        let entries = paths
+            .filter_map(|path| load_entry(&mut previous, &mut self.entry_cache, path, &locales))
+            .chain(appimages.into_iter().filter_map(|path| {
+                Some(Arc::new(
+                    DesktopEntry::from_path(path, Some(&locales)).ok()?,
+                ))
+            }))
             .filter_map(|de| {
-                // Cache flatpak apps separately from non-flatpak apps.
-                let _flatpak_appid;
-                let appid = match de.flatpak() {
-                    Some(base_id) => {
-                        _flatpak_appid = [base_id, ".", de.appid.as_str()].concat();
-                        _flatpak_appid.as_str()
+                // Cache flatpak apps and AppImages separately from other apps.
+                let _prefixed_appid;
+                let appid = match (de.flatpak(), packaging(&de)) {
+                    (Some(base_id), _) => {
+                        _prefixed_appid = [base_id, ".", de.appid.as_str()].concat();
+                        _prefixed_appid.as_str()
+                    }
+                    (None, Packaging::AppImage) => {
+                        _prefixed_appid = ["appimage.", de.appid.as_str()].concat();
+                        _prefixed_appid.as_str()
                     }
-                    None => de.appid.as_str(),
+                    (None, _) => de.appid.as_str(),
                 };
 
                 if deduplicator.contains(appid) {
@@ -103,34 +170,33 @@ impl<W: AsyncWrite + Unpin> App<W> {
                 // Avoid showing the GNOME Shell entry entirely
                 if de
                     .name(&[] as &[&str])
-                    .map_or(false, |v| EXCLUSIONS.contains(&v.as_ref()))
+                    .is_some_and(|v| EXCLUSIONS.contains(&v.as_ref()))
                 {
                     return None;
                 }
 
                 // Do not show if our desktop is defined in `NotShowIn`.
-                if let Some(not_show_in) = de.not_show_in() {
-                    if let Some(current_desktop) = &self.current_desktop {
-                        if not_show_in.iter().any(|not_show| {
-                            current_desktop
-                                .iter()
-                                .any(|desktop| &not_show.to_ascii_lowercase() == desktop)
-                        }) {
-                            return None;
-                        }
-                    }
+                if let Some(not_show_in) = de.not_show_in()
+                    && let Some(current_desktop) = &self.current_desktop
+                    && not_show_in.iter().any(|not_show| {
+                        current_desktop
+                            .iter()
+                            .any(|desktop| &not_show.to_ascii_lowercase() == desktop)
+                    })
+                {
+                    return None;
                 }
 
                 // Do not show if our desktop is not defined in `OnlyShowIn`.
                 if let Some(only_show_in) = de.only_show_in() {
-                    if let Some(current_desktop) = &self.current_desktop {
-                        if !only_show_in.iter().any(|show_in| {
+                    if let Some(current_desktop) = &self.current_desktop
+                        && !only_show_in.iter().any(|show_in| {
                             current_desktop
                                 .iter()
                                 .any(|desktop| &show_in.to_ascii_lowercase() == desktop)
-                        }) {
-                            return None;
-                        }
+                        })
+                    {
+                        return None;
                     }
                 }
                 // Treat `OnlyShowIn` as an override otherwise do not show if `NoDisplay` is true
@@ -151,64 +217,129 @@ impl<W: AsyncWrite + Unpin> App<W> {
             })
             .collect::<Vec<_>>();
 
-        self.desktop_entries = desktop_entries;
+        let mut desktop_entries = Vec::new();
+
+        self.variants = group_variants(entries, &self.config)
+            .into_iter()
+            .map(|mut group| {
+                desktop_entries.push(group.remove(0));
+                group
+            })
+            .collect();
 
-        self.gpus = try_get_gpus().await;
+        self.desktop_actions = desktop_entries
+            .iter()
+            .enumerate()
+            .flat_map(|(id, entry)| {
+                let actions = entry.actions().unwrap_or_default();
+                actions
+                    .into_iter()
+                    .map(move |action| (id, action.to_owned()))
+            })
+            .collect();
+
+        self.desktop_entries = desktop_entries;
     }
 
     async fn activate(&mut self, id: u32) {
         if let Some(entry) = self.desktop_entries.get(id as usize) {
             let response = PluginResponse::DesktopEntry {
                 path: entry.path.to_path_buf(),
-                gpu_preference: if entry.prefers_non_default_gpu() {
-                    GpuPreference::NonDefault
-                } else {
-                    GpuPreference::Default
-                },
+                gpu_preference: default_gpu_preference(entry),
                 action_name: None,
             };
 
+            send(&mut self.tx, response).await;
+        } else if let Some((entry, action)) = self.desktop_action(id) {
+            let response = PluginResponse::DesktopEntry {
+                path: entry.path.to_path_buf(),
+                gpu_preference: default_gpu_preference(entry),
+                action_name: Some(action.to_owned()),
+            };
+
             send(&mut self.tx, response).await;
         }
     }
 
     async fn activate_context(&mut self, id: u32, context: u32) {
-        if let Some(entry) = self.desktop_entries.get(id as usize) {
-            let gpu_len = self.gpus.as_ref().map(Vec::len).unwrap_or(0) as u32;
-
-            let gpu_preference = if self.is_desktop_cosmic {
-                if context < gpu_len {
-                    GpuPreference::SpecificIdx(context)
-                } else if entry.prefers_non_default_gpu() {
-                    GpuPreference::NonDefault
-                } else {
-                    GpuPreference::Default
-                }
-            } else if !entry.prefers_non_default_gpu() {
-                GpuPreference::NonDefault
-            } else {
-                GpuPreference::Default
-            };
+        let Some(entry) = self.desktop_entries.get(id as usize) else {
+            return;
+        };
 
-            let response = PluginResponse::DesktopEntry {
-                path: entry.path.to_path_buf(),
-                gpu_preference,
-                action_name: (self.is_desktop_cosmic && context >= gpu_len).then(|| {
-                    entry.actions().unwrap_or_default()[(context - gpu_len) as usize].to_string()
-                }),
-            };
+        if (CONFIRM_UNINSTALL..=CANCEL_UNINSTALL).contains(&context) {
+            if self.confirming.take() == Some(id) && context == CONFIRM_UNINSTALL {
+                let entry = entry.clone();
+                send(&mut self.tx, PluginResponse::Close).await;
+                uninstall(&entry);
+            }
 
-            send(&mut self.tx, response).await;
+            return;
         }
+
+        self.confirming = None;
+
+        let Some(item) = self
+            .context_items(id as usize, entry)
+            .into_iter()
+            .nth(context as usize)
+        else {
+            return;
+        };
+
+        let (entry, gpu_preference, action_name) = match item.action {
+            ContextAction::Gpu(gpu_preference) => (entry, gpu_preference, None),
+            ContextAction::DesktopAction(action) => {
+                (entry, default_gpu_preference(entry), Some(action))
+            }
+            ContextAction::Variant(variant) => {
+                let Some(variant) = self.variants[id as usize].get(variant) else {
+                    return;
+                };
+
+                (variant, default_gpu_preference(variant), None)
+            }
+            ContextAction::AppInfo => {
+                let id = entry.flatpak().unwrap_or(&entry.appid);
+                crate::xdg_open(["appstream://", id].concat());
+                send(&mut self.tx, PluginResponse::Close).await;
+                return;
+            }
+            ContextAction::Uninstall => {
+                let name = entry.name(&self.locales).unwrap_or_default();
+
+                let options = vec![
+                    ContextOption {
+                        id: CONFIRM_UNINSTALL,
+                        name: format!("Uninstall {name}"),
+                        ..Default::default()
+                    },
+                    ContextOption {
+                        id: CANCEL_UNINSTALL,
+                        name: String::from("Cancel"),
+                        ..Default::default()
+                    },
+                ];
+
+                self.confirming = Some(id);
+                send(&mut self.tx, PluginResponse::Context { id, options }).await;
+                return;
+            }
+        };
+
+        let response = PluginResponse::DesktopEntry {
+            path: entry.path.to_path_buf(),
+            gpu_preference,
+            action_name,
+        };
+
+        send(&mut self.tx, response).await;
     }
 
     async fn context(&mut self, id: u32) {
+        self.confirming = None;
+
         if let Some(entry) = self.desktop_entries.get(id as usize) {
-            let options = if self.is_desktop_cosmic {
-                self.cosmic_context(entry).await
-            } else {
-                self.gnome_context(entry).await
-            };
+            let options = self.context_options(id as usize, entry);
 
             if !options.is_empty() {
                 let response = PluginResponse::Context { id, options };
@@ -218,13 +349,25 @@ impl<W: AsyncWrite + Unpin> App<W> {
         }
     }
 
+    /// Desktop actions follow the desktop entries in the IDs of search results.
+    fn desktop_action(&self, id: u32) -> Option<(&DesktopEntry, &str)> {
+        let (entry, action) = self
+            .desktop_actions
+            .get((id as usize).checked_sub(self.desktop_entries.len())?)?;
+
+        Some((self.desktop_entries.get(*entry)?, action))
+    }
+
     async fn search(&mut self, query: &str) {
+        self.confirming = None;
+        self.reload_changed().await;
+
         let query = query.to_ascii_lowercase();
 
         let &mut Self {
             ref desktop_entries,
+            ref desktop_actions,
             ref locales,
-            ref mut tx,
             ..
         } = self;
 
@@ -233,113 +376,699 @@ impl<W: AsyncWrite + Unpin> App<W> {
         for (id, entry) in desktop_entries.iter().enumerate() {
             let name = entry.name(locales).unwrap_or_default();
             let keywords = entry.keywords(locales);
+            let generic_name = entry.generic_name(locales);
+            let categories = entry.categories().unwrap_or_default();
+
+            // The untranslated name, and the app ID of Flatpak apps.
+            let mut alt_names = Vec::new();
+            if let Some(untranslated) = entry.name(&[] as &[&str])
+                && untranslated != name
+            {
+                alt_names.push(untranslated.into_owned());
+            }
+            if let Some(app_id) = entry.flatpak() {
+                alt_names.push(app_id.to_owned());
+            }
+
             items.extend(name.split_ascii_whitespace().map(ToOwned::to_owned));
 
             if let Some(keywords) = keywords.as_ref() {
                 items.extend(keywords.iter().map(|x| String::from(x.as_ref())));
             }
 
+            for alt_name in &alt_names {
+                items.extend(alt_name.split_ascii_whitespace().map(ToOwned::to_owned));
+            }
+
             if let Some(exec) = entry.exec() {
                 items.push(exec.to_owned());
             }
 
-            for search_interest in items.drain(..) {
-                let search_interest = search_interest.to_ascii_lowercase();
-                let append = search_interest.starts_with(&*query)
-                    || query
-                        .split_ascii_whitespace()
-                        .any(|query| search_interest.contains(&*query))
-                    || strsim::jaro_winkler(&*query, &*search_interest) > 0.6;
-
-                if append {
-                    let response = PluginResponse::Append(PluginSearchResult {
-                        id: id as u32,
-                        name: entry.name(&self.locales).unwrap_or_default().to_string(),
-                        description: get_description(entry, &self.locales),
-                        keywords: entry
-                            .keywords(&self.locales)
-                            .map(|v| v.iter().map(|e| e.to_string()).collect()),
-                        icon: entry
-                            .icon()
-                            .map(|e| Cow::Owned(e.to_string()))
-                            .map(IconSource::Name),
-                        exec: entry.exec().map(|e| e.to_string()),
-                        ..Default::default()
-                    });
+            let described = described_by(&query, generic_name.as_deref(), &categories);
+
+            if items.drain(..).any(|interest| is_match(&query, &interest)) || described {
+                let response = PluginResponse::Append(PluginSearchResult {
+                    id: id as u32,
+                    name: name.to_string(),
+                    description: get_description(entry, &self.locales),
+                    alt_names: (!alt_names.is_empty()).then_some(alt_names),
+                    keywords: search_keywords(keywords, generic_name, &categories),
+                    icon: entry
+                        .icon()
+                        .map(|e| Cow::Owned(e.to_string()))
+                        .map(IconSource::Name),
+                    exec: entry.exec().map(|e| e.to_string()),
+                    app_id: Some(entry.appid.clone()),
+                    alternate: self.alternate(entry),
+                    ..Default::default()
+                });
 
-                    send(tx, response).await;
+                send(&mut self.tx, response).await;
+            }
+        }
 
-                    break;
-                }
+        for (id, (entry, action)) in desktop_actions.iter().enumerate() {
+            let entry = &desktop_entries[*entry];
+
+            let Some(name) = entry.action_name(action, locales) else {
+                continue;
+            };
+
+            let app_name = entry.name(locales).unwrap_or_default();
+
+            if action_matches(&query, &app_name, &name) {
+                let response = PluginResponse::Append(PluginSearchResult {
+                    id: (desktop_entries.len() + id) as u32,
+                    name: name.into_owned(),
+                    description: app_name.into_owned(),
+                    icon: entry
+                        .icon()
+                        .map(|e| Cow::Owned(e.to_string()))
+                        .map(IconSource::Name),
+                    ..Default::default()
+                });
+
+                send(&mut self.tx, response).await;
             }
         }
 
         send(&mut self.tx, PluginResponse::Finished).await;
-        let _ = self.tx.flush();
-    }
-
-    async fn gnome_context(&self, entry: &DesktopEntry) -> Vec<ContextOption> {
-        if self.gpus.is_some() {
-            vec![ContextOption {
-                id: 0,
-                name: (if entry.prefers_non_default_gpu() {
-                    "Launch Using Integrated Graphics Card"
-                } else {
-                    "Launch Using Discrete Graphics Card"
-                })
-                .to_owned(),
-            }]
-        } else {
-            Vec::new()
+        let _ = self.tx.flush().await;
+    }
+
+    /// Context options of an entry, where launching with the GPU that the
+    /// entry does not prefer is the alternate action.
+    fn context_options(&self, id: usize, entry: &DesktopEntry) -> Vec<ContextOption> {
+        self.context_items(id, entry)
+            .into_iter()
+            .enumerate()
+            .map(|(id, item)| context_option(id, item))
+            .collect()
+    }
+
+    /// The alternate action of an entry, without building the rest of its
+    /// context menu, which the GPU options lead.
+    fn alternate(&self, entry: &DesktopEntry) -> Option<ContextOption> {
+        self.gpu_items(entry)
+            .into_iter()
+            .enumerate()
+            .find(|(_, item)| item.alternate)
+            .map(|(id, item)| context_option(id, item))
+    }
+
+    /// Items of an entry's context menu, whose positions are the IDs of their options.
+    fn context_items(&self, id: usize, entry: &DesktopEntry) -> Vec<ContextItem> {
+        let mut items = self.gpu_items(entry);
+
+        items.extend(package_items(
+            entry,
+            if self.is_desktop_cosmic {
+                "App info"
+            } else {
+                "App Info"
+            },
+        ));
+
+        for action in entry.actions().unwrap_or_default() {
+            let name = entry
+                .action_name(action, &self.locales)
+                .map_or_else(|| action.to_owned(), Cow::into_owned);
+
+            items.push(ContextItem {
+                action: ContextAction::DesktopAction(action.to_owned()),
+                name,
+                alternate: false,
+            });
+        }
+
+        for (variant, de) in self.variants[id].iter().enumerate() {
+            items.push(ContextItem {
+                action: ContextAction::Variant(variant),
+                name: format!("Launch {} Version", source_name(de)),
+                alternate: false,
+            });
         }
+
+        items
     }
 
-    async fn cosmic_context(&self, entry: &DesktopEntry) -> Vec<ContextOption> {
-        let mut options = Vec::new();
+    /// Items which launch the entry with a GPU, if there is more than one.
+    fn gpu_items(&self, entry: &DesktopEntry) -> Vec<ContextItem> {
+        let Some(gpus) = self.gpus.as_ref() else {
+            return Vec::new();
+        };
 
-        if let Some(gpus) = self.gpus.as_ref() {
-            let default_idx = if entry.prefers_non_default_gpu() {
-                gpus.iter().position(|gpu| !gpu.default).unwrap_or(0)
+        if !self.is_desktop_cosmic {
+            let (gpu_preference, name) = if entry.prefers_non_default_gpu() {
+                (
+                    GpuPreference::Default,
+                    "Launch Using Integrated Graphics Card",
+                )
             } else {
-                gpus.iter().position(|gpu| gpu.default).unwrap_or(0)
+                (
+                    GpuPreference::NonDefault,
+                    "Launch Using Discrete Graphics Card",
+                )
             };
-            for (i, gpu) in gpus.iter().enumerate() {
-                options.push(ContextOption {
-                    id: i as u32,
-                    name: format!(
-                        "Launch using {}{}",
-                        gpu.name,
-                        if i == default_idx { " (default)" } else { "" }
-                    ),
-                });
-            }
+
+            return vec![ContextItem {
+                action: ContextAction::Gpu(gpu_preference),
+                name: name.to_owned(),
+                alternate: true,
+            }];
         }
 
-        let options_offset = self.gpus.as_ref().map(|gpus| gpus.len()).unwrap_or(0);
-        for (i, action) in entry.actions().unwrap_or_default().iter().enumerate() {
-            options.push(ContextOption {
-                id: (i + options_offset) as u32,
-                name: action.to_string(),
+        let prefers_non_default = entry.prefers_non_default_gpu();
+        let default_idx = if prefers_non_default {
+            gpus.iter().position(|gpu| !gpu.default).unwrap_or(0)
+        } else {
+            gpus.iter().position(|gpu| gpu.default).unwrap_or(0)
+        };
+        let alternate_idx = gpus
+            .iter()
+            .position(|gpu| gpu.default == prefers_non_default);
+
+        gpus.iter()
+            .enumerate()
+            .map(|(i, gpu)| ContextItem {
+                action: ContextAction::Gpu(GpuPreference::SpecificIdx(i as u32)),
+                name: format!(
+                    "Launch using {}{}",
+                    gpu.name,
+                    if i == default_idx { " (default)" } else { "" }
+                ),
+                alternate: Some(i) == alternate_idx,
+            })
+            .collect()
+    }
+}
+
+fn context_option(id: usize, item: ContextItem) -> ContextOption {
+    ContextOption {
+        id: id as u32,
+        name: item.name,
+        accelerator: item.alternate.then(|| String::from("Shift+Return")),
+        default_alternate: item.alternate,
+    }
+}
+
+/// What activating an option from the context menu of a desktop entry does.
+enum ContextAction {
+    /// Launch the entry with the given GPU.
+    Gpu(GpuPreference),
+    /// Launch the desktop action with the given ID.
+    DesktopAction(String),
+    /// Launch another installed variant of the entry, by its position.
+    Variant(usize),
+    /// Open the entry's page in the software center.
+    AppInfo,
+    /// Ask to confirm the uninstallation of the entry.
+    Uninstall,
+}
+
+struct ContextItem {
+    action: ContextAction,
+    name: String,
+    /// Whether this is the alternate action of the entry.
+    alternate: bool,
+}
+
+fn default_gpu_preference(entry: &DesktopEntry) -> GpuPreference {
+    if entry.prefers_non_default_gpu() {
+        GpuPreference::NonDefault
+    } else {
+        GpuPreference::Default
+    }
+}
+
+/// Options of the context menu which confirms an uninstallation, whose IDs
+/// never collide with the options of an entry, and precede those which are
+/// reserved for the service.
+const CONFIRM_UNINSTALL: u32 = SERVICE_CONTEXT_OFFSET - 2;
+const CANCEL_UNINSTALL: u32 = SERVICE_CONTEXT_OFFSET - 1;
+
+/// Options for the software center and uninstallation of apps from Flatpak
+/// and system packages.
+fn package_items(entry: &DesktopEntry, app_info: &str) -> Vec<ContextItem> {
+    match PathSource::guess_from(&entry.path) {
+        PathSource::LocalFlatpak | PathSource::SystemFlatpak | PathSource::System => vec![
+            ContextItem {
+                action: ContextAction::AppInfo,
+                name: app_info.to_owned(),
+                alternate: false,
+            },
+            ContextItem {
+                action: ContextAction::Uninstall,
+                name: String::from("Uninstall"),
+                alternate: false,
+            },
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Uninstalls a Flatpak app with `flatpak`, and system packages through PackageKit.
+fn uninstall(entry: &DesktopEntry) {
+    let path = entry.path.clone();
+
+    let scope = match PathSource::guess_from(&path) {
+        PathSource::LocalFlatpak => "--user",
+        PathSource::SystemFlatpak => "--system",
+        _ => {
+            tokio::spawn(async move {
+                if let Err(why) = packagekit::uninstall(&path).await {
+                    tracing::error!("failed to uninstall {}: {}", path.display(), why);
+                }
             });
+
+            return;
+        }
+    };
+
+    let app_id = entry.flatpak().unwrap_or(&entry.appid).to_owned();
+
+    tokio::spawn(async move {
+        let status = tokio::process::Command::new("flatpak")
+            .args(["uninstall", "--noninteractive", scope, &app_id])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status()
+            .await;
+
+        match status {
+            Ok(status) if status.success() => (),
+            Ok(status) => tracing::error!("failed to uninstall {}: {}", app_id, status),
+            Err(why) => tracing::error!("failed to run flatpak: {}", why),
+        }
+    });
+}
+
+fn packaging(entry: &DesktopEntry) -> Packaging {
+    if appimage::cache_dir().is_some_and(|dir| entry.path.starts_with(dir)) {
+        return Packaging::AppImage;
+    }
+
+    match PathSource::guess_from(&entry.path) {
+        PathSource::LocalFlatpak | PathSource::SystemFlatpak => Packaging::Flatpak,
+        PathSource::SystemSnap => Packaging::Snap,
+        PathSource::LocalNix | PathSource::Nix => Packaging::Nix,
+        _ => Packaging::Native,
+    }
+}
+
+/// Parsed desktop entries by the files that their paths resolve to, and
+/// when those files were modified.
+type EntryCache = HashMap<PathBuf, (SystemTime, Arc<DesktopEntry>)>;
+
+/// Moves the entry at the path from the previous cache into the cache, or
+/// parses it if its file has changed since. Entries are cached by the files
+/// that their paths resolve to, so that the entries of Flatpak apps, which
+/// are symlinks into their current deployment, are parsed again once their
+/// targets change.
+fn load_entry(
+    previous: &mut EntryCache,
+    cache: &mut EntryCache,
+    path: PathBuf,
+    locales: &[String],
+) -> Option<Arc<DesktopEntry>> {
+    let file = std::fs::canonicalize(&path).ok()?;
+    let modified = file.metadata().and_then(|m| m.modified()).ok()?;
+
+    let de = match previous.remove(&file) {
+        Some((cached, de)) if cached == modified => de,
+        _ => Arc::new(DesktopEntry::from_path(path, Some(locales)).ok()?),
+    };
+
+    cache.insert(file, (modified, de.clone()));
+    Some(de)
+}
+
+/// Groups the entries of apps which are installed more than once, where
+/// the variant whose packaging is preferred leads its group.
+fn group_variants(entries: Vec<Arc<DesktopEntry>>, config: &Config) -> Vec<Vec<Arc<DesktopEntry>>> {
+    let mut groups: Vec<Vec<Arc<DesktopEntry>>> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for de in entries {
+        match positions.entry(app_key(&de)) {
+            hash_map::Entry::Occupied(entry) => groups[*entry.get()].push(de),
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(groups.len());
+                groups.push(vec![de]);
+            }
         }
+    }
 
-        options
+    for group in &mut groups {
+        group.sort_by_key(|de| config.rank(packaging(de)));
     }
+
+    groups
 }
 
-async fn try_get_gpus() -> Option<Vec<switcheroo_control::Gpu>> {
-    let connection = zbus::Connection::system().await.ok()?;
-    let proxy = switcheroo_control::SwitcherooControlProxy::new(&connection)
-        .await
-        .ok()?;
+/// Identifies an app across its packaging formats: by the ID of a Flatpak
+/// app, by the name of a snap for its main app, and otherwise by the name
+/// of its desktop file.
+fn app_key(entry: &DesktopEntry) -> String {
+    if let Some(app_id) = entry.flatpak() {
+        return app_id.to_owned();
+    }
+
+    // Snaps are named `<snap>_<app>`, where the main app is named after the snap.
+    if packaging(entry) == Packaging::Snap
+        && let Some((snap, app)) = entry.appid.split_once('_')
+        && snap == app
+    {
+        return snap.to_owned();
+    }
+
+    entry.appid.clone()
+}
 
-    if !proxy.has_dual_gpu().await.ok()? {
-        return None;
+/// Whether the query describes the app by its generic name or categories,
+/// which are only matched by the words that they start with.
+fn described_by(query: &str, generic_name: Option<&str>, categories: &[&str]) -> bool {
+    if query.is_empty() {
+        return false;
     }
 
-    let gpus = proxy.get_gpus().await.ok()?;
-    if gpus.is_empty() {
-        return None;
+    let generic_name = generic_name.unwrap_or_default().to_ascii_lowercase();
+
+    generic_name.starts_with(query)
+        || generic_name
+            .split_ascii_whitespace()
+            .any(|word| word.starts_with(query))
+        || categories
+            .iter()
+            .any(|category| category.to_ascii_lowercase().starts_with(query))
+}
+
+/// The keywords of an entry, followed by its generic name and categories,
+/// which the service weighs less by coming later.
+fn search_keywords(
+    keywords: Option<Vec<Cow<'_, str>>>,
+    generic_name: Option<Cow<'_, str>>,
+    categories: &[&str],
+) -> Option<Vec<String>> {
+    let keywords: Vec<String> = keywords
+        .into_iter()
+        .flatten()
+        .chain(generic_name)
+        .map(Cow::into_owned)
+        .chain(categories.iter().map(|&category| category.to_owned()))
+        .collect();
+
+    (!keywords.is_empty()).then_some(keywords)
+}
+
+/// Whether the words of the query are found within the name of the action,
+/// or the name of its app followed by the action, such as `firefox private`.
+fn action_matches(query: &str, app_name: &str, action_name: &str) -> bool {
+    let app_name = app_name.to_ascii_lowercase();
+    let action_name = action_name.to_ascii_lowercase();
+    let mut in_action = false;
+
+    query.split_ascii_whitespace().all(|word| {
+        if action_name.contains(word) {
+            in_action = true;
+            true
+        } else {
+            app_name.contains(word)
+        }
+    }) && in_action
+}
+
+fn is_match(query: &str, search_interest: &str) -> bool {
+    let search_interest = search_interest.to_ascii_lowercase();
+    search_interest.starts_with(query)
+        || query
+            .split_ascii_whitespace()
+            .any(|query| search_interest.contains(query))
+        || strsim::jaro_winkler(query, &search_interest) > 0.6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, extra: &str) -> Arc<DesktopEntry> {
+        let input = format!("[Desktop Entry]\nType=Application\nName=App\nExec=app\n{extra}");
+        Arc::new(DesktopEntry::from_str(path, &input, None::<&[&str]>).unwrap())
+    }
+
+    fn native() -> Arc<DesktopEntry> {
+        entry("/usr/share/applications/org.mozilla.firefox.desktop", "")
+    }
+
+    fn flatpak() -> Arc<DesktopEntry> {
+        entry(
+            "/var/lib/flatpak/exports/share/applications/org.mozilla.firefox.desktop",
+            "X-Flatpak=org.mozilla.firefox\n",
+        )
+    }
+
+    fn snap() -> Arc<DesktopEntry> {
+        entry(
+            "/var/lib/snapd/desktop/applications/firefox_firefox.desktop",
+            "",
+        )
+    }
+
+    fn app(entries: Vec<Arc<DesktopEntry>>) -> App<Vec<u8>> {
+        App {
+            current_desktop: None,
+            is_desktop_cosmic: false,
+            variants: vec![Vec::new(); entries.len()],
+            desktop_entries: entries,
+            desktop_actions: Vec::new(),
+            entry_cache: HashMap::new(),
+            changes: None,
+            locales: Vec::new(),
+            config: Config::default(),
+            tx: Vec::new(),
+            gpus: None,
+            confirming: None,
+        }
+    }
+
+    fn responses(app: &mut App<Vec<u8>>) -> Vec<PluginResponse> {
+        let output = std::mem::take(&mut app.tx);
+        serde_json::Deserializer::from_slice(&output)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn context_ids(response: &PluginResponse) -> Vec<u32> {
+        match response {
+            PluginResponse::Context { options, .. } => options.iter().map(|o| o.id).collect(),
+            _ => panic!("expected a context menu"),
+        }
+    }
+
+    #[tokio::test]
+    async fn uninstall_is_confirmed() {
+        let mut app = app(vec![flatpak()]);
+
+        // App Info, followed by Uninstall.
+        app.context(0).await;
+        assert_eq!(context_ids(&responses(&mut app)[0]), [0, 1]);
+
+        app.activate_context(0, 1).await;
+        let confirmation = responses(&mut app);
+        assert_eq!(
+            context_ids(&confirmation[0]),
+            [CONFIRM_UNINSTALL, CANCEL_UNINSTALL]
+        );
+        assert_eq!(app.confirming, Some(0));
+
+        // Cancelling sends nothing, and a confirmation is no longer expected.
+        app.activate_context(0, CANCEL_UNINSTALL).await;
+        assert!(responses(&mut app).is_empty());
+        assert_eq!(app.confirming, None);
+
+        // Confirming after searching again does nothing.
+        app.activate_context(0, 1).await;
+        responses(&mut app);
+        app.search("firefox").await;
+        responses(&mut app);
+        assert_eq!(app.confirming, None);
+
+        app.activate_context(0, CONFIRM_UNINSTALL).await;
+        assert!(responses(&mut app).is_empty());
+
+        // Options of the entry are not mistaken for confirmations.
+        app.activate_context(0, 1).await;
+        responses(&mut app);
+        app.activate_context(0, 1).await;
+        assert_eq!(
+            context_ids(&responses(&mut app)[0]),
+            [CONFIRM_UNINSTALL, CANCEL_UNINSTALL]
+        );
+    }
+
+    #[test]
+    fn entries_are_parsed_again_once_changed() {
+        let dir = std::env::temp_dir().join(format!(
+            "pop-launcher-desktop-entries-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let write = |file: &str, name: &str| {
+            let path = dir.join(file);
+            std::fs::write(&path, format!("[Desktop Entry]\nName={name}\nExec=app\n")).unwrap();
+            path
+        };
+
+        let set_modified = |path: &PathBuf, modified: SystemTime| {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        let load = |previous: &mut EntryCache, path: &PathBuf| {
+            let mut cache = EntryCache::new();
+            let de = load_entry(previous, &mut cache, path.clone(), &[]).unwrap();
+            *previous = cache;
+            de
+        };
+
+        let name = |de: &DesktopEntry| de.name(&[] as &[&str]).unwrap().into_owned();
+
+        let mut cache = EntryCache::new();
+        let app = write("app.desktop", "App");
+        let first = load(&mut cache, &app);
+        assert!(Arc::ptr_eq(&first, &load(&mut cache, &app)));
+
+        // Modified files are parsed again.
+        write("app.desktop", "Renamed");
+        set_modified(&app, SystemTime::now() + std::time::Duration::from_secs(10));
+        assert_eq!(name(&load(&mut cache, &app)), "Renamed");
+
+        // Symlinks are parsed again once retargeted, even to a file which
+        // was modified at the same time.
+        let v1 = write("v1.desktop", "Version 1");
+        let v2 = write("v2.desktop", "Version 2");
+        set_modified(&v2, std::fs::metadata(&v1).unwrap().modified().unwrap());
+
+        let link = dir.join("link.desktop");
+        std::os::unix::fs::symlink(&v1, &link).unwrap();
+        let linked = load(&mut cache, &link);
+        assert_eq!(name(&linked), "Version 1");
+        assert_eq!(linked.path, link);
+
+        std::fs::remove_file(&link).unwrap();
+        std::os::unix::fs::symlink(&v2, &link).unwrap();
+        assert_eq!(name(&load(&mut cache, &link)), "Version 2");
+
+        // Entries which are gone are not loaded.
+        std::fs::remove_file(&link).unwrap();
+        assert!(load_entry(&mut cache, &mut EntryCache::new(), link, &[]).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn app_keys() {
+        assert_eq!(app_key(&native()), "org.mozilla.firefox");
+        assert_eq!(app_key(&flatpak()), "org.mozilla.firefox");
+        assert_eq!(app_key(&snap()), "firefox");
+
+        let snap_app = entry(
+            "/var/lib/snapd/desktop/applications/libreoffice_writer.desktop",
+            "",
+        );
+        assert_eq!(app_key(&snap_app), "libreoffice_writer");
+
+        // Apps which merely share the last segment of their IDs are distinct.
+        let other = entry("/usr/share/applications/org.example.firefox.desktop", "");
+        assert_eq!(app_key(&other), "org.example.firefox");
+    }
+
+    #[test]
+    fn described_by_generic_name_and_categories() {
+        let categories = ["Graphics", "RasterGraphics"];
+        let described = |query| described_by(query, Some("Image Editor"), &categories);
+
+        assert!(described("image editor"));
+        assert!(described("edit"));
+        assert!(described("raster"));
+
+        // Neither fuzzily, nor by the middle of a word.
+        assert!(!described(""));
+        assert!(!described("imgae"));
+        assert!(!described("ditor"));
+        assert!(!described("phics"));
+
+        assert_eq!(
+            search_keywords(
+                Some(vec![Cow::Borrowed("gimp")]),
+                Some(Cow::Borrowed("Image Editor")),
+                &categories
+            ),
+            Some(vec![
+                String::from("gimp"),
+                String::from("Image Editor"),
+                String::from("Graphics"),
+                String::from("RasterGraphics"),
+            ])
+        );
+        assert_eq!(search_keywords(None, None, &[]), None);
+    }
+
+    #[test]
+    fn actions_match_by_name() {
+        let matches = |query| action_matches(query, "Firefox", "New Private Window");
+
+        assert!(matches("private"));
+        assert!(matches("new win"));
+        assert!(matches("firefox priv"));
+        assert!(matches("firefox new private window"));
+
+        // Neither an empty query, the app's name alone, nor fuzzy matches.
+        assert!(!matches(""));
+        assert!(!matches("firefox"));
+        assert!(!matches("privte"));
+        assert!(!matches("chrome private"));
+    }
+
+    #[test]
+    fn variants_are_grouped_by_preference() {
+        let firefox = entry("/usr/share/applications/firefox.desktop", "");
+        let entries = vec![flatpak(), snap(), firefox.clone(), native()];
+
+        let groups = group_variants(entries.clone(), &Config::default());
+        let paths = |groups: &[Vec<Arc<DesktopEntry>>]| -> Vec<Vec<PathBuf>> {
+            groups
+                .iter()
+                .map(|group| group.iter().map(|de| de.path.clone()).collect())
+                .collect()
+        };
+
+        assert_eq!(
+            paths(&groups),
+            vec![
+                vec![native().path.clone(), flatpak().path.clone()],
+                vec![firefox.path.clone(), snap().path.clone()],
+            ]
+        );
+
+        let config = Config {
+            packaging: vec![Packaging::Snap, Packaging::Flatpak],
+            ..Config::default()
+        };
+
+        assert_eq!(
+            paths(&group_variants(entries, &config)),
+            vec![
+                vec![flatpak().path.clone(), native().path.clone()],
+                vec![snap().path.clone(), firefox.path.clone()],
+            ]
+        );
     }
-    Some(gpus)
 }
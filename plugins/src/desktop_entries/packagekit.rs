@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Removal of system packages through PackageKit.
+
+use futures::StreamExt;
+use serde::Serialize;
+use std::path::Path;
+use zbus::{Connection, Proxy};
+use zvariant::{OwnedObjectPath, Type};
+
+const DEST: &str = "org.freedesktop.PackageKit";
+const PATH: &str = "/org/freedesktop/PackageKit";
+const TRANSACTION: &str = "org.freedesktop.PackageKit.Transaction";
+
+/// Only packages which are installed.
+const FILTER_INSTALLED: u64 = 1 << 2;
+
+/// Only packages from trusted repositories.
+const FLAG_ONLY_TRUSTED: u64 = 1 << 1;
+
+/// Uninstalls the package which owns the file, where PackageKit asks the
+/// user for authorization.
+pub async fn uninstall(file: &Path) -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let file = file.to_string_lossy().into_owned();
+
+    let packages = transaction(&connection, "SearchFiles", &(FILTER_INSTALLED, vec![file])).await?;
+
+    if packages.is_empty() {
+        return Err(zbus::Error::Failure(String::from(
+            "no installed package owns the desktop entry",
+        )));
+    }
+
+    transaction(
+        &connection,
+        "RemovePackages",
+        &(FLAG_ONLY_TRUSTED, packages, false, false),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Runs a method on a new transaction, returning the IDs of the packages it reported.
+async fn transaction<A: Serialize + Type>(
+    connection: &Connection,
+    method: &str,
+    args: &A,
+) -> zbus::Result<Vec<String>> {
+    let path: OwnedObjectPath = connection
+        .call_method(Some(DEST), PATH, Some(DEST), "CreateTransaction", &())
+        .await?
+        .body()
+        .deserialize()?;
+
+    let proxy = Proxy::new(connection, DEST, path.as_str(), TRANSACTION).await?;
+
+    // Signals are received in the order they were emitted only through a single stream.
+    let mut signals = proxy.receive_all_signals().await?;
+
+    proxy.call_method(method, args).await?;
+
+    let mut packages = Vec::new();
+
+    while let Some(message) = signals.next().await {
+        let header = message.header();
+
+        match header.member().map(|member| member.as_str()) {
+            Some("Package") => {
+                let (_info, package, _summary): (u32, String, String) =
+                    message.body().deserialize()?;
+                packages.push(package);
+            }
+            Some("ErrorCode") => {
+                let (_code, details): (u32, String) = message.body().deserialize()?;
+                return Err(zbus::Error::Failure(details));
+            }
+            Some("Finished") => break,
+            _ => (),
+        }
+    }
+
+    Ok(packages)
+}
@@ -0,0 +1,868 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Reads files from the squashfs image which an AppImage appends to its
+//! runtime, so that AppImages are indexed without being run.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+const MAGIC: u32 = 0x7371_7368;
+const SUPERBLOCK_SIZE: usize = 96;
+
+/// Size of a metadata block once it is uncompressed.
+const METADATA_SIZE: usize = 8192;
+const METADATA_UNCOMPRESSED: u16 = 1 << 15;
+const DATA_UNCOMPRESSED: u32 = 1 << 24;
+const NO_FRAGMENT: u32 = u32::MAX;
+const FRAGMENT_ENTRY_SIZE: u64 = 16;
+
+/// Only small files are read from the image, such as desktop entries and icons.
+const MAX_FILE_SIZE: u64 = 16 << 20;
+const MAX_SYMLINKS: usize = 8;
+
+#[derive(Clone, Copy, Debug)]
+enum Compression {
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    fn decompress(self, data: &[u8], max: usize) -> io::Result<Vec<u8>> {
+        fn failed<E>(_: E) -> io::Error {
+            invalid("failed to decompress a block")
+        }
+
+        match self {
+            Self::Gzip => {
+                miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, max).map_err(failed)
+            }
+
+            Self::Xz => {
+                let mut output = Vec::new();
+                lzma_rs::xz_decompress(&mut &data[..], &mut output).map_err(failed)?;
+                if output.len() > max {
+                    return Err(invalid("block is larger than its limit"));
+                }
+
+                Ok(output)
+            }
+
+            Self::Zstd => {
+                let mut output = Vec::new();
+                ruzstd::decoding::StreamingDecoder::new(data)
+                    .map_err(failed)?
+                    .take(max as u64 + 1)
+                    .read_to_end(&mut output)?;
+                if output.len() > max {
+                    return Err(invalid("block is larger than its limit"));
+                }
+
+                Ok(output)
+            }
+        }
+    }
+}
+
+enum Inode {
+    Directory {
+        start: u32,
+        offset: u16,
+        size: u32,
+    },
+    File {
+        blocks_start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: u32,
+        blocks: Vec<u32>,
+    },
+    Symlink(String),
+    Other,
+}
+
+/// A squashfs image within a file, whose files are read on demand.
+pub struct Image {
+    file: File,
+    /// Position of the image within the file.
+    offset: u64,
+    block_size: u32,
+    compression: Compression,
+    root_inode: u64,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+}
+
+impl Image {
+    /// Opens the image which follows the ELF runtime of a type 2 AppImage.
+    pub fn open_appimage(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let offset = elf_size(&file)?;
+        Self::open(file, offset)
+    }
+
+    pub fn open(file: File, offset: u64) -> io::Result<Self> {
+        let mut superblock = [0; SUPERBLOCK_SIZE];
+        file.read_exact_at(&mut superblock, offset)?;
+
+        let u16_at = |at: usize| u16::from_le_bytes([superblock[at], superblock[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(superblock[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(superblock[at..at + 8].try_into().unwrap());
+
+        if u32_at(0) != MAGIC || u16_at(28) != 4 {
+            return Err(invalid("not a squashfs 4.0 image"));
+        }
+
+        let compression = match u16_at(20) {
+            1 => Compression::Gzip,
+            4 => Compression::Xz,
+            6 => Compression::Zstd,
+            _ => return Err(invalid("unsupported squashfs compression")),
+        };
+
+        let block_size = u32_at(12);
+
+        if block_size == 0 || block_size > 1 << 20 {
+            return Err(invalid("invalid squashfs block size"));
+        }
+
+        Ok(Self {
+            file,
+            offset,
+            block_size,
+            compression,
+            root_inode: u64_at(32),
+            inode_table: u64_at(64),
+            directory_table: u64_at(72),
+            fragment_table: u64_at(80),
+        })
+    }
+
+    /// Names of the files and directories at the root of the image.
+    pub fn root_names(&self) -> io::Result<Vec<String>> {
+        match self.inode(self.root_inode)? {
+            Inode::Directory {
+                start,
+                offset,
+                size,
+            } => Ok(self
+                .read_dir(start, offset, size)?
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()),
+            _ => Err(invalid("the root of the image is not a directory")),
+        }
+    }
+
+    /// Reads the file at the path, following symlinks within the image.
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let Inode::File {
+            blocks_start,
+            size,
+            fragment,
+            fragment_offset,
+            blocks,
+        } = self.lookup(path)?
+        else {
+            return Err(invalid("not a regular file"));
+        };
+
+        let size = size as usize;
+        let block_size = self.block_size as usize;
+        let mut data = Vec::with_capacity(size);
+        let mut position = self.offset + blocks_start;
+
+        for block in blocks {
+            let stored = (block & !DATA_UNCOMPRESSED) as usize;
+            let expected = block_size.min(size - data.len());
+
+            // Sparse blocks are not stored.
+            if stored == 0 {
+                data.resize(data.len() + expected, 0);
+                continue;
+            }
+
+            let raw = self.read_at(position, stored, block_size)?;
+            position += stored as u64;
+
+            if block & DATA_UNCOMPRESSED == 0 {
+                data.extend(
+                    self.compression
+                        .decompress(&raw, block_size)?
+                        .into_iter()
+                        .take(expected),
+                );
+            } else {
+                data.extend(raw.into_iter().take(expected));
+            }
+        }
+
+        if fragment != NO_FRAGMENT {
+            let block = self.fragment(fragment)?;
+            let start = fragment_offset as usize;
+            let tail = block
+                .get(start..start + (size - data.len()))
+                .ok_or_else(|| invalid("file exceeds its fragment"))?;
+            data.extend_from_slice(tail);
+        }
+
+        if data.len() != size {
+            return Err(invalid("file is shorter than its size"));
+        }
+
+        Ok(data)
+    }
+
+    fn lookup(&self, path: &str) -> io::Result<Inode> {
+        let mut components: VecDeque<String> = path.split('/').map(String::from).collect();
+        // Directories from the root to the current directory.
+        let mut parents = vec![self.root_inode];
+        let mut symlinks = 0;
+
+        while let Some(component) = components.pop_front() {
+            match component.as_str() {
+                "" | "." => continue,
+                ".." => {
+                    if parents.len() > 1 {
+                        parents.pop();
+                    }
+
+                    continue;
+                }
+                _ => (),
+            }
+
+            let Inode::Directory {
+                start,
+                offset,
+                size,
+            } = self.inode(*parents.last().unwrap())?
+            else {
+                return Err(invalid("not a directory"));
+            };
+
+            let Some((_, child)) = self
+                .read_dir(start, offset, size)?
+                .into_iter()
+                .find(|(name, _)| *name == component)
+            else {
+                return Err(io::ErrorKind::NotFound.into());
+            };
+
+            if let Inode::Symlink(target) = self.inode(child)? {
+                symlinks += 1;
+                if symlinks > MAX_SYMLINKS {
+                    return Err(invalid("too many levels of symbolic links"));
+                }
+
+                if target.starts_with('/') {
+                    parents.truncate(1);
+                }
+
+                for component in target.rsplit('/') {
+                    components.push_front(component.to_owned());
+                }
+
+                continue;
+            }
+
+            parents.push(child);
+        }
+
+        self.inode(*parents.last().unwrap())
+    }
+
+    fn inode(&self, reference: u64) -> io::Result<Inode> {
+        let mut metadata = self.metadata(
+            self.inode_table + (reference >> 16),
+            (reference & 0xFFFF) as usize,
+        )?;
+
+        let kind = metadata.u16()?;
+        // Permissions, owner, group, modification time, and inode number.
+        metadata.bytes(14)?;
+
+        let inode = match kind {
+            1 => {
+                let start = metadata.u32()?;
+                let _links = metadata.u32()?;
+                let size = u32::from(metadata.u16()?);
+                let offset = metadata.u16()?;
+
+                Inode::Directory {
+                    start,
+                    offset,
+                    size,
+                }
+            }
+
+            8 => {
+                let _links = metadata.u32()?;
+                let size = metadata.u32()?;
+                let start = metadata.u32()?;
+                let _parent = metadata.u32()?;
+                let _index_count = metadata.u16()?;
+                let offset = metadata.u16()?;
+
+                Inode::Directory {
+                    start,
+                    offset,
+                    size,
+                }
+            }
+
+            2 | 9 => {
+                let (blocks_start, size, fragment, fragment_offset) = if kind == 2 {
+                    let blocks_start = u64::from(metadata.u32()?);
+                    let fragment = metadata.u32()?;
+                    let fragment_offset = metadata.u32()?;
+                    let size = u64::from(metadata.u32()?);
+                    (blocks_start, size, fragment, fragment_offset)
+                } else {
+                    let blocks_start = metadata.u64()?;
+                    let size = metadata.u64()?;
+                    let _sparse = metadata.u64()?;
+                    let _links = metadata.u32()?;
+                    let fragment = metadata.u32()?;
+                    let fragment_offset = metadata.u32()?;
+                    let _xattr = metadata.u32()?;
+                    (blocks_start, size, fragment, fragment_offset)
+                };
+
+                if size > MAX_FILE_SIZE {
+                    return Err(invalid("file is too large to read"));
+                }
+
+                let block_size = u64::from(self.block_size);
+                let count = if fragment == NO_FRAGMENT {
+                    size.div_ceil(block_size)
+                } else {
+                    size / block_size
+                };
+
+                let blocks = (0..count)
+                    .map(|_| metadata.u32())
+                    .collect::<io::Result<_>>()?;
+
+                Inode::File {
+                    blocks_start,
+                    size,
+                    fragment,
+                    fragment_offset,
+                    blocks,
+                }
+            }
+
+            3 | 10 => {
+                let _links = metadata.u32()?;
+                let len = metadata.u32()? as usize;
+
+                if len > METADATA_SIZE {
+                    return Err(invalid("symlink target is too long"));
+                }
+
+                let target = metadata.bytes(len)?;
+                Inode::Symlink(String::from_utf8_lossy(&target).into_owned())
+            }
+
+            _ => Inode::Other,
+        };
+
+        Ok(inode)
+    }
+
+    /// Names of the entries of a directory, and references to their inodes.
+    fn read_dir(&self, start: u32, offset: u16, size: u32) -> io::Result<Vec<(String, u64)>> {
+        let mut entries = Vec::new();
+
+        // The size includes the `.` and `..` entries, which are not stored.
+        let Some(mut remaining) = (size as usize).checked_sub(3) else {
+            return Ok(entries);
+        };
+
+        let mut metadata =
+            self.metadata(self.directory_table + u64::from(start), offset as usize)?;
+
+        while remaining > 0 {
+            let count = metadata.u32()? as usize + 1;
+            let inode_start = u64::from(metadata.u32()?);
+            let _inode_number = metadata.u32()?;
+            remaining = remaining.saturating_sub(12);
+
+            for _ in 0..count {
+                let inode_offset = u64::from(metadata.u16()?);
+                let _inode_number = metadata.u16()?;
+                let _kind = metadata.u16()?;
+                let len = metadata.u16()? as usize + 1;
+                let name = metadata.bytes(len)?;
+                remaining = remaining.saturating_sub(8 + len);
+
+                entries.push((
+                    String::from_utf8_lossy(&name).into_owned(),
+                    (inode_start << 16) | inode_offset,
+                ));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// The uncompressed block which holds the tails of files.
+    fn fragment(&self, index: u32) -> io::Result<Vec<u8>> {
+        let entries_per_block = METADATA_SIZE as u64 / FRAGMENT_ENTRY_SIZE;
+        let index = u64::from(index);
+
+        let pointer = self.read_at(
+            self.offset + self.fragment_table + index / entries_per_block * 8,
+            8,
+            8,
+        )?;
+
+        let mut metadata = self.metadata(
+            u64::from_le_bytes(pointer.try_into().unwrap()),
+            ((index % entries_per_block) * FRAGMENT_ENTRY_SIZE) as usize,
+        )?;
+
+        let start = metadata.u64()?;
+        let stored = metadata.u32()?;
+        let block_size = self.block_size as usize;
+        let raw = self.read_at(
+            self.offset + start,
+            (stored & !DATA_UNCOMPRESSED) as usize,
+            block_size,
+        )?;
+
+        if stored & DATA_UNCOMPRESSED == 0 {
+            self.compression.decompress(&raw, block_size)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    /// Reads metadata from the block at `start` within the image onward,
+    /// beginning at `offset` within that block once it is uncompressed.
+    fn metadata(&self, start: u64, offset: usize) -> io::Result<Metadata<'_>> {
+        let mut metadata = Metadata {
+            image: self,
+            next: self.offset + start,
+            block: Vec::new(),
+            position: 0,
+        };
+
+        metadata.next_block()?;
+        metadata.position = offset;
+        Ok(metadata)
+    }
+
+    fn read_at(&self, position: u64, len: usize, max: usize) -> io::Result<Vec<u8>> {
+        if len > max {
+            return Err(invalid("block is larger than its limit"));
+        }
+
+        let mut buffer = vec![0; len];
+        self.file.read_exact_at(&mut buffer, position)?;
+        Ok(buffer)
+    }
+}
+
+/// Reads consecutive metadata blocks of the image.
+struct Metadata<'a> {
+    image: &'a Image,
+    /// Position of the next block within the file.
+    next: u64,
+    block: Vec<u8>,
+    position: usize,
+}
+
+impl Metadata<'_> {
+    fn next_block(&mut self) -> io::Result<()> {
+        let header = self.image.read_at(self.next, 2, 2)?;
+        let header = u16::from_le_bytes([header[0], header[1]]);
+        let len = (header & !METADATA_UNCOMPRESSED) as usize;
+
+        let raw = self.image.read_at(self.next + 2, len, METADATA_SIZE)?;
+        self.next += 2 + len as u64;
+
+        self.block = if header & METADATA_UNCOMPRESSED == 0 {
+            self.image.compression.decompress(&raw, METADATA_SIZE)?
+        } else {
+            raw
+        };
+
+        self.position = 0;
+        Ok(())
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len);
+
+        while bytes.len() < len {
+            if self.position >= self.block.len() {
+                self.next_block()?;
+                if self.block.is_empty() {
+                    return Err(invalid("empty metadata block"));
+                }
+            }
+
+            let take = (len - bytes.len()).min(self.block.len() - self.position);
+            bytes.extend_from_slice(&self.block[self.position..self.position + take]);
+            self.position += take;
+        }
+
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
+
+/// Size of a little-endian ELF file, by the end of its section headers, which
+/// is where the runtime of an AppImage ends.
+fn elf_size(file: &File) -> io::Result<u64> {
+    let mut header = [0; 64];
+    file.read_exact_at(&mut header, 0)?;
+
+    if header[..4] != *b"\x7fELF" || header[5] != 1 {
+        return Err(invalid("not a little-endian ELF file"));
+    }
+
+    let u16_at = |at: usize| u64::from(u16::from_le_bytes([header[at], header[at + 1]]));
+
+    let (section_headers, entry_size, entries) = match header[4] {
+        1 => (
+            u64::from(u32::from_le_bytes(header[0x20..0x24].try_into().unwrap())),
+            u16_at(0x2E),
+            u16_at(0x30),
+        ),
+        2 => (
+            u64::from_le_bytes(header[0x28..0x30].try_into().unwrap()),
+            u16_at(0x3A),
+            u16_at(0x3C),
+        ),
+        _ => return Err(invalid("unknown ELF class")),
+    };
+
+    Ok(section_headers + entry_size * entries)
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Size of the ELF header of the AppImages which are built for tests.
+    const ELF_SIZE: usize = 0x40 + 2 * 0x40;
+
+    pub(in super::super) enum Node<'a> {
+        File(&'a str, &'a [u8]),
+        Symlink(&'a str, &'a str),
+        Directory(&'a str, Vec<Node<'a>>),
+    }
+
+    /// Builds a gzip-compressed image, whose files span several blocks
+    /// and store their tails in a fragment.
+    struct Builder {
+        block_size: usize,
+        data: Vec<u8>,
+        fragment: Vec<u8>,
+        inodes: Vec<u8>,
+        directories: Vec<u8>,
+        inode_count: u32,
+    }
+
+    impl Builder {
+        fn compress(data: &[u8]) -> Option<Vec<u8>> {
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+            (compressed.len() < data.len()).then_some(compressed)
+        }
+
+        fn metadata(data: &[u8]) -> Vec<u8> {
+            let (header, block) = match Self::compress(data) {
+                Some(compressed) => (compressed.len() as u16, compressed),
+                None => (data.len() as u16 | METADATA_UNCOMPRESSED, data.to_vec()),
+            };
+
+            let mut output = header.to_le_bytes().to_vec();
+            output.extend(block);
+            output
+        }
+
+        /// Begins an inode, returning its reference.
+        fn inode(&mut self, kind: u16) -> u64 {
+            let reference = self.inodes.len() as u64;
+            self.inode_count += 1;
+            self.inodes.extend(kind.to_le_bytes());
+            self.inodes.extend([0; 10]);
+            self.inodes.extend(self.inode_count.to_le_bytes());
+            reference
+        }
+
+        fn add(&mut self, node: &Node) -> (String, u16, u64) {
+            match node {
+                Node::File(name, content) => {
+                    let start = SUPERBLOCK_SIZE + self.data.len();
+                    let mut sizes = Vec::new();
+                    let mut chunks = content.chunks_exact(self.block_size);
+
+                    for chunk in &mut chunks {
+                        match Self::compress(chunk) {
+                            Some(compressed) => {
+                                sizes.push(compressed.len() as u32);
+                                self.data.extend(compressed);
+                            }
+                            None => {
+                                sizes.push(chunk.len() as u32 | DATA_UNCOMPRESSED);
+                                self.data.extend(chunk);
+                            }
+                        }
+                    }
+
+                    let tail = chunks.remainder();
+                    let (fragment, offset) = if tail.is_empty() {
+                        (NO_FRAGMENT, 0)
+                    } else {
+                        let offset = self.fragment.len() as u32;
+                        self.fragment.extend(tail);
+                        (0, offset)
+                    };
+
+                    let reference = self.inode(2);
+                    self.inodes.extend((start as u32).to_le_bytes());
+                    self.inodes.extend(fragment.to_le_bytes());
+                    self.inodes.extend(offset.to_le_bytes());
+                    self.inodes.extend((content.len() as u32).to_le_bytes());
+                    for size in sizes {
+                        self.inodes.extend(size.to_le_bytes());
+                    }
+
+                    (name.to_string(), 2, reference)
+                }
+
+                Node::Symlink(name, target) => {
+                    let reference = self.inode(3);
+                    self.inodes.extend(1u32.to_le_bytes());
+                    self.inodes.extend((target.len() as u32).to_le_bytes());
+                    self.inodes.extend(target.as_bytes());
+                    (name.to_string(), 3, reference)
+                }
+
+                Node::Directory(name, children) => {
+                    let children: Vec<_> = children.iter().map(|child| self.add(child)).collect();
+                    let (name, reference) = self.directory(name, children);
+                    (name, 1, reference)
+                }
+            }
+        }
+
+        fn directory(&mut self, name: &str, children: Vec<(String, u16, u64)>) -> (String, u64) {
+            let offset = self.directories.len();
+
+            if !children.is_empty() {
+                self.directories
+                    .extend((children.len() as u32 - 1).to_le_bytes());
+                self.directories.extend([0; 8]);
+
+                for (name, kind, reference) in children {
+                    self.directories.extend((reference as u16).to_le_bytes());
+                    self.directories.extend([0; 2]);
+                    self.directories.extend(kind.to_le_bytes());
+                    self.directories
+                        .extend((name.len() as u16 - 1).to_le_bytes());
+                    self.directories.extend(name.as_bytes());
+                }
+            }
+
+            let size = self.directories.len() - offset + 3;
+            let reference = self.inode(1);
+            self.inodes.extend(0u32.to_le_bytes());
+            self.inodes.extend(2u32.to_le_bytes());
+            self.inodes.extend((size as u16).to_le_bytes());
+            self.inodes.extend((offset as u16).to_le_bytes());
+            self.inodes.extend(0u32.to_le_bytes());
+            (name.to_owned(), reference)
+        }
+    }
+
+    /// An image of the files, as it is embedded within an AppImage.
+    pub(in super::super) fn image(block_size: usize, files: Vec<Node>) -> Vec<u8> {
+        let mut builder = Builder {
+            block_size,
+            data: Vec::new(),
+            fragment: Vec::new(),
+            inodes: Vec::new(),
+            directories: Vec::new(),
+            inode_count: 0,
+        };
+
+        let (_, _, root) = builder.add(&Node::Directory("", files));
+
+        let mut image = vec![0; SUPERBLOCK_SIZE];
+        image.extend(&builder.data);
+
+        let fragment_start = image.len() as u64;
+        let fragment = match Builder::compress(&builder.fragment) {
+            Some(compressed) => (compressed.len() as u32, compressed),
+            None => (
+                builder.fragment.len() as u32 | DATA_UNCOMPRESSED,
+                builder.fragment.clone(),
+            ),
+        };
+        image.extend(&fragment.1);
+
+        let inode_table = image.len() as u64;
+        image.extend(Builder::metadata(&builder.inodes));
+
+        let directory_table = image.len() as u64;
+        image.extend(Builder::metadata(&builder.directories));
+
+        let mut entry = fragment_start.to_le_bytes().to_vec();
+        entry.extend(fragment.0.to_le_bytes());
+        entry.extend([0; 4]);
+        let entries = image.len() as u64;
+        image.extend(Builder::metadata(&entry));
+
+        let fragment_table = image.len() as u64;
+        image.extend(entries.to_le_bytes());
+
+        let superblock = &mut image[..SUPERBLOCK_SIZE];
+        superblock[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        superblock[4..8].copy_from_slice(&builder.inode_count.to_le_bytes());
+        superblock[12..16].copy_from_slice(&(block_size as u32).to_le_bytes());
+        superblock[16..20].copy_from_slice(&1u32.to_le_bytes());
+        superblock[20..22].copy_from_slice(&1u16.to_le_bytes());
+        superblock[28..30].copy_from_slice(&4u16.to_le_bytes());
+        superblock[32..40].copy_from_slice(&root.to_le_bytes());
+        superblock[64..72].copy_from_slice(&inode_table.to_le_bytes());
+        superblock[72..80].copy_from_slice(&directory_table.to_le_bytes());
+        superblock[80..88].copy_from_slice(&fragment_table.to_le_bytes());
+
+        image
+    }
+
+    /// An AppImage, whose runtime is only an ELF header.
+    pub(in super::super) fn appimage(files: Vec<Node>) -> Vec<u8> {
+        let mut appimage = vec![0; ELF_SIZE];
+        appimage[..4].copy_from_slice(b"\x7fELF");
+        appimage[4] = 2;
+        appimage[5] = 1;
+        appimage[0x28..0x30].copy_from_slice(&0x40u64.to_le_bytes());
+        appimage[0x3A..0x3C].copy_from_slice(&0x40u16.to_le_bytes());
+        appimage[0x3C..0x3E].copy_from_slice(&2u16.to_le_bytes());
+        appimage.extend(image(64, files));
+        appimage
+    }
+
+    #[test]
+    fn files_are_read() {
+        let path = std::env::temp_dir().join(format!(
+            "pop-launcher-squashfs-{}.AppImage",
+            std::process::id()
+        ));
+
+        let large: Vec<u8> = (0..200u8).collect();
+        let repeated = b"abc".repeat(50);
+
+        std::fs::write(
+            &path,
+            appimage(vec![
+                Node::File("large", &large),
+                Node::File("repeated", &repeated),
+                Node::File("small", b"small"),
+                Node::Symlink("app.desktop", "usr/share/app.desktop"),
+                Node::Symlink("loop", "loop"),
+                Node::Directory(
+                    "usr",
+                    vec![Node::Directory(
+                        "share",
+                        vec![
+                            Node::File("app.desktop", b"[Desktop Entry]\n"),
+                            Node::Symlink("up", "../../small"),
+                            Node::Symlink("absolute", "/small"),
+                        ],
+                    )],
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let image = Image::open_appimage(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(
+            image.root_names().unwrap(),
+            ["large", "repeated", "small", "app.desktop", "loop", "usr"]
+        );
+
+        assert_eq!(image.read("large").unwrap(), large);
+        assert_eq!(image.read("repeated").unwrap(), repeated);
+        assert_eq!(image.read("small").unwrap(), b"small");
+        assert_eq!(image.read("app.desktop").unwrap(), b"[Desktop Entry]\n");
+        assert_eq!(image.read("usr/share/up").unwrap(), b"small");
+        assert_eq!(image.read("usr/share/absolute").unwrap(), b"small");
+
+        assert!(image.read("missing").is_err());
+        assert!(image.read("usr").is_err());
+        assert!(image.read("small/file").is_err());
+        assert!(image.read("loop").is_err());
+    }
+
+    #[test]
+    fn blocks_are_decompressed() {
+        let block = b"squashfs ".repeat(100);
+
+        let gzip = miniz_oxide::deflate::compress_to_vec_zlib(&block, 6);
+        let zstd = ruzstd::encoding::compress_to_vec(
+            &block[..],
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut &block[..], &mut xz).unwrap();
+
+        for (compression, compressed) in [
+            (Compression::Gzip, gzip),
+            (Compression::Xz, xz),
+            (Compression::Zstd, zstd),
+        ] {
+            assert_eq!(
+                compression.decompress(&compressed, block.len()).unwrap(),
+                block,
+                "{compression:?}"
+            );
+
+            // Blocks may not exceed their size once uncompressed.
+            assert!(compression.decompress(&compressed, 100).is_err());
+            assert!(compression.decompress(b"corrupt", 100).is_err());
+        }
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let path = std::env::temp_dir().join(format!(
+            "pop-launcher-squashfs-{}.not-appimage",
+            std::process::id()
+        ));
+
+        std::fs::write(&path, b"#!/bin/sh\necho not an AppImage\n").unwrap();
+        let opened = Image::open_appimage(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(opened.is_err());
+    }
+}
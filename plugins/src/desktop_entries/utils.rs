@@ -4,8 +4,6 @@ use std::borrow::Cow;
 
 use freedesktop_desktop_entry::{DesktopEntry, PathSource};
 
-// todo: subscriptions with notify
-
 pub fn path_string(source: &PathSource) -> Cow<'static, str> {
     match source {
         PathSource::Local | PathSource::LocalDesktop => "Local".into(),
@@ -20,10 +18,17 @@ pub fn path_string(source: &PathSource) -> Cow<'static, str> {
     }
 }
 
-pub fn get_description<'a>(de: &'a DesktopEntry, locales: &[String]) -> String {
-    let path_source = PathSource::guess_from(&de.path);
+/// Where the entry was installed from.
+pub fn source_name(de: &DesktopEntry) -> Cow<'static, str> {
+    if super::appimage::cache_dir().is_some_and(|dir| de.path.starts_with(dir)) {
+        return "AppImage".into();
+    }
+
+    path_string(&PathSource::guess_from(&de.path))
+}
 
-    let desc_source = path_string(&path_source).to_string();
+pub fn get_description(de: &DesktopEntry, locales: &[String]) -> String {
+    let desc_source = source_name(de).to_string();
 
     match de.comment(locales) {
         Some(desc) => {
@@ -22,7 +22,9 @@ pub async fn main() {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
+                Request::ActivateContext { id, .. } => app.activate_context(id).await,
                 Request::Complete(id) => app.complete(id).await,
+                Request::Context(id) => app.context(id).await,
                 Request::Search(query) => app.search(query).await,
                 Request::Exit => break,
                 _ => (),
@@ -60,6 +62,17 @@ impl App {
         }
     }
 
+    pub async fn activate_context(&mut self, id: u32) {
+        if let Some(parent) = self
+            .search_results
+            .get(id as usize)
+            .and_then(|selected| selected.path.parent())
+        {
+            crate::xdg_open(parent);
+            crate::send(&mut self.out, PluginResponse::Close).await;
+        }
+    }
+
     pub async fn complete(&mut self, id: u32) {
         if let Some(selected) = self.search_results.get(id as usize) {
             let path = match selected.path.strip_prefix(&self.home) {
@@ -77,6 +90,11 @@ impl App {
         }
     }
 
+    pub async fn context(&mut self, id: u32) {
+        let options = vec![open_containing_folder()];
+        crate::send(&mut self.out, PluginResponse::Context { id, options }).await;
+    }
+
     pub async fn search(&mut self, query: String) {
         let path = if let Some(stripped) = query.strip_prefix("~/") {
             self.home.join(stripped)
@@ -172,6 +190,7 @@ impl App {
                     name: selection.name.clone(),
                     description: selection.description.clone(),
                     icon: Some(selection.icon.clone()),
+                    alternate: Some(open_containing_folder()),
                     ..Default::default()
                 }),
             )
@@ -185,3 +204,12 @@ impl App {
         crate::send(&mut self.out, PluginResponse::Finished).await;
     }
 }
+
+fn open_containing_folder() -> ContextOption {
+    ContextOption {
+        id: 0,
+        name: String::from("Open Containing Folder"),
+        accelerator: Some(String::from("Shift+Return")),
+        default_alternate: true,
+    }
+}
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Result ids for handles whose ids are assigned elsewhere, and may be reused,
+//! such as the Wayland objects of windows.
+
+use pop_launcher::{Generation, Indice};
+
+/// Bits of a result id which hold the index of a slot. The rest hold the
+/// generation of the slot.
+const INDEX_BITS: u32 = 16;
+
+/// The result id of the handle at a key, given by [`HandleTable::key`].
+///
+/// Requests carry only the id of a result, so the generation is encoded in
+/// it for [`HandleTable::get`] to reject ids of handles which were replaced.
+pub fn result_id((generation, index): (Generation, Indice)) -> Indice {
+    (generation << INDEX_BITS) | index
+}
+
+/// Handles keyed by the index of their slot, and the generation of the slot.
+///
+/// The slot of a removed handle is retired until [`HandleTable::recycle`] is
+/// called, and takes a new generation when it is reused, so that results
+/// which still refer to it are ignored, rather than activating the handle
+/// that takes its place.
+pub struct HandleTable<H> {
+    slots: Vec<Slot<H>>,
+    retired: Vec<Indice>,
+    free: Vec<Indice>,
+}
+
+struct Slot<H> {
+    generation: Generation,
+    handle: Option<H>,
+}
+
+impl<H> Default for HandleTable<H> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            retired: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<H: PartialEq> HandleTable<H> {
+    /// The handle of a result id, if its slot is occupied by the same
+    /// generation.
+    pub fn get(&self, id: Indice) -> Option<&H> {
+        let slot = self.slots.get((id & ((1 << INDEX_BITS) - 1)) as usize)?;
+        (slot.generation == id >> INDEX_BITS)
+            .then_some(slot.handle.as_ref())
+            .flatten()
+    }
+
+    /// Adds a handle, or finds the handle if it was already added.
+    pub fn insert(&mut self, handle: H) -> (Generation, Indice) {
+        if let Some(key) = self.key(&handle) {
+            return key;
+        }
+
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.generation = (slot.generation + 1) % (1 << (Generation::BITS - INDEX_BITS));
+            slot.handle = Some(handle);
+            return (slot.generation, index);
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            handle: Some(handle),
+        });
+
+        (0, self.slots.len() as Indice - 1)
+    }
+
+    pub fn key(&self, handle: &H) -> Option<(Generation, Indice)> {
+        self.slots
+            .iter()
+            .zip(0..)
+            .find(|(slot, _)| slot.handle.as_ref() == Some(handle))
+            .map(|(slot, index)| (slot.generation, index))
+    }
+
+    /// Removes a handle, retiring its slot until the next recycle.
+    pub fn remove(&mut self, handle: &H) -> Option<(Generation, Indice)> {
+        let (generation, index) = self.key(handle)?;
+        self.slots[index as usize].handle = None;
+        self.retired.push(index);
+        Some((generation, index))
+    }
+
+    /// Frees the retired slots for reuse, once no results refer to them.
+    pub fn recycle(&mut self) {
+        self.free.append(&mut self.retired);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_slots_after_recycle() {
+        let mut table = HandleTable::default();
+
+        assert_eq!(table.insert("a"), (0, 0));
+        assert_eq!(table.insert("b"), (0, 1));
+        assert_eq!(table.insert("a"), (0, 0));
+
+        let a = result_id((0, 0));
+        assert_eq!(table.get(a), Some(&"a"));
+        assert_eq!(table.remove(&"a"), Some((0, 0)));
+        assert_eq!(table.get(a), None);
+
+        // A retired slot is not taken by a new handle.
+        assert_eq!(table.insert("c"), (0, 2));
+
+        table.recycle();
+        assert_eq!(table.insert("d"), (1, 0));
+        assert_eq!(table.key(&"d"), Some((1, 0)));
+        assert_eq!(table.remove(&"a"), None);
+
+        // The id of the removed handle does not resolve to its successor.
+        assert_eq!(table.get(a), None);
+        assert_eq!(table.get(result_id((1, 0))), Some(&"d"));
+    }
+}
@@ -3,18 +3,33 @@
 
 pub mod calc;
 pub mod cosmic_toplevel;
+pub mod dbus;
 pub mod desktop_entries;
 pub mod files;
 pub mod find;
+pub mod handle_table;
+pub mod mpris;
+pub mod network;
 pub mod pop_shell;
 pub mod pulse;
 pub mod recent;
 pub mod scripts;
+pub mod session;
 pub mod terminal;
+pub mod watcher;
 pub mod web;
+pub mod wlr_toplevel;
 
 use pop_launcher::PluginResponse;
-use std::{borrow::Cow, ffi::OsStr, future::Future, path::Path, process::Stdio};
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    future::Future,
+    io,
+    path::Path,
+    process::{Output, Stdio},
+    time::Duration,
+};
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 pub async fn send<W: AsyncWrite + Unpin>(tx: &mut W, response: PluginResponse) {
@@ -54,3 +69,22 @@ pub fn xdg_open<S: AsRef<OsStr>>(file: S) {
         .stderr(Stdio::null())
         .spawn();
 }
+
+/// Runs a command to completion and captures its output, or kills it and
+/// returns `None` if it does not exit within the timeout.
+pub async fn capture_output(
+    mut command: tokio::process::Command,
+    timeout: Duration,
+) -> io::Result<Option<Output>> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.map(Some),
+        Err(_) => Ok(None),
+    }
+}
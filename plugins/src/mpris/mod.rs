@@ -0,0 +1,576 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+use crate::{dbus, send};
+use futures::StreamExt;
+use pop_launcher::*;
+use serde::Serialize;
+use std::{borrow::Cow, collections::HashMap};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use zbus::Connection;
+use zvariant::{OwnedValue, Type};
+
+const PATH: &str = "/org/mpris/MediaPlayer2";
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MEDIA_PLAYER: &str = "org.mpris.MediaPlayer2";
+const PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Microseconds that the seek controls move the position of the track by.
+const SEEK_STEP: i64 = 10_000_000;
+
+/// Words which list every player rather than matching their tracks.
+const KEYWORDS: &[&str] = &["media", "music", "player", "playing"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Control {
+    PlayPause,
+    Next,
+    Previous,
+    SeekForward,
+    SeekBackward,
+}
+
+impl Control {
+    /// Controls in the order of their context options, by which they are identified.
+    const ALL: [Control; 5] = [
+        Control::PlayPause,
+        Control::Next,
+        Control::Previous,
+        Control::SeekForward,
+        Control::SeekBackward,
+    ];
+
+    fn name(self, player: &Player) -> &'static str {
+        match self {
+            Control::PlayPause if player.playing() => "Pause",
+            Control::PlayPause => "Play",
+            Control::Next => "Next Track",
+            Control::Previous => "Previous Track",
+            Control::SeekForward => "Seek Forward 10s",
+            Control::SeekBackward => "Seek Backward 10s",
+        }
+    }
+
+    fn icon(self, player: &Player) -> &'static str {
+        match self {
+            Control::PlayPause if player.playing() => "media-playback-pause",
+            Control::PlayPause => "media-playback-start",
+            Control::Next => "media-skip-forward",
+            Control::Previous => "media-skip-backward",
+            Control::SeekForward => "media-seek-forward",
+            Control::SeekBackward => "media-seek-backward",
+        }
+    }
+
+    /// Words of a query which select the control.
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Control::PlayPause => &["play", "pause", "resume", "toggle"],
+            Control::Next => &["next", "skip"],
+            Control::Previous => &["previous", "prev", "back"],
+            Control::SeekForward => &["seek", "forward", "ff"],
+            Control::SeekBackward => &["seek", "backward", "rewind"],
+        }
+    }
+
+    fn available(self, player: &Player) -> bool {
+        match self {
+            Control::PlayPause => player.can_play || player.can_pause,
+            Control::Next => player.can_go_next,
+            Control::Previous => player.can_go_previous,
+            Control::SeekForward | Control::SeekBackward => player.can_seek,
+        }
+    }
+}
+
+/// A media player on the session bus, and its current track.
+#[derive(Debug)]
+struct Player {
+    /// Its name on the bus, such as `org.mpris.MediaPlayer2.vlc`.
+    name: String,
+    identity: String,
+    status: String,
+    title: Option<String>,
+    artists: Vec<String>,
+    /// Path of the album art, which is only shown when it is a local file.
+    art: Option<String>,
+    can_play: bool,
+    can_pause: bool,
+    can_go_next: bool,
+    can_go_previous: bool,
+    can_seek: bool,
+}
+
+impl Player {
+    async fn load(connection: &Connection, name: String) -> zbus::Result<Self> {
+        let mut properties = dbus::get_all(connection, &name, PATH, PLAYER).await?;
+
+        let identity = dbus::get_all(connection, &name, PATH, MEDIA_PLAYER)
+            .await
+            .ok()
+            .and_then(|mut properties| properties.remove("Identity"))
+            .and_then(|value| String::try_from(value).ok())
+            .unwrap_or_else(|| name.trim_start_matches(PREFIX).to_owned());
+
+        let mut metadata = properties
+            .remove("Metadata")
+            .and_then(|value| HashMap::<String, OwnedValue>::try_from(value).ok())
+            .unwrap_or_default();
+
+        let flag = |property: &str| {
+            properties
+                .get(property)
+                .and_then(|value| bool::try_from(value).ok())
+                .unwrap_or(false)
+        };
+
+        Ok(Player {
+            can_play: flag("CanPlay"),
+            can_pause: flag("CanPause"),
+            can_go_next: flag("CanGoNext"),
+            can_go_previous: flag("CanGoPrevious"),
+            can_seek: flag("CanSeek"),
+            status: properties
+                .remove("PlaybackStatus")
+                .and_then(|value| String::try_from(value).ok())
+                .unwrap_or_default(),
+            title: metadata
+                .remove("xesam:title")
+                .and_then(|value| String::try_from(value).ok())
+                .filter(|title| !title.is_empty()),
+            artists: metadata
+                .remove("xesam:artist")
+                .and_then(|value| Vec::<String>::try_from(value).ok())
+                .unwrap_or_default(),
+            art: metadata
+                .remove("mpris:artUrl")
+                .and_then(|value| String::try_from(value).ok())
+                .and_then(|art| url::Url::parse(&art).ok()?.to_file_path().ok())
+                .map(|path| path.to_string_lossy().into_owned()),
+            identity,
+            name,
+        })
+    }
+
+    fn playing(&self) -> bool {
+        self.status == "Playing"
+    }
+
+    /// The title and artists of the track, or the name of the player without one.
+    fn now_playing(&self) -> String {
+        match (&self.title, self.artists.is_empty()) {
+            (Some(title), true) => title.clone(),
+            (Some(title), false) => format!("{} — {}", title, self.artists.join(", ")),
+            (None, _) => self.identity.clone(),
+        }
+    }
+
+    fn icon(&self) -> IconSource {
+        match self.art.as_ref() {
+            Some(art) => IconSource::Name(Cow::Owned(art.clone())),
+            None => IconSource::Name(Cow::Borrowed("multimedia-player")),
+        }
+    }
+
+    async fn control(&self, connection: &Connection, control: Control) -> zbus::Result<()> {
+        match control {
+            Control::PlayPause => self.call(connection, "PlayPause", &()).await,
+            Control::Next => self.call(connection, "Next", &()).await,
+            Control::Previous => self.call(connection, "Previous", &()).await,
+            Control::SeekForward => self.call(connection, "Seek", &(SEEK_STEP,)).await,
+            Control::SeekBackward => self.call(connection, "Seek", &(-SEEK_STEP,)).await,
+        }
+    }
+
+    async fn call<A: Serialize + Type>(
+        &self,
+        connection: &Connection,
+        method: &str,
+        args: &A,
+    ) -> zbus::Result<()> {
+        connection
+            .call_method(Some(self.name.as_str()), PATH, Some(PLAYER), method, args)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Names of the media players on the bus.
+async fn player_names(connection: &Connection) -> zbus::Result<Vec<String>> {
+    let names: Vec<String> = connection
+        .call_method(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            Some("org.freedesktop.DBus"),
+            "ListNames",
+            &(),
+        )
+        .await?
+        .body()
+        .deserialize()?;
+
+    Ok(names
+        .into_iter()
+        .filter(|name| name.starts_with(PREFIX))
+        .collect())
+}
+
+pub async fn main() {
+    let connection = match Connection::session().await {
+        Ok(conn) => conn,
+        Err(_) => {
+            let mut out = async_stdout();
+            let _ = crate::send(&mut out, PluginResponse::Deactivate).await;
+            return;
+        }
+    };
+
+    let mut app = App::new(connection, async_stdout());
+
+    let mut requests = json_input_stream(async_stdin());
+    while let Some(request) = requests.next().await {
+        match request {
+            Ok(request) => match request {
+                Request::Activate(id) => app.activate(id).await,
+                Request::ActivateContext { id, context } => app.activate_context(id, context).await,
+                Request::Context(id) => app.context(id).await,
+                Request::Search(query) => app.search(&query).await,
+                Request::Exit => break,
+                _ => (),
+            },
+            Err(why) => {
+                tracing::error!("malformed JSON request: {}", why);
+            }
+        }
+    }
+}
+
+struct App<W> {
+    connection: Connection,
+    players: Vec<Player>,
+    /// The player and control of each result of the last search, by their IDs.
+    results: Vec<(usize, Control)>,
+    tx: W,
+}
+
+impl<W: AsyncWrite + Unpin> App<W> {
+    fn new(connection: Connection, tx: W) -> Self {
+        Self {
+            connection,
+            players: Vec::new(),
+            results: Vec::new(),
+            tx,
+        }
+    }
+
+    async fn reload(&mut self) {
+        self.players.clear();
+
+        let names = match player_names(&self.connection).await {
+            Ok(names) => names,
+            Err(why) => {
+                tracing::error!("failed to list media players: {}", why);
+                return;
+            }
+        };
+
+        for name in names {
+            match Player::load(&self.connection, name).await {
+                Ok(player) => self.players.push(player),
+                Err(why) => tracing::error!("failed to get media player: {}", why),
+            }
+        }
+    }
+
+    async fn activate(&mut self, id: u32) {
+        if let Some(&(player, control)) = self.results.get(id as usize) {
+            self.control(player, control).await;
+        }
+    }
+
+    async fn activate_context(&mut self, id: u32, context: u32) {
+        if let Some(&(player, _)) = self.results.get(id as usize)
+            && let Some(&control) = Control::ALL.get(context as usize)
+        {
+            self.control(player, control).await;
+        }
+    }
+
+    async fn context(&mut self, id: u32) {
+        let Some(player) = self
+            .results
+            .get(id as usize)
+            .and_then(|&(player, _)| self.players.get(player))
+        else {
+            return;
+        };
+
+        let options = Control::ALL
+            .iter()
+            .enumerate()
+            .filter(|(_, control)| control.available(player))
+            .map(|(context, control)| ContextOption {
+                id: context as u32,
+                name: control.name(player).to_owned(),
+                ..Default::default()
+            })
+            .collect();
+
+        send(&mut self.tx, PluginResponse::Context { id, options }).await;
+    }
+
+    async fn control(&mut self, player: usize, control: Control) {
+        let Some(player) = self.players.get(player) else {
+            return;
+        };
+
+        if let Err(why) = player.control(&self.connection, control).await {
+            tracing::error!("failed to control {}: {}", player.name, why);
+        }
+    }
+
+    async fn search(&mut self, query: &str) {
+        self.results.clear();
+
+        let query = query.trim().to_ascii_lowercase();
+
+        if !query.is_empty() {
+            self.reload().await;
+            self.search_players(&query).await;
+        }
+
+        send(&mut self.tx, PluginResponse::Finished).await;
+        let _ = self.tx.flush().await;
+    }
+
+    /// Lists the controls named by the first word of the query, or otherwise
+    /// the players whose tracks match every word of the query.
+    async fn search_players(&mut self, query: &str) {
+        let mut words = query.split_ascii_whitespace();
+        let first = words.next().unwrap_or_default();
+
+        let controls: Vec<Control> = Control::ALL
+            .into_iter()
+            .filter(|control| control.keywords().contains(&first))
+            .collect();
+
+        for (index, player) in self.players.iter().enumerate() {
+            if !controls.is_empty() {
+                // The remaining words select the player to control.
+                let filter: Vec<&str> = words.clone().collect();
+
+                if !matches(player, &filter) {
+                    continue;
+                }
+
+                for &control in controls.iter().filter(|c| c.available(player)) {
+                    let result = PluginSearchResult {
+                        id: self.results.len() as u32,
+                        name: control.name(player).to_owned(),
+                        description: format!("{} · {}", player.now_playing(), player.identity),
+                        icon: Some(IconSource::Name(Cow::Borrowed(control.icon(player)))),
+                        ..Default::default()
+                    };
+
+                    self.results.push((index, control));
+                    send(&mut self.tx, PluginResponse::Append(result)).await;
+                }
+
+                continue;
+            }
+
+            let filter: Vec<&str> = query
+                .split_ascii_whitespace()
+                .filter(|word| !KEYWORDS.contains(word))
+                .collect();
+
+            if !matches(player, &filter) {
+                continue;
+            }
+
+            let result = PluginSearchResult {
+                id: self.results.len() as u32,
+                name: player.now_playing(),
+                description: format!("{} · {}", player.identity, player.status),
+                icon: Some(player.icon()),
+                ..Default::default()
+            };
+
+            self.results.push((index, Control::PlayPause));
+            send(&mut self.tx, PluginResponse::Append(result)).await;
+        }
+    }
+}
+
+/// Whether the title, artists, or name of the player contain every word.
+fn matches(player: &Player, words: &[&str]) -> bool {
+    let haystack = [
+        player.title.as_deref().unwrap_or_default(),
+        &player.artists.join(" "),
+        &player.identity,
+    ]
+    .join(" ")
+    .to_ascii_lowercase();
+
+    words.iter().all(|word| haystack.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::testing::PrivateBus;
+    use std::sync::{Arc, Mutex};
+
+    struct MockRoot;
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2")]
+    impl MockRoot {
+        #[zbus(property)]
+        fn identity(&self) -> &str {
+            "Mock Player"
+        }
+    }
+
+    struct MockPlayer {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl MockPlayer {
+        fn play_pause(&self) {
+            self.calls.lock().unwrap().push("PlayPause".into());
+        }
+
+        fn next(&self) {
+            self.calls.lock().unwrap().push("Next".into());
+        }
+
+        fn seek(&self, offset: i64) {
+            self.calls.lock().unwrap().push(format!("Seek {}", offset));
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> &str {
+            "Playing"
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            HashMap::from([
+                (
+                    "xesam:title".into(),
+                    OwnedValue::from(zvariant::Str::from("Song")),
+                ),
+                (
+                    "xesam:artist".into(),
+                    OwnedValue::try_from(zvariant::Value::from(vec!["Artist"])).unwrap(),
+                ),
+                (
+                    "mpris:artUrl".into(),
+                    OwnedValue::from(zvariant::Str::from("file:///tmp/cover.png")),
+                ),
+            ])
+        }
+
+        #[zbus(property)]
+        fn can_play(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_pause(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_go_next(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_go_previous(&self) -> bool {
+            false
+        }
+
+        #[zbus(property)]
+        fn can_seek(&self) -> bool {
+            true
+        }
+    }
+
+    fn results(out: &[u8]) -> Vec<PluginResponse> {
+        String::from_utf8_lossy(out)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|response| !matches!(response, PluginResponse::Finished))
+            .collect()
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon"]
+    async fn controls_players_on_private_bus() {
+        let bus = PrivateBus::new().await;
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let _service = bus
+            .builder()
+            .name("org.mpris.MediaPlayer2.mock")
+            .unwrap()
+            .serve_at(PATH, MockRoot)
+            .unwrap()
+            .serve_at(
+                PATH,
+                MockPlayer {
+                    calls: calls.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let connection = bus.connect().await;
+
+        let mut app = App::new(connection, Vec::new());
+
+        app.search("music").await;
+        let PluginResponse::Append(result) = &results(&app.tx)[0] else {
+            panic!("expected a result");
+        };
+        assert_eq!(result.name, "Song — Artist");
+        assert_eq!(result.description, "Mock Player · Playing");
+        assert!(matches!(&result.icon, Some(IconSource::Name(icon)) if icon == "/tmp/cover.png"));
+
+        app.tx.clear();
+        app.context(0).await;
+        let PluginResponse::Context { options, .. } = &results(&app.tx)[0] else {
+            panic!("expected context options");
+        };
+        let options: Vec<_> = options.iter().map(|o| (o.id, o.name.as_str())).collect();
+        assert_eq!(
+            options,
+            [
+                (0, "Pause"),
+                (1, "Next Track"),
+                (3, "Seek Forward 10s"),
+                (4, "Seek Backward 10s")
+            ]
+        );
+
+        app.activate(0).await;
+        app.activate_context(0, 4).await;
+
+        app.search("next song").await;
+        assert_eq!(app.results, [(0, Control::Next)]);
+        app.activate(0).await;
+
+        app.search("previous").await;
+        assert!(app.results.is_empty());
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["PlayPause", "Seek -10000000", "Next"]
+        );
+    }
+}
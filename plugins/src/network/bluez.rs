@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Paired Bluetooth devices from BlueZ.
+
+use super::{Item, Kind, State, Toggle};
+use crate::dbus::call;
+use std::{borrow::Cow, collections::HashMap};
+use zbus::Connection;
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+pub const BLUEZ: &str = "org.bluez";
+const DEVICE: &str = "org.bluez.Device1";
+const OBJECT_MANAGER: &str = "org.freedesktop.DBus.ObjectManager";
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+pub async fn items(connection: &Connection) -> zbus::Result<Vec<Item>> {
+    let objects: ManagedObjects = call(
+        connection,
+        BLUEZ,
+        "/",
+        OBJECT_MANAGER,
+        "GetManagedObjects",
+        &(),
+    )
+    .await?;
+
+    let mut items: Vec<Item> = objects
+        .into_iter()
+        .filter_map(|(path, mut interfaces)| {
+            let mut device = interfaces.remove(DEVICE)?;
+
+            let flag = |property: &str| {
+                device
+                    .get(property)
+                    .and_then(|value| bool::try_from(value).ok())
+                    .unwrap_or(false)
+            };
+
+            if !flag("Paired") {
+                return None;
+            }
+
+            let connected = flag("Connected");
+
+            let string =
+                |value: Option<OwnedValue>| value.and_then(|value| String::try_from(value).ok());
+
+            let icon = string(device.remove("Icon"))
+                .map_or(Cow::Borrowed("bluetooth-active-symbolic"), Cow::Owned);
+
+            Some(Item {
+                kind: Kind::Bluetooth,
+                name: string(device.remove("Alias")).unwrap_or_else(|| path.to_string()),
+                state: if connected {
+                    State::Connected
+                } else {
+                    State::Disconnected
+                },
+                detail: None,
+                icon,
+                toggle: if connected {
+                    Toggle::Disconnect(path)
+                } else {
+                    Toggle::Connect(path)
+                },
+            })
+        })
+        .collect();
+
+    items.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(items)
+}
+
+pub async fn toggle(connection: &Connection, toggle: &Toggle) -> zbus::Result<()> {
+    match toggle {
+        Toggle::Connect(path) => call(connection, BLUEZ, path, DEVICE, "Connect", &()).await,
+        Toggle::Disconnect(path) => call(connection, BLUEZ, path, DEVICE, "Disconnect", &()).await,
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,667 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+use crate::{dbus::call, send};
+use futures::StreamExt;
+use pop_launcher::*;
+use std::borrow::Cow;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use zbus::Connection;
+use zvariant::OwnedObjectPath;
+
+mod bluez;
+mod networkmanager;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Wifi,
+    Vpn,
+    Bluetooth,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Wifi => "Wi-Fi",
+            Kind::Vpn => "VPN",
+            Kind::Bluetooth => "Bluetooth",
+        }
+    }
+
+    /// Words which list every item of a kind.
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Kind::Wifi => &["wifi", "wi-fi", "wireless", "network"],
+            Kind::Vpn => &["vpn", "network"],
+            Kind::Bluetooth => &["bluetooth"],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Connected,
+    Connecting,
+    Disconnected,
+}
+
+impl State {
+    fn label(self) -> &'static str {
+        match self {
+            State::Connected => "Connected",
+            State::Connecting => "Connecting",
+            State::Disconnected => "Disconnected",
+        }
+    }
+}
+
+/// What activating an item does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Toggle {
+    /// Activates a saved NetworkManager connection.
+    Activate {
+        connection: OwnedObjectPath,
+        device: OwnedObjectPath,
+        specific_object: OwnedObjectPath,
+    },
+    /// Connects to a Wi-Fi network without a saved connection.
+    AddAndActivate {
+        device: OwnedObjectPath,
+        access_point: OwnedObjectPath,
+    },
+    /// Deactivates an active NetworkManager connection.
+    Deactivate(OwnedObjectPath),
+    /// Connects a Bluetooth device.
+    Connect(OwnedObjectPath),
+    /// Disconnects a Bluetooth device.
+    Disconnect(OwnedObjectPath),
+}
+
+#[derive(Debug)]
+pub struct Item {
+    pub kind: Kind,
+    pub name: String,
+    pub state: State,
+    /// Signal strength and security of a Wi-Fi network.
+    pub detail: Option<String>,
+    pub icon: Cow<'static, str>,
+    pub toggle: Toggle,
+}
+
+impl Item {
+    fn description(&self) -> String {
+        let mut description = [self.kind.label(), self.state.label()].join(" · ");
+
+        if let Some(detail) = self.detail.as_deref() {
+            description.push_str(" · ");
+            description.push_str(detail);
+        }
+
+        description
+    }
+
+    /// Whether the name contains every word, or a keyword of its kind is the
+    /// word or begins with it, where the word is at least three letters.
+    fn matches(&self, words: &[&str]) -> bool {
+        let name = self.name.to_ascii_lowercase();
+
+        words.iter().all(|word| {
+            name.contains(word)
+                || self.kind.keywords().iter().any(|keyword| {
+                    keyword == word || (word.len() >= 3 && keyword.starts_with(word))
+                })
+        })
+    }
+}
+
+pub async fn main() {
+    let connection = match Connection::system().await {
+        Ok(conn) => conn,
+        Err(_) => {
+            let mut out = async_stdout();
+            let _ = crate::send(&mut out, PluginResponse::Deactivate).await;
+            return;
+        }
+    };
+
+    let networkmanager = has_owner(&connection, networkmanager::NM).await;
+    let bluez = has_owner(&connection, bluez::BLUEZ).await;
+
+    if !networkmanager && !bluez {
+        let mut out = async_stdout();
+        let _ = crate::send(&mut out, PluginResponse::Deactivate).await;
+        return;
+    }
+
+    let mut app = App::new(connection, networkmanager, bluez, async_stdout());
+
+    let mut requests = json_input_stream(async_stdin());
+    while let Some(request) = requests.next().await {
+        match request {
+            Ok(request) => match request {
+                Request::Activate(id) => app.activate(id).await,
+                Request::Search(query) => app.search(&query).await,
+                Request::Exit => break,
+                _ => (),
+            },
+            Err(why) => {
+                tracing::error!("malformed JSON request: {}", why);
+            }
+        }
+    }
+}
+
+struct App<W> {
+    connection: Connection,
+    /// Whether NetworkManager is on the bus.
+    networkmanager: bool,
+    /// Whether BlueZ is on the bus.
+    bluez: bool,
+    /// Items of the last search, by the IDs of their results.
+    items: Vec<Item>,
+    /// The last query, which later queries of a search extend as they are typed.
+    query: String,
+    /// Whether the items are reloaded by the next query, which happens after
+    /// an item is toggled.
+    stale: bool,
+    tx: W,
+}
+
+impl<W: AsyncWrite + Unpin> App<W> {
+    fn new(connection: Connection, networkmanager: bool, bluez: bool, tx: W) -> Self {
+        Self {
+            connection,
+            networkmanager,
+            bluez,
+            items: Vec::new(),
+            query: String::new(),
+            stale: true,
+            tx,
+        }
+    }
+
+    async fn reload(&mut self) {
+        self.items.clear();
+
+        if self.networkmanager {
+            match networkmanager::items(&self.connection).await {
+                Ok(items) => self.items.extend(items),
+                Err(why) => tracing::error!("failed to get networks: {}", why),
+            }
+        }
+
+        if self.bluez {
+            match bluez::items(&self.connection).await {
+                Ok(items) => self.items.extend(items),
+                Err(why) => tracing::error!("failed to get bluetooth devices: {}", why),
+            }
+        }
+    }
+
+    async fn activate(&mut self, id: u32) {
+        let Some(item) = self.items.get(id as usize) else {
+            return;
+        };
+
+        send(&mut self.tx, PluginResponse::Close).await;
+        let _ = self.tx.flush().await;
+        self.stale = true;
+
+        let result = match item.kind {
+            Kind::Wifi | Kind::Vpn => networkmanager::toggle(&self.connection, &item.toggle).await,
+            Kind::Bluetooth => bluez::toggle(&self.connection, &item.toggle).await,
+        };
+
+        if let Err(why) = result {
+            tracing::error!("failed to toggle {}: {}", item.name, why);
+        }
+    }
+
+    async fn search(&mut self, query: &str) {
+        let query = query.trim().to_ascii_lowercase();
+        let words: Vec<&str> = query.split_ascii_whitespace().collect();
+
+        // Networks are scanned once per search, rather than for every key
+        // typed, where a search which does not extend the last one is new.
+        if !query.starts_with(&self.query) {
+            self.stale = true;
+        }
+
+        self.query.clone_from(&query);
+
+        if !words.is_empty() {
+            if self.stale {
+                self.reload().await;
+                self.stale = false;
+            }
+
+            for (id, item) in self.items.iter().enumerate() {
+                if !item.matches(&words) {
+                    continue;
+                }
+
+                let result = PluginSearchResult {
+                    id: id as u32,
+                    name: item.name.clone(),
+                    description: item.description(),
+                    icon: Some(IconSource::Name(item.icon.clone())),
+                    ..Default::default()
+                };
+
+                send(&mut self.tx, PluginResponse::Append(result)).await;
+            }
+        }
+
+        send(&mut self.tx, PluginResponse::Finished).await;
+        let _ = self.tx.flush().await;
+    }
+}
+
+async fn has_owner(connection: &Connection, name: &str) -> bool {
+    call(
+        connection,
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "NameHasOwner",
+        &(name,),
+    )
+    .await
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::testing::PrivateBus;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use zvariant::{ObjectPath, OwnedValue, Value};
+
+    type Calls = Arc<Mutex<Vec<String>>>;
+
+    const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+    const DEVICE_PATH: &str = "/org/freedesktop/NetworkManager/Devices/1";
+    const ACTIVE_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+    const HEADPHONES_PATH: &str = "/org/bluez/hci0/dev_00_11_22_33_44_55";
+
+    fn path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path).unwrap()
+    }
+
+    fn value<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+        OwnedValue::try_from(value.into()).unwrap()
+    }
+
+    struct MockManager {
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager")]
+    impl MockManager {
+        fn get_devices(&self) -> Vec<OwnedObjectPath> {
+            vec![path(DEVICE_PATH)]
+        }
+
+        fn activate_connection(
+            &self,
+            connection: ObjectPath<'_>,
+            device: ObjectPath<'_>,
+            specific_object: ObjectPath<'_>,
+        ) -> OwnedObjectPath {
+            self.calls.lock().unwrap().push(format!(
+                "ActivateConnection {} {} {}",
+                connection, device, specific_object
+            ));
+            path(ACTIVE_PATH)
+        }
+
+        fn add_and_activate_connection(
+            &self,
+            _settings: HashMap<String, HashMap<String, OwnedValue>>,
+            device: ObjectPath<'_>,
+            specific_object: ObjectPath<'_>,
+        ) -> (OwnedObjectPath, OwnedObjectPath) {
+            self.calls.lock().unwrap().push(format!(
+                "AddAndActivateConnection {} {}",
+                device, specific_object
+            ));
+            (path(SETTINGS_PATH), path(ACTIVE_PATH))
+        }
+
+        fn deactivate_connection(&self, active: ObjectPath<'_>) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("DeactivateConnection {}", active));
+        }
+
+        #[zbus(property)]
+        fn active_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![path(ACTIVE_PATH)]
+        }
+    }
+
+    struct MockSettings;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Settings")]
+    impl MockSettings {
+        fn list_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![
+                path("/org/freedesktop/NetworkManager/Settings/1"),
+                path("/org/freedesktop/NetworkManager/Settings/2"),
+            ]
+        }
+    }
+
+    struct MockConnection {
+        settings: fn() -> HashMap<String, HashMap<String, OwnedValue>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+    impl MockConnection {
+        fn get_settings(&self) -> HashMap<String, HashMap<String, OwnedValue>> {
+            (self.settings)()
+        }
+    }
+
+    struct MockActive;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+    impl MockActive {
+        #[zbus(property)]
+        fn connection(&self) -> OwnedObjectPath {
+            path("/org/freedesktop/NetworkManager/Settings/2")
+        }
+
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            2
+        }
+    }
+
+    struct MockDevice;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Device")]
+    impl MockDevice {
+        #[zbus(property)]
+        fn device_type(&self) -> u32 {
+            2
+        }
+    }
+
+    struct MockWireless {
+        scans: Calls,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+    impl MockWireless {
+        fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+            self.scans.lock().unwrap().push("GetAllAccessPoints".into());
+            (1..=3)
+                .map(|n| path(&format!("/org/freedesktop/NetworkManager/AccessPoint/{n}")))
+                .collect()
+        }
+    }
+
+    struct MockAccessPoint {
+        ssid: &'static str,
+        strength: u8,
+        rsn_flags: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl MockAccessPoint {
+        #[zbus(property)]
+        fn ssid(&self) -> Vec<u8> {
+            self.ssid.as_bytes().to_vec()
+        }
+
+        #[zbus(property)]
+        fn strength(&self) -> u8 {
+            self.strength
+        }
+
+        #[zbus(property)]
+        fn rsn_flags(&self) -> u32 {
+            self.rsn_flags
+        }
+    }
+
+    struct MockBluetoothDevice {
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.bluez.Device1")]
+    impl MockBluetoothDevice {
+        fn connect(&self) {
+            self.calls.lock().unwrap().push("Connect".into());
+        }
+
+        #[zbus(property)]
+        fn alias(&self) -> &str {
+            "Headphones"
+        }
+
+        #[zbus(property)]
+        fn icon(&self) -> &str {
+            "audio-headphones"
+        }
+
+        #[zbus(property)]
+        fn paired(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn connected(&self) -> bool {
+            false
+        }
+    }
+
+    fn vpn_settings() -> HashMap<String, HashMap<String, OwnedValue>> {
+        HashMap::from([(
+            "connection".into(),
+            HashMap::from([
+                ("id".into(), value("Office VPN")),
+                ("type".into(), value("vpn")),
+            ]),
+        )])
+    }
+
+    fn wifi_settings() -> HashMap<String, HashMap<String, OwnedValue>> {
+        HashMap::from([
+            (
+                "connection".into(),
+                HashMap::from([
+                    ("id".into(), value("Home")),
+                    ("type".into(), value("802-11-wireless")),
+                ]),
+            ),
+            (
+                "802-11-wireless".into(),
+                HashMap::from([("ssid".into(), value(b"Home".to_vec()))]),
+            ),
+        ])
+    }
+
+    fn results(out: &[u8]) -> Vec<(String, String)> {
+        String::from_utf8_lossy(out)
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line).unwrap() {
+                PluginResponse::Append(result) => Some((result.name, result.description)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn items_match_whole_keywords_or_prefixes() {
+        let item = |kind| Item {
+            kind,
+            name: String::from("Office"),
+            state: State::Disconnected,
+            detail: None,
+            icon: Cow::Borrowed("network-vpn"),
+            toggle: Toggle::Deactivate(path(ACTIVE_PATH)),
+        };
+
+        let vpn = item(Kind::Vpn);
+        assert!(vpn.matches(&["vpn"]));
+        assert!(vpn.matches(&["net"]));
+        assert!(vpn.matches(&["off"]));
+        assert!(vpn.matches(&["vpn", "office"]));
+        assert!(!vpn.matches(&["v"]));
+        assert!(!vpn.matches(&["vp"]));
+        assert!(!vpn.matches(&["ne"]));
+        assert!(!vpn.matches(&["vpn", "home"]));
+
+        let bluetooth = item(Kind::Bluetooth);
+        assert!(bluetooth.matches(&["blue"]));
+        assert!(!bluetooth.matches(&["b"]));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon"]
+    async fn toggles_connections_on_private_bus() {
+        let bus = PrivateBus::new().await;
+
+        let calls = Calls::default();
+        let scans = Calls::default();
+
+        let access_point = |n: u32, ssid, strength, rsn_flags| {
+            (
+                format!("/org/freedesktop/NetworkManager/AccessPoint/{n}"),
+                MockAccessPoint {
+                    ssid,
+                    strength,
+                    rsn_flags,
+                },
+            )
+        };
+
+        let mut builder = bus
+            .builder()
+            .name(networkmanager::NM)
+            .unwrap()
+            .serve_at(
+                networkmanager::NM_PATH,
+                MockManager {
+                    calls: calls.clone(),
+                },
+            )
+            .unwrap()
+            .serve_at(SETTINGS_PATH, MockSettings)
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/NetworkManager/Settings/1",
+                MockConnection {
+                    settings: vpn_settings,
+                },
+            )
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/NetworkManager/Settings/2",
+                MockConnection {
+                    settings: wifi_settings,
+                },
+            )
+            .unwrap()
+            .serve_at(ACTIVE_PATH, MockActive)
+            .unwrap()
+            .serve_at(DEVICE_PATH, MockDevice)
+            .unwrap()
+            .serve_at(
+                DEVICE_PATH,
+                MockWireless {
+                    scans: scans.clone(),
+                },
+            )
+            .unwrap();
+
+        for (path, access_point) in [
+            access_point(1, "Home", 80, 0x188),
+            access_point(2, "Cafe", 40, 0),
+            access_point(3, "Cafe", 30, 0),
+        ] {
+            builder = builder.serve_at(path, access_point).unwrap();
+        }
+
+        let _networkmanager = builder.build().await.unwrap();
+
+        let _bluez = bus
+            .builder()
+            .name(bluez::BLUEZ)
+            .unwrap()
+            .serve_at("/", zbus::fdo::ObjectManager)
+            .unwrap()
+            .serve_at(
+                HEADPHONES_PATH,
+                MockBluetoothDevice {
+                    calls: calls.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let connection = bus.connect().await;
+
+        assert!(has_owner(&connection, networkmanager::NM).await);
+        assert!(!has_owner(&connection, "org.example.Missing").await);
+
+        let mut app = App::new(connection, true, true, Vec::new());
+
+        app.search("vpn").await;
+        assert_eq!(
+            results(&app.tx),
+            [("Office VPN".to_owned(), "VPN · Disconnected".to_owned())]
+        );
+        app.activate(0).await;
+
+        app.tx.clear();
+        app.search("wifi").await;
+        assert_eq!(
+            results(&app.tx),
+            [
+                (
+                    "Home".to_owned(),
+                    "Wi-Fi · Connected · 80% · Secured".to_owned()
+                ),
+                ("Cafe".to_owned(), "Wi-Fi · Disconnected · 40%".to_owned()),
+            ]
+        );
+        app.activate(1).await;
+        app.activate(2).await;
+
+        app.tx.clear();
+        app.search("head").await;
+        assert_eq!(
+            results(&app.tx),
+            [(
+                "Headphones".to_owned(),
+                "Bluetooth · Disconnected".to_owned()
+            )]
+        );
+        app.activate(3).await;
+
+        // Networks are scanned once per search, and again once toggled.
+        assert_eq!(scans.lock().unwrap().len(), 3);
+        for query in ["c", "ca", "caf"] {
+            app.search(query).await;
+        }
+        assert_eq!(scans.lock().unwrap().len(), 4);
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "ActivateConnection /org/freedesktop/NetworkManager/Settings/1 / /",
+                "DeactivateConnection /org/freedesktop/NetworkManager/ActiveConnection/1",
+                "AddAndActivateConnection /org/freedesktop/NetworkManager/Devices/1 \
+                 /org/freedesktop/NetworkManager/AccessPoint/2",
+                "Connect",
+            ]
+        );
+    }
+}
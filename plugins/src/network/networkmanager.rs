@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Wi-Fi networks and VPN connections from NetworkManager.
+
+use super::{Item, Kind, State, Toggle};
+use crate::dbus::{call, get_all};
+use std::{borrow::Cow, collections::HashMap};
+use zbus::Connection;
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+pub const NM: &str = "org.freedesktop.NetworkManager";
+pub const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+const SETTINGS: &str = "org.freedesktop.NetworkManager.Settings";
+const SETTINGS_CONNECTION: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+const ACTIVE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const DEVICE: &str = "org.freedesktop.NetworkManager.Device";
+const WIRELESS: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const ACCESS_POINT: &str = "org.freedesktop.NetworkManager.AccessPoint";
+
+const DEVICE_TYPE_WIFI: u32 = 2;
+const ACTIVE_STATE_ACTIVATED: u32 = 2;
+
+type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+pub async fn items(connection: &Connection) -> zbus::Result<Vec<Item>> {
+    let mut manager = get_all(connection, NM, NM_PATH, NM).await?;
+
+    // Active connections and their states, by the path of their settings.
+    let mut active = HashMap::new();
+
+    let active_paths = manager
+        .remove("ActiveConnections")
+        .and_then(|value| Vec::<OwnedObjectPath>::try_from(value).ok())
+        .unwrap_or_default();
+
+    for path in active_paths {
+        let Ok(mut properties) = get_all(connection, NM, &path, ACTIVE).await else {
+            continue;
+        };
+
+        let settings = properties
+            .remove("Connection")
+            .and_then(|value| OwnedObjectPath::try_from(value).ok());
+
+        let state = match properties.get("State").map(u32::try_from) {
+            Some(Ok(ACTIVE_STATE_ACTIVATED)) => State::Connected,
+            _ => State::Connecting,
+        };
+
+        if let Some(settings) = settings {
+            active.insert(settings, (path, state));
+        }
+    }
+
+    let mut items = Vec::new();
+
+    // Saved Wi-Fi connections by their SSIDs.
+    let mut saved_wifi = HashMap::new();
+
+    let saved: Vec<OwnedObjectPath> = call(
+        connection,
+        NM,
+        SETTINGS_PATH,
+        SETTINGS,
+        "ListConnections",
+        &(),
+    )
+    .await?;
+
+    for path in saved {
+        let Ok(mut settings) = call::<_, Settings>(
+            connection,
+            NM,
+            &path,
+            SETTINGS_CONNECTION,
+            "GetSettings",
+            &(),
+        )
+        .await
+        else {
+            continue;
+        };
+
+        let mut section = settings.remove("connection").unwrap_or_default();
+        let string =
+            |value: Option<OwnedValue>| value.and_then(|value| String::try_from(value).ok());
+
+        let id = string(section.remove("id")).unwrap_or_default();
+
+        match string(section.remove("type")).as_deref() {
+            Some("vpn" | "wireguard") => {
+                let (state, toggle) = match active.get(&path) {
+                    Some((active, state)) => (*state, Toggle::Deactivate(active.clone())),
+                    None => (
+                        State::Disconnected,
+                        Toggle::Activate {
+                            connection: path.clone(),
+                            device: root(),
+                            specific_object: root(),
+                        },
+                    ),
+                };
+
+                items.push(Item {
+                    kind: Kind::Vpn,
+                    name: id,
+                    state,
+                    detail: None,
+                    icon: Cow::Borrowed("network-vpn-symbolic"),
+                    toggle,
+                });
+            }
+
+            Some("802-11-wireless") => {
+                let ssid = settings
+                    .get_mut("802-11-wireless")
+                    .and_then(|wireless| wireless.remove("ssid"))
+                    .and_then(|value| Vec::<u8>::try_from(value).ok());
+
+                if let Some(ssid) = ssid {
+                    saved_wifi.insert(ssid, path);
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    let devices: Vec<OwnedObjectPath> =
+        call(connection, NM, NM_PATH, NM, "GetDevices", &()).await?;
+
+    for device in devices {
+        let device_type = get_all(connection, NM, &device, DEVICE)
+            .await
+            .ok()
+            .and_then(|properties| properties.get("DeviceType").map(u32::try_from))
+            .and_then(Result::ok);
+
+        if device_type != Some(DEVICE_TYPE_WIFI) {
+            continue;
+        }
+
+        let access_points: Vec<OwnedObjectPath> =
+            match call(connection, NM, &device, WIRELESS, "GetAllAccessPoints", &()).await {
+                Ok(access_points) => access_points,
+                Err(_) => continue,
+            };
+
+        // The strongest access point of each network, by its SSID.
+        let mut networks: HashMap<Vec<u8>, (u8, bool, OwnedObjectPath)> = HashMap::new();
+
+        for access_point in access_points {
+            let Ok(mut properties) = get_all(connection, NM, &access_point, ACCESS_POINT).await
+            else {
+                continue;
+            };
+
+            let ssid = properties
+                .remove("Ssid")
+                .and_then(|value| Vec::<u8>::try_from(value).ok())
+                .unwrap_or_default();
+
+            // Hidden networks have no name to show.
+            if ssid.is_empty() {
+                continue;
+            }
+
+            let strength = properties
+                .get("Strength")
+                .and_then(|value| u8::try_from(value).ok())
+                .unwrap_or(0);
+
+            let secured = ["Flags", "WpaFlags", "RsnFlags"].iter().any(|flags| {
+                properties
+                    .get(*flags)
+                    .and_then(|value| u32::try_from(value).ok())
+                    .is_some_and(|flags| flags != 0)
+            });
+
+            if networks
+                .get(&ssid)
+                .is_none_or(|(strongest, ..)| strength > *strongest)
+            {
+                networks.insert(ssid, (strength, secured, access_point));
+            }
+        }
+
+        let mut networks: Vec<_> = networks.into_iter().collect();
+        networks.sort_by(|(_, (a, ..)), (_, (b, ..))| b.cmp(a));
+
+        for (ssid, (strength, secured, access_point)) in networks {
+            let saved = saved_wifi.get(&ssid);
+
+            let (state, toggle) = match saved.and_then(|saved| active.get(saved)) {
+                Some((active, state)) => (*state, Toggle::Deactivate(active.clone())),
+                None => (
+                    State::Disconnected,
+                    match saved {
+                        Some(saved) => Toggle::Activate {
+                            connection: saved.clone(),
+                            device: device.clone(),
+                            specific_object: access_point,
+                        },
+                        None => Toggle::AddAndActivate {
+                            device: device.clone(),
+                            access_point,
+                        },
+                    },
+                ),
+            };
+
+            let mut detail = format!("{}%", strength);
+
+            if secured {
+                detail.push_str(" · Secured");
+            }
+
+            items.push(Item {
+                kind: Kind::Wifi,
+                name: String::from_utf8_lossy(&ssid).into_owned(),
+                state,
+                detail: Some(detail),
+                icon: Cow::Borrowed(signal_icon(strength)),
+                toggle,
+            });
+        }
+    }
+
+    Ok(items)
+}
+
+pub async fn toggle(connection: &Connection, toggle: &Toggle) -> zbus::Result<()> {
+    match toggle {
+        Toggle::Activate {
+            connection: settings,
+            device,
+            specific_object,
+        } => {
+            call::<_, OwnedObjectPath>(
+                connection,
+                NM,
+                NM_PATH,
+                NM,
+                "ActivateConnection",
+                &(settings, device, specific_object),
+            )
+            .await?;
+        }
+
+        Toggle::AddAndActivate {
+            device,
+            access_point,
+        } => {
+            // NetworkManager fills in the settings from the access point, and
+            // asks the secret agent of the session for a password if needed.
+            call::<_, (OwnedObjectPath, OwnedObjectPath)>(
+                connection,
+                NM,
+                NM_PATH,
+                NM,
+                "AddAndActivateConnection",
+                &(Settings::new(), device, access_point),
+            )
+            .await?;
+        }
+
+        Toggle::Deactivate(active) => {
+            call::<_, ()>(
+                connection,
+                NM,
+                NM_PATH,
+                NM,
+                "DeactivateConnection",
+                &(active,),
+            )
+            .await?;
+        }
+
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// The object path which NetworkManager takes as an unspecified object.
+fn root() -> OwnedObjectPath {
+    OwnedObjectPath::try_from("/").expect("/ is a valid object path")
+}
+
+fn signal_icon(strength: u8) -> &'static str {
+    match strength {
+        80.. => "network-wireless-signal-excellent-symbolic",
+        55.. => "network-wireless-signal-good-symbolic",
+        30.. => "network-wireless-signal-ok-symbolic",
+        5.. => "network-wireless-signal-weak-symbolic",
+        _ => "network-wireless-signal-none-symbolic",
+    }
+}
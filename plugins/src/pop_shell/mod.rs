@@ -14,6 +14,10 @@ use zvariant::{Signature, Type};
 mod config;
 pub use config::{Config, load};
 
+/// The pop-shell D-Bus API, which lists windows by `WindowList`, and may only
+/// focus, highlight, or close them. It has no methods to minimize, maximize,
+/// fullscreen, or move windows, and lists no workspaces or outputs, so these
+/// windows have no context actions nor a `ws:` filter.
 const DEST: &str = "com.System76.PopShell";
 const PATH: &str = "/com/System76/PopShell";
 
@@ -163,6 +167,10 @@ impl<W: AsyncWrite + Unpin> App<W> {
                     description: item.description.clone(),
                     icon: Some(IconSource::Name(icon_name)),
                     window: Some(item.entity),
+                    app_id: item
+                        .desktop_entry
+                        .strip_suffix(".desktop")
+                        .map(ToOwned::to_owned),
                     ..Default::default()
                 }),
             )
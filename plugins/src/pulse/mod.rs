@@ -1,51 +1,72 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
-use async_pidfd::AsyncPidFd;
 use futures::prelude::*;
 use pop_launcher::*;
-use std::io;
+use std::{
+    borrow::Cow,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+use tokio::io::AsyncWrite;
+
+mod pactl;
+
+use pactl::{Device, Kind};
+
+/// Change in volume of the `Volume Up` and `Volume Down` selections.
+const VOLUME_STEP: &str = "5%";
+
+/// Highest volume which may be set, as PulseAudio allows amplifying beyond 100%.
+const VOLUME_MAX: u32 = 150;
 
 struct Selection {
-    pub id: u32,
-    pub name: String,
-    pub description: String,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub action: fn(usize) -> Action,
 }
 
-pub struct App {
-    selections: Vec<Selection>,
-    out: tokio::io::Stdout,
+const SELECTIONS: &[Selection] = &[
+    Selection {
+        name: "Toggle Mute",
+        description: "Silence and unsilence the default audio sink",
+        action: Action::Mute,
+    },
+    Selection {
+        name: "Volume Up",
+        description: "Raise volume 5%",
+        action: |device| Action::Volume(device, ["+", VOLUME_STEP].concat()),
+    },
+    Selection {
+        name: "Volume Down",
+        description: "Lower volume 5%",
+        action: |device| Action::Volume(device, ["-", VOLUME_STEP].concat()),
+    },
+];
+
+/// What activating a result does to the device at an index of `App::devices`.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Default(usize),
+    Mute(usize),
+    /// Sets the volume of the device to a percentage, or changes it by one.
+    Volume(usize, String),
 }
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            out: async_stdout(),
-            selections: vec![
-                Selection {
-                    id: 0,
-                    name: "Toggle Mute".into(),
-                    description: "Silence and unsilence the default audio sink".into(),
-                },
-                Selection {
-                    id: 1,
-                    name: "Volume Up".into(),
-                    description: "Raise volume 5%".into(),
-                },
-                Selection {
-                    id: 2,
-                    name: "Volume Down".into(),
-                    description: "Lower volume 5%".into(),
-                },
-            ],
-        }
-    }
+pub struct App<W> {
+    /// The `pactl` program, which lists and controls the devices.
+    pactl: PathBuf,
+    devices: Vec<Device>,
+    /// Actions of the results of the last search, by their IDs.
+    actions: Vec<Action>,
+    out: W,
 }
 
 pub async fn main() {
     let mut requests = json_input_stream(async_stdin());
 
-    let mut app = App::default();
+    let mut app = App::new("pactl", async_stdout());
 
     while let Some(result) = requests.next().await {
         match result {
@@ -62,98 +83,372 @@ pub async fn main() {
     }
 }
 
-impl App {
+impl<W: AsyncWrite + Unpin> App<W> {
+    fn new(pactl: impl Into<PathBuf>, out: W) -> Self {
+        Self {
+            pactl: pactl.into(),
+            devices: Vec::new(),
+            actions: Vec::new(),
+            out,
+        }
+    }
+
     async fn activate(&mut self, id: u32) {
-        let (cmd, arg1, arg2) = match id {
-            0 => ("pactl", "set-sink-mute", "toggle"),
-            1 => ("pactl", "set-sink-volume", "+5%"),
-            2 => ("pactl", "set-sink-volume", "-5%"),
-            _ => return,
+        let Some(action) = self.actions.get(id as usize) else {
+            return;
         };
 
-        let mut handles = Vec::new();
+        let (Action::Default(device) | Action::Mute(device) | Action::Volume(device, _)) = action;
 
-        let sinks = pactl_sinks();
+        let Some(device) = self.devices.get(*device) else {
+            return;
+        };
 
-        while let Ok(id) = sinks.recv_async().await {
-            handles.push(tokio::spawn(async move {
-                let args = &[arg1, id.as_str(), arg2];
-                let _ = command_spawn(cmd, args).await;
-            }));
-        }
+        let kind = device.kind.command();
+
+        let (command, arg) = match action {
+            Action::Default(_) => (format!("set-default-{}", kind), None),
+            Action::Mute(_) => (format!("set-{}-mute", kind), Some("toggle")),
+            Action::Volume(_, volume) => (format!("set-{}-volume", kind), Some(volume.as_str())),
+        };
+
+        let mut args = vec![command.as_str(), device.name.as_str()];
+        args.extend(arg);
 
-        for handle in handles {
-            let _ = handle.await;
+        if let Err(why) = command_spawn(&self.pactl, &args).await {
+            tracing::error!("failed to run pactl: {}", why);
         }
     }
 
     async fn search(&mut self, query: String) {
+        self.actions.clear();
+
+        let query = query.trim().to_ascii_lowercase();
+
         if !query.is_empty() {
-            for selection in filter(&self.selections, &query.to_ascii_lowercase()) {
-                crate::send(
-                    &mut self.out,
-                    PluginResponse::Append(PluginSearchResult {
-                        id: selection.id,
-                        name: selection.name.clone(),
-                        description: selection.description.clone(),
-                        ..Default::default()
-                    }),
-                )
-                .await;
+            self.devices = pactl::devices(&self.pactl).await;
+
+            let mut words = query.split_ascii_whitespace();
+            let command = words.next().unwrap_or_default();
+            let words: Vec<&str> = words.collect();
+
+            if matches!(command, "vol" | "volume")
+                && let Some((volume, filter)) = words.split_first()
+                && let Some(volume) = parse_volume(volume)
+            {
+                for device in self.targets(filter) {
+                    let name = format!("Set Volume to {}%", volume);
+                    let action = Action::Volume(device, format!("{}%", volume));
+                    self.append(name, device, action).await;
+                }
+            } else if matches!(command, "mute" | "unmute") {
+                for device in self.targets(&words) {
+                    let name = if self.devices[device].mute {
+                        "Unmute"
+                    } else {
+                        "Mute"
+                    };
+
+                    self.append(name.to_owned(), device, Action::Mute(device))
+                        .await;
+                }
+            } else {
+                self.search_devices(&query).await;
             }
         }
 
         crate::send(&mut self.out, PluginResponse::Finished).await;
     }
+
+    /// Lists the devices and selections which match the words of the query.
+    async fn search_devices(&mut self, query: &str) {
+        let words: Vec<&str> = query
+            .split_ascii_whitespace()
+            .filter(|word| !matches!(*word, "vol" | "volume"))
+            .collect();
+
+        for (index, device) in self.devices.iter().enumerate() {
+            let description = device.description.to_ascii_lowercase();
+            let matched = words.iter().all(|word| {
+                description.contains(word) || kind_keywords(device.kind).contains(word)
+            });
+
+            if !matched {
+                continue;
+            }
+
+            let mut state = vec![
+                if device.default {
+                    Cow::Owned(format!(
+                        "Default {}",
+                        device.kind.label().to_ascii_lowercase()
+                    ))
+                } else {
+                    Cow::Borrowed(device.kind.label())
+                },
+                Cow::Owned(format!("{}%", device.volume)),
+            ];
+
+            if device.mute {
+                state.push(Cow::Borrowed("muted"));
+            }
+
+            // The default device is muted when activated, and others become the default.
+            let (action, hint) = if device.default {
+                let hint = if device.mute { "unmute" } else { "mute" };
+                (Action::Mute(index), hint)
+            } else {
+                (Action::Default(index), "make default")
+            };
+
+            state.push(Cow::Borrowed(hint));
+
+            let result = PluginSearchResult {
+                id: self.actions.len() as u32,
+                name: device.description.clone(),
+                description: state.join(" · "),
+                icon: Some(IconSource::Name(Cow::Borrowed(icon(device)))),
+                ..Default::default()
+            };
+
+            self.actions.push(action);
+            crate::send(&mut self.out, PluginResponse::Append(result)).await;
+        }
+
+        let Some(sink) = self.default_device(Kind::Sink) else {
+            return;
+        };
+
+        for selection in SELECTIONS {
+            let name = selection.name.to_ascii_lowercase();
+            let description = selection.description.to_ascii_lowercase();
+
+            if !(name.contains(query) || description.contains(query)) {
+                continue;
+            }
+
+            let result = PluginSearchResult {
+                id: self.actions.len() as u32,
+                name: selection.name.to_owned(),
+                description: selection.description.to_owned(),
+                ..Default::default()
+            };
+
+            self.actions.push((selection.action)(sink));
+            crate::send(&mut self.out, PluginResponse::Append(result)).await;
+        }
+    }
+
+    async fn append(&mut self, name: String, device: usize, action: Action) {
+        let device = &self.devices[device];
+
+        let result = PluginSearchResult {
+            id: self.actions.len() as u32,
+            name,
+            description: device.description.clone(),
+            icon: Some(IconSource::Name(Cow::Borrowed(icon(device)))),
+            ..Default::default()
+        };
+
+        self.actions.push(action);
+        crate::send(&mut self.out, PluginResponse::Append(result)).await;
+    }
+
+    fn default_device(&self, kind: Kind) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|device| device.kind == kind && device.default)
+    }
+
+    /// Devices which a command applies to, being the default output without a
+    /// filter, the default input or output when named as such, or else the
+    /// devices whose descriptions match every word of the filter.
+    fn targets(&self, filter: &[&str]) -> Vec<usize> {
+        match filter {
+            [] => self.default_device(Kind::Sink).into_iter().collect(),
+            [word] if kind_keywords(Kind::Source).contains(word) => {
+                self.default_device(Kind::Source).into_iter().collect()
+            }
+            [word] if kind_keywords(Kind::Sink).contains(word) => {
+                self.default_device(Kind::Sink).into_iter().collect()
+            }
+            words => self
+                .devices
+                .iter()
+                .enumerate()
+                .filter(|(_, device)| {
+                    let description = device.description.to_ascii_lowercase();
+                    words.iter().all(|word| description.contains(word))
+                })
+                .map(|(index, _)| index)
+                .collect(),
+        }
+    }
+}
+
+/// Words which refer to every device of a kind.
+fn kind_keywords(kind: Kind) -> &'static [&'static str] {
+    match kind {
+        Kind::Sink => &["output", "speaker", "speakers", "audio", "sound"],
+        Kind::Source => &["input", "mic", "microphone", "audio", "sound"],
+    }
 }
 
-fn filter<'a>(
-    selections: &'a [Selection],
-    query: &'a str,
-) -> impl Iterator<Item = &'a Selection> + 'a {
-    selections.iter().filter(move |selection| {
-        selection.name.to_ascii_lowercase().contains(query)
-            || selection.description.to_ascii_lowercase().contains(query)
-    })
+fn icon(device: &Device) -> &'static str {
+    match (device.kind, device.mute) {
+        (Kind::Sink, false) => "audio-speakers",
+        (Kind::Sink, true) => "audio-volume-muted",
+        (Kind::Source, false) => "audio-input-microphone",
+        (Kind::Source, true) => "microphone-sensitivity-muted",
+    }
 }
 
-async fn command_spawn(cmd: &str, args: &[&str]) -> io::Result<()> {
-    use std::process::{Command, Stdio};
+fn parse_volume(word: &str) -> Option<u32> {
+    word.strip_suffix('%')
+        .unwrap_or(word)
+        .parse::<u32>()
+        .ok()
+        .map(|volume| volume.min(VOLUME_MAX))
+}
 
-    let child = Command::new(cmd)
+async fn command_spawn(cmd: &Path, args: &[&str]) -> io::Result<()> {
+    tokio::process::Command::new(cmd)
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .args(args)
-        .spawn()?;
+        .status()
+        .await
+        .map(|_| ())
+}
 
-    let _ = AsyncPidFd::from_pid(child.id() as i32)?.wait().await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::Once;
 
-    Ok(())
-}
+    const INFO: &str = r#"{"default_sink_name":"speakers","default_source_name":"mic"}"#;
 
-fn pactl_sinks() -> flume::Receiver<String> {
-    let (tx, rx) = flume::bounded(4);
-
-    tokio::spawn(async move {
-        let child = tokio::process::Command::new("pactl")
-            .env("LANG", "C")
-            .args(["list", "sinks"])
-            .stdout(std::process::Stdio::piped())
-            .spawn();
-
-        if let Ok(mut child) = child {
-            if let Some(stdout) = child.stdout.take() {
-                use tokio::io::AsyncBufReadExt;
-                let mut lines = tokio::io::BufReader::new(stdout).lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    if let Some(stripped) = line.strip_prefix("Sink #") {
-                        let _ = tx.send_async(stripped.trim().to_owned()).await;
-                    }
-                }
-            }
-        }
-    });
+    const SINKS: &str = r#"[
+        {"index":1,"name":"speakers","description":"Built-in Speakers","mute":false,
+         "volume":{"front-left":{"value":26214,"value_percent":"40%"},"front-right":{"value":26214,"value_percent":"40%"}}},
+        {"index":2,"name":"hdmi","description":"HDMI Output","mute":true,
+         "volume":{"mono":{"value":65536,"value_percent":"100%"}}}
+    ]"#;
 
-    rx
+    const SOURCES: &str = r#"[
+        {"index":1,"name":"speakers.monitor","description":"Monitor of Built-in Speakers","mute":false,
+         "volume":{"mono":{"value":65536,"value_percent":"100%"}},"monitor_of_sink":"speakers"},
+        {"index":2,"name":"mic","description":"Built-in Microphone","mute":false,
+         "volume":{"mono":{"value":32768,"value_percent":"50%"}},"monitor_of_sink":null}
+    ]"#;
+
+    /// Writes a fake `pactl` into the returned directory, which prints the
+    /// devices above and records the commands that change them in `log`.
+    fn fake_pactl() -> PathBuf {
+        static ONCE: Once = Once::new();
+
+        let dir = std::env::temp_dir().join(format!("pop-launcher-pulse-{}", std::process::id()));
+
+        ONCE.call_once(|| {
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let script = format!(
+                "#!/bin/sh\n\
+                 [ \"$1\" = --format=json ] && shift\n\
+                 case \"$*\" in\n\
+                 info) echo '{INFO}' ;;\n\
+                 'list sinks') echo '{SINKS}' ;;\n\
+                 'list sources') echo '{SOURCES}' ;;\n\
+                 *) echo \"$*\" >> \"{}\" ;;\n\
+                 esac\n",
+                dir.join("log").display()
+            );
+
+            let pactl = dir.join("pactl");
+            std::fs::write(&pactl, script).unwrap();
+
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&pactl, std::fs::Permissions::from_mode(0o755)).unwrap();
+        });
+
+        dir
+    }
+
+    fn results(out: &[u8]) -> Vec<(String, String)> {
+        String::from_utf8_lossy(out)
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line).unwrap() {
+                PluginResponse::Append(result) => Some((result.name, result.description)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn lists_devices() {
+        let pactl = fake_pactl().join("pactl");
+
+        let devices = pactl::devices(&pactl).await;
+        let summary: Vec<_> = devices
+            .iter()
+            .map(|device| {
+                (
+                    device.name.as_str(),
+                    device.volume,
+                    device.mute,
+                    device.default,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            [
+                ("speakers", 40, false, true),
+                ("hdmi", 100, true, false),
+                ("mic", 50, false, true),
+            ]
+        );
+
+        let mut app = App::new(pactl, Vec::new());
+        app.search("hdmi".into()).await;
+
+        assert_eq!(
+            results(&app.out),
+            [(
+                "HDMI Output".to_owned(),
+                "Output · 100% · muted · make default".to_owned()
+            )]
+        );
+        assert_eq!(app.actions, [Action::Default(1)]);
+    }
+
+    #[tokio::test]
+    async fn controls_devices() {
+        let dir = fake_pactl();
+        let log = dir.join("log");
+
+        let mut app = App::new(dir.join("pactl"), Vec::new());
+
+        app.search("vol 40".into()).await;
+        assert_eq!(app.actions, [Action::Volume(0, "40%".into())]);
+        app.activate(0).await;
+
+        app.search("mute mic".into()).await;
+        assert_eq!(app.actions, [Action::Mute(2)]);
+        app.activate(0).await;
+
+        app.search("output".into()).await;
+        assert_eq!(app.actions, [Action::Mute(0), Action::Default(1)]);
+        app.activate(1).await;
+
+        assert_eq!(
+            std::fs::read_to_string(log).unwrap(),
+            "set-sink-volume speakers 40%\n\
+             set-source-mute mic toggle\n\
+             set-default-sink hdmi\n"
+        );
+    }
 }
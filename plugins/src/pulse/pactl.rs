@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Enumerates audio devices from the JSON output of `pactl`, which is
+//! provided by both PulseAudio and PipeWire.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, io, path::Path, process::Stdio};
+
+/// Volume of a channel at 100%.
+const VOLUME_NORM: u64 = 0x10000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Sink,
+    Source,
+}
+
+impl Kind {
+    /// Name of the kind within pactl commands, such as `set-sink-volume`.
+    pub fn command(self) -> &'static str {
+        match self {
+            Kind::Sink => "sink",
+            Kind::Source => "source",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Sink => "Output",
+            Kind::Source => "Input",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    pub kind: Kind,
+    pub name: String,
+    pub description: String,
+    /// Average volume of its channels, as a percentage.
+    pub volume: u32,
+    pub mute: bool,
+    pub default: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info {
+    default_sink_name: Option<String>,
+    default_source_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawDevice {
+    name: String,
+    description: String,
+    mute: bool,
+    #[serde(default)]
+    volume: BTreeMap<String, Channel>,
+    #[serde(default)]
+    monitor_of_sink: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Channel {
+    value: u64,
+}
+
+/// Sinks followed by sources, excluding the monitors of sinks.
+pub async fn devices(pactl: &Path) -> Vec<Device> {
+    let (info, sinks, sources) = futures::join!(
+        json(pactl, &["info"]),
+        json(pactl, &["list", "sinks"]),
+        json(pactl, &["list", "sources"])
+    );
+
+    let info = info
+        .ok()
+        .and_then(|json| serde_json::from_slice::<Info>(&json).ok());
+
+    let (default_sink, default_source) = match info {
+        Some(info) => (info.default_sink_name, info.default_source_name),
+        None => (None, None),
+    };
+
+    let mut devices = Vec::new();
+
+    for (kind, json, default) in [
+        (Kind::Sink, sinks, default_sink),
+        (Kind::Source, sources, default_source),
+    ] {
+        let raw = match json.map(|json| serde_json::from_slice::<Vec<RawDevice>>(&json)) {
+            Ok(Ok(raw)) => raw,
+            Ok(Err(why)) => {
+                tracing::error!("failed to parse pactl {} list: {}", kind.command(), why);
+                continue;
+            }
+            Err(why) => {
+                tracing::error!("failed to list pactl {}s: {}", kind.command(), why);
+                continue;
+            }
+        };
+
+        devices.extend(
+            raw.into_iter()
+                .filter(|device| {
+                    device
+                        .monitor_of_sink
+                        .as_deref()
+                        .is_none_or(|sink| sink == "n/a")
+                })
+                .map(|device| Device {
+                    kind,
+                    default: default.as_deref() == Some(device.name.as_str()),
+                    volume: average_volume(&device.volume),
+                    name: device.name,
+                    description: device.description,
+                    mute: device.mute,
+                }),
+        );
+    }
+
+    devices
+}
+
+fn average_volume(channels: &BTreeMap<String, Channel>) -> u32 {
+    let count = channels.len() as u64;
+
+    if count == 0 {
+        return 0;
+    }
+
+    let sum: u64 = channels.values().map(|channel| channel.value).sum();
+
+    ((sum * 100 + count * VOLUME_NORM / 2) / (count * VOLUME_NORM)) as u32
+}
+
+async fn json(pactl: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = tokio::process::Command::new(pactl)
+        .env("LANG", "C")
+        .arg("--format=json")
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "pactl exited with {}",
+            output.status
+        )));
+    }
+
+    Ok(output.stdout)
+}
@@ -4,12 +4,15 @@
 use crate::*;
 use pop_launcher::*;
 
-use flume::Sender;
+use flume::{Receiver, Sender};
 use futures::StreamExt;
+use pop_launcher::launch::TerminalConfig;
 use regex::Regex;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::time::Duration;
 use tokio::io::AsyncBufReadExt;
 use tokio::process::Command;
 
@@ -17,6 +20,16 @@ const LOCAL_PATH: &str = ".local/share/pop-launcher/scripts";
 const SYSTEM_ADMIN_PATH: &str = "/etc/pop-launcher/scripts";
 const DISTRIBUTION_PATH: &str = "/usr/lib/pop-launcher/scripts";
 
+/// How long to wait for a script whose output is notified or shown.
+const OUTPUT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for the results of a dynamic script.
+const DYNAMIC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Options of the context menu which confirms that a script should run.
+const CONFIRM: u32 = 0;
+const CANCEL: u32 = 1;
+
 pub async fn main() {
     let mut requests = json_input_stream(async_stdin());
 
@@ -28,6 +41,7 @@ pub async fn main() {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
+                Request::ActivateContext { id, context } => app.activate_context(id, context).await,
                 Request::Search(query) => app.search(&query).await,
                 Request::Exit => break,
                 _ => (),
@@ -42,6 +56,18 @@ pub async fn main() {
 
 pub struct App {
     scripts: Vec<ScriptInfo>,
+    /// Changes to the script directories since the last reload.
+    changes: Option<Receiver<HashSet<PathBuf>>>,
+    /// The last query, which scripts take their input from.
+    query: String,
+    /// Script which awaits confirmation from a follow-up context menu.
+    confirming: Option<u32>,
+    /// Output of the last script whose output is shown, whose lines replace
+    /// the results until the next search.
+    captured: Option<Vec<String>>,
+    /// Results of dynamic scripts from the last search, by the index of their
+    /// script and their name. Their IDs follow the IDs of the scripts.
+    items: Vec<(usize, String)>,
     out: tokio::io::Stdout,
 }
 
@@ -49,97 +75,258 @@ impl App {
     fn new() -> Self {
         App {
             scripts: Vec::with_capacity(16),
+            changes: crate::watcher::watch(script_dirs()),
+            query: String::new(),
+            confirming: None,
+            captured: None,
+            items: Vec::new(),
             out: async_stdout(),
         }
     }
 
     async fn activate(&mut self, id: u32) {
-        if let Some(script) = self.scripts.get(id as usize) {
-            let mut shell: String = Default::default();
-            let mut args: Vec<&OsStr> = Vec::new();
+        self.confirming = None;
 
-            let program = script
-                .interpreter
-                .as_deref()
-                .and_then(|interpreter| {
-                    // split the shebang into parts, e.g. ["/bin/bash"], or a more complex ["/usr/bin/env", "bash"]
-                    let mut parts = interpreter.split_ascii_whitespace();
+        if let Some(captured) = self.captured.as_ref() {
+            let text = match id.checked_sub(1) {
+                Some(line) => captured.get(line as usize).cloned(),
+                None => Some(captured.join("\n")),
+            };
 
-                    // first part must be the command to run, e.g. "/usr/bin/env"
-                    let command = parts.next()?;
+            if let Some(text) = text {
+                send(&mut self.out, PluginResponse::Copy(text)).await;
+            }
 
-                    for arg in parts {
-                        args.push(arg.as_ref());
-                    }
+            return;
+        }
 
-                    Some(command)
-                })
-                .or_else(|| {
-                    if let Ok(string) = std::env::var("SHELL") {
-                        shell = string;
-                        return Some(&shell);
-                    }
+        // Results of dynamic scripts are activated by their script.
+        if let Some((script, name)) = (id as usize)
+            .checked_sub(self.scripts.len())
+            .and_then(|item| self.items.get(item))
+        {
+            let (script, args) = (*script, vec![String::from("--activate"), name.clone()]);
+            self.run(script as u32, args).await;
+            return;
+        }
 
-                    None
-                })
-                .unwrap_or("sh");
+        let Some(script) = self.scripts.get(id as usize) else {
+            return;
+        };
 
-            // add the script file itself as a final arg for the interpreter
-            args.push(script.path.as_ref());
+        // Dynamic scripts are searched by typing their prefix.
+        if let Some(prefix) = script.prefix.as_ref() {
+            let fill = [prefix.as_str(), " "].concat();
+            send(&mut self.out, PluginResponse::Fill(fill)).await;
+            return;
+        }
+
+        // Prompt for the input of scripts which take it.
+        if script.args.is_some() && script.input(&self.query).is_none_or(str::is_empty) {
+            let fill = [script.name.as_str(), " "].concat();
+            send(&mut self.out, PluginResponse::Fill(fill)).await;
+            return;
+        }
 
-            send(&mut self.out, PluginResponse::Close).await;
+        if let Some(confirm) = script.confirm.as_ref() {
+            let options = vec![
+                ContextOption {
+                    id: CONFIRM,
+                    name: confirm.clone(),
+                    ..Default::default()
+                },
+                ContextOption {
+                    id: CANCEL,
+                    name: String::from("Cancel"),
+                    ..Default::default()
+                },
+            ];
 
-            let _ = Command::new(program)
-                .args(args)
-                .stdin(Stdio::null())
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn();
+            self.confirming = Some(id);
+            send(&mut self.out, PluginResponse::Context { id, options }).await;
+            return;
         }
-    }
 
-    async fn reload(&mut self) {
-        let (tx, rx) = flume::bounded::<ScriptInfo>(20);
+        self.run_with_input(id).await;
+    }
 
-        let mut queue = VecDeque::new();
+    async fn activate_context(&mut self, id: u32, context: u32) {
+        if self.confirming.take() == Some(id) && context == CONFIRM {
+            self.run_with_input(id).await;
+        }
+    }
 
-        queue.push_back(
-            dirs::home_dir()
-                .expect("user does not have home dir")
-                .join(LOCAL_PATH),
-        );
-        queue.push_back(Path::new(SYSTEM_ADMIN_PATH).to_owned());
-        queue.push_back(Path::new(DISTRIBUTION_PATH).to_owned());
+    /// Runs the script with the input from the query, for scripts which take it.
+    async fn run_with_input(&mut self, id: u32) {
+        let args = self
+            .scripts
+            .get(id as usize)
+            .filter(|script| script.args.is_some())
+            .and_then(|script| script.input(&self.query))
+            .map(split_input)
+            .unwrap_or_default();
+
+        self.run(id, args).await;
+    }
 
-        let script_sender = async move {
-            while let Some(path) = queue.pop_front() {
-                load_from(&path, &mut queue, tx.clone()).await;
+    async fn run(&mut self, id: u32, args: Vec<String>) {
+        if let Some(script) = self.scripts.get(id as usize) {
+            let mut command = if script.terminal {
+                let (program, script_args) = script.invocation();
+                let mut command = Command::from(TerminalConfig::load().command());
+                command.arg(program).args(script_args).args(args);
+                command
+            } else {
+                let mut command = script.command();
+                command.args(args);
+                command
+            };
+
+            if script.terminal || script.output == ScriptOutput::Discard {
+                send(&mut self.out, PluginResponse::Close).await;
+
+                let _ = command
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn();
+
+                return;
             }
-        };
 
-        let script_receiver = async {
-            'outer: while let Ok(script) = rx.recv_async().await {
-                tracing::debug!("appending script: {:?}", script);
-                for cached_script in &self.scripts {
-                    if cached_script.name == script.name {
-                        continue 'outer;
-                    }
+            let (status, lines) = match crate::capture_output(command, OUTPUT_TIMEOUT).await {
+                Ok(Some(output)) => {
+                    let status = match output.status.code() {
+                        Some(0) => String::from("finished"),
+                        Some(code) => format!("failed with status {}", code),
+                        None => String::from("terminated by a signal"),
+                    };
+
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+
+                    let lines: Vec<String> = stdout
+                        .lines()
+                        .chain(stderr.lines())
+                        .filter(|line| !line.trim().is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect();
+
+                    (status, lines)
                 }
-                self.scripts.push(script);
+                Ok(None) => (String::from("timed out"), Vec::new()),
+                Err(why) => (format!("failed to run: {}", why), Vec::new()),
+            };
+
+            if script.output == ScriptOutput::Notify {
+                send(&mut self.out, PluginResponse::Close).await;
+
+                let body = if lines.is_empty() {
+                    status
+                } else {
+                    lines.join("\n")
+                };
+
+                let _ = Command::new("notify-send")
+                    .args(["--app-name=Pop Launcher", "--icon"])
+                    .arg(script.icon.as_deref().unwrap_or("utilities-terminal"))
+                    .arg(&script.name)
+                    .arg(body)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn();
+
+                return;
             }
-        };
 
-        futures::future::join(script_sender, script_receiver).await;
+            send(&mut self.out, PluginResponse::Clear).await;
+
+            send(
+                &mut self.out,
+                PluginResponse::Append(PluginSearchResult {
+                    id: 0,
+                    name: script.name.clone(),
+                    description: status,
+                    icon: script
+                        .icon
+                        .as_ref()
+                        .map(|icon| IconSource::Name(icon.clone().into())),
+                    ordered: true,
+                    ..Default::default()
+                }),
+            )
+            .await;
+
+            for (id, line) in lines.iter().enumerate() {
+                send(
+                    &mut self.out,
+                    PluginResponse::Append(PluginSearchResult {
+                        id: id as u32 + 1,
+                        name: line.clone(),
+                        description: String::from("copy to clipboard"),
+                        ordered: true,
+                        ..Default::default()
+                    }),
+                )
+                .await;
+            }
+
+            send(&mut self.out, PluginResponse::Finished).await;
+
+            self.captured = Some(lines);
+        }
+    }
+
+    async fn reload(&mut self) {
+        self.scripts = load_scripts(script_dirs()).await;
+    }
+
+    /// Reloads the scripts if their directories have changed since the last reload.
+    async fn reload_changed(&mut self) {
+        let changed = self
+            .changes
+            .as_ref()
+            .is_some_and(|changes| changes.try_iter().count() != 0);
+
+        if changed {
+            self.reload().await;
+        }
     }
 
     async fn search(&mut self, query: &str) {
+        self.reload_changed().await;
+
+        query.clone_into(&mut self.query);
+        self.confirming = None;
+        self.captured = None;
+        self.items.clear();
+
         let &mut Self {
             ref scripts,
+            ref mut items,
             ref mut out,
             ..
         } = self;
         for (id, script) in scripts.iter().enumerate() {
-            let should_include = script.name.to_ascii_lowercase().contains(query)
+            if let Some(prefix) = script.prefix.as_deref()
+                && let Some(input) = strip_prefix(query, prefix)
+            {
+                search_dynamic(id, script, input, scripts.len(), items, out).await;
+                continue;
+            }
+
+            let input = script.args.as_ref().and_then(|prompt| {
+                let input = script.input(query)?;
+                Some(if input.is_empty() {
+                    prompt.clone()
+                } else {
+                    [prompt, ": ", input].concat()
+                })
+            });
+
+            let should_include = input.is_some()
+                || script.name.to_ascii_lowercase().contains(query)
                 || script.description.to_ascii_lowercase().contains(query)
                 || script.keywords.iter().any(|k| k.contains(query));
 
@@ -149,7 +336,7 @@ impl App {
                     PluginResponse::Append(PluginSearchResult {
                         id: id as u32,
                         name: script.name.clone(),
-                        description: script.description.clone(),
+                        description: input.unwrap_or_else(|| script.full_description()),
                         icon: script
                             .icon
                             .as_ref()
@@ -166,30 +353,340 @@ impl App {
     }
 }
 
+/// Appends the results that a dynamic script produces for the input.
+///
+/// Each line of its output is a result, either as a JSON `PluginSearchResult`
+/// or as a name, description, and icon name which are separated by tabs.
+async fn search_dynamic(
+    id: usize,
+    script: &ScriptInfo,
+    input: &str,
+    offset: usize,
+    items: &mut Vec<(usize, String)>,
+    out: &mut tokio::io::Stdout,
+) {
+    let mut command = script.command();
+    command.arg(input);
+
+    let output = match crate::capture_output(command, DYNAMIC_TIMEOUT).await {
+        Ok(Some(output)) => output,
+        Ok(None) => {
+            tracing::error!("dynamic script timed out: {}", script.path.display());
+            return;
+        }
+        Err(why) => {
+            tracing::error!("failed to run {}: {}", script.path.display(), why);
+            return;
+        }
+    };
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some(mut result) = parse_item(line) else {
+            continue;
+        };
+
+        result.id = (offset + items.len()) as u32;
+        result.ordered = true;
+
+        if result.icon.is_none() {
+            result.icon = script
+                .icon
+                .as_ref()
+                .map(|icon| IconSource::Name(icon.clone().into()));
+        }
+
+        items.push((id, result.name.clone()));
+        send(out, PluginResponse::Append(result)).await;
+    }
+}
+
+fn parse_item(line: &str) -> Option<PluginSearchResult> {
+    let line = line.trim_end();
+
+    if line.starts_with('{') {
+        // The ID is assigned by the plugin, and may be omitted with the description.
+        let mut value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let object = value.as_object_mut()?;
+        object.entry("id").or_insert(0.into());
+        object.entry("description").or_insert("".into());
+
+        return serde_json::from_value(value).ok();
+    }
+
+    let mut fields = line.split('\t');
+    let name = fields.next().filter(|name| !name.is_empty())?;
+
+    Some(PluginSearchResult {
+        name: name.to_owned(),
+        description: fields.next().unwrap_or_default().to_owned(),
+        icon: fields
+            .next()
+            .filter(|icon| !icon.is_empty())
+            .map(|icon| IconSource::Name(icon.to_owned().into())),
+        ..Default::default()
+    })
+}
+
+/// The rest of the query, if it begins with the prefix, ignoring case,
+/// followed by a space.
+fn strip_prefix<'a>(query: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() || !query.is_char_boundary(prefix.len().min(query.len())) {
+        return None;
+    }
+
+    let (head, rest) = query.split_at_checked(prefix.len())?;
+
+    if !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix(' ').map(str::trim)
+    }
+}
+
 #[derive(Debug, Default)]
 struct ScriptInfo {
     interpreter: Option<String>,
     name: String,
     icon: Option<String>,
     path: PathBuf,
+    /// Path of the script within its script directory.
+    relative: PathBuf,
+    /// Position of its script directory, where lower takes precedence.
+    precedence: usize,
+    /// Name of the subdirectory which the script is in.
+    category: Option<String>,
     keywords: Vec<String>,
     description: String,
+    /// Prompt for the input which the script takes from the query.
+    args: Option<String>,
+    /// Option which confirms that the script should run.
+    confirm: Option<String>,
+    /// Run the script in a terminal.
+    terminal: bool,
+    output: ScriptOutput,
+    /// Prefix of the queries which a dynamic script produces results for.
+    prefix: Option<String>,
+}
+
+impl ScriptInfo {
+    fn full_description(&self) -> String {
+        match self.category.as_deref() {
+            Some(category) if self.description.is_empty() => category.to_owned(),
+            Some(category) => format!("{} - {}", category, self.description),
+            None => self.description.clone(),
+        }
+    }
+
+    /// Input which follows the name or a keyword of the script in the query.
+    fn input<'a>(&self, query: &'a str) -> Option<&'a str> {
+        std::iter::once(&self.name)
+            .chain(self.keywords.iter())
+            .find_map(|prefix| strip_prefix(query, prefix))
+    }
+
+    /// Program which runs the script, and its arguments up to and including the script.
+    fn invocation(&self) -> (String, Vec<OsString>) {
+        let mut args = Vec::new();
+
+        let program = self
+            .interpreter
+            .as_deref()
+            .and_then(|interpreter| {
+                // split the shebang into parts, e.g. ["/bin/bash"], or a more complex ["/usr/bin/env", "bash"]
+                let mut parts = interpreter.split_ascii_whitespace();
+
+                // first part must be the command to run, e.g. "/usr/bin/env"
+                let command = parts.next()?;
+
+                for arg in parts {
+                    args.push(OsString::from(arg));
+                }
+
+                Some(command.to_owned())
+            })
+            .or_else(|| std::env::var("SHELL").ok())
+            .unwrap_or_else(|| String::from("sh"));
+
+        // add the script file itself as a final arg for the interpreter
+        args.push(self.path.clone().into_os_string());
+
+        (program, args)
+    }
+
+    fn command(&self) -> Command {
+        let (program, args) = self.invocation();
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    }
+
+    /// Reads the shebang and the fields from the comments which begin the script.
+    fn read_header<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) {
+        let shebang_re = Regex::new(r"^!\s*").unwrap();
+
+        let mut first = true;
+
+        for line in lines {
+            let Some(line) = line.strip_prefix('#') else {
+                break;
+            };
+
+            let line = line.trim();
+
+            if first {
+                first = false;
+                if shebang_re.is_match(line) {
+                    self.interpreter = Some(shebang_re.replace(line, "").to_string());
+                    continue;
+                }
+            }
+
+            if let Some(stripped) = line.strip_prefix("name:") {
+                stripped.trim_start().clone_into(&mut self.name);
+            } else if let Some(stripped) = line.strip_prefix("description:") {
+                stripped.trim_start().clone_into(&mut self.description);
+            } else if let Some(stripped) = line.strip_prefix("icon:") {
+                self.icon = Some(stripped.trim_start().to_owned());
+            } else if let Some(stripped) = line.strip_prefix("keywords:") {
+                self.keywords = stripped.trim_start().split(' ').map(String::from).collect();
+            } else if let Some(stripped) = line.strip_prefix("args:") {
+                self.args = Some(stripped.trim().to_owned());
+            } else if let Some(stripped) = line.strip_prefix("confirm:") {
+                self.confirm = match stripped.trim() {
+                    "false" => None,
+                    "" | "true" => Some(String::new()),
+                    message => Some(message.to_owned()),
+                };
+            } else if let Some(stripped) = line.strip_prefix("terminal:") {
+                self.terminal = stripped.trim() == "true";
+            } else if let Some(stripped) = line.strip_prefix("mode:") {
+                if stripped.trim() == "dynamic" {
+                    self.prefix.get_or_insert_with(String::new);
+                }
+            } else if let Some(stripped) = line.strip_prefix("prefix:") {
+                self.prefix = Some(stripped.trim().to_owned());
+            } else if let Some(stripped) = line.strip_prefix("output:") {
+                self.output = match stripped.trim() {
+                    "notify" => ScriptOutput::Notify,
+                    "show" => ScriptOutput::Show,
+                    _ => ScriptOutput::Discard,
+                };
+            }
+        }
+
+        // Dynamic scripts without a prefix of their own are searched by their name.
+        if let Some(prefix) = self.prefix.as_mut()
+            && prefix.is_empty()
+        {
+            *prefix = self.name.to_ascii_lowercase();
+        }
+
+        // Confirmations without a message of their own are named after the script.
+        if let Some(confirm) = self.confirm.as_mut()
+            && confirm.is_empty()
+        {
+            *confirm = format!("Confirm {}", self.name);
+        }
+    }
+}
+
+/// Splits the input for a script into its arguments as a shell would, or at
+/// whitespace if its quotes are unbalanced.
+fn split_input(input: &str) -> Vec<String> {
+    shell_words::split(input)
+        .unwrap_or_else(|_| input.split_ascii_whitespace().map(String::from).collect())
+}
+
+/// What is done with the output of a script.
+#[derive(Debug, Default, PartialEq, Eq)]
+enum ScriptOutput {
+    /// The output is discarded.
+    #[default]
+    Discard,
+    /// The output is shown in a desktop notification.
+    Notify,
+    /// The output replaces the results in the launcher.
+    Show,
+}
+
+/// Script directories, in order of precedence.
+fn script_dirs() -> Vec<PathBuf> {
+    vec![
+        dirs::home_dir()
+            .expect("user does not have home dir")
+            .join(LOCAL_PATH),
+        Path::new(SYSTEM_ADMIN_PATH).to_owned(),
+        Path::new(DISTRIBUTION_PATH).to_owned(),
+    ]
 }
 
-async fn load_from(path: &Path, paths: &mut VecDeque<PathBuf>, tx: Sender<ScriptInfo>) {
+/// Loads the scripts within the directories, which are in order of precedence,
+/// sorted by their names.
+async fn load_scripts(roots: Vec<PathBuf>) -> Vec<ScriptInfo> {
+    let mut scripts: Vec<ScriptInfo> = Vec::with_capacity(16);
+
+    let (tx, rx) = flume::bounded::<ScriptInfo>(20);
+
+    let mut queue: VecDeque<(usize, PathBuf)> = roots.iter().cloned().enumerate().collect();
+
+    let script_sender = async move {
+        while let Some((precedence, path)) = queue.pop_front() {
+            let root = &roots[precedence];
+            load_from(precedence, root, &path, &mut queue, tx.clone()).await;
+        }
+    };
+
+    let script_receiver = async {
+        while let Ok(script) = rx.recv_async().await {
+            tracing::debug!("appending script: {:?}", script);
+
+            // Scripts override those at the same path in directories of lower precedence.
+            match scripts
+                .iter_mut()
+                .find(|cached| cached.relative == script.relative)
+            {
+                Some(cached) if cached.precedence > script.precedence => *cached = script,
+                Some(_) => (),
+                None => scripts.push(script),
+            }
+        }
+    };
+
+    futures::future::join(script_sender, script_receiver).await;
+
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    scripts
+}
+
+async fn load_from(
+    precedence: usize,
+    root: &Path,
+    path: &Path,
+    paths: &mut VecDeque<(usize, PathBuf)>,
+    tx: Sender<ScriptInfo>,
+) {
     if let Ok(directory) = path.read_dir() {
         for entry in directory.filter_map(Result::ok) {
             let tx = tx.clone();
             let path = entry.path();
 
             if path.is_dir() {
-                paths.push_back(path);
+                paths.push_back((precedence, path));
                 continue;
             }
 
-            tokio::spawn(async move {
-                let shebang_re = Regex::new(r"^!\s*").unwrap();
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_owned();
 
+            let category = relative
+                .parent()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned());
+
+            tokio::spawn(async move {
                 let mut file = match tokio::fs::File::open(&path).await {
                     Ok(file) => tokio::io::BufReader::new(file).lines(),
                     Err(why) => {
@@ -198,42 +695,220 @@ async fn load_from(path: &Path, paths: &mut VecDeque<PathBuf>, tx: Sender<Script
                     }
                 };
 
-                let mut info = ScriptInfo {
-                    path,
-                    ..Default::default()
-                };
-
-                let mut first = true;
+                let mut header = Vec::new();
 
                 while let Ok(Some(line)) = file.next_line().await {
                     if !line.starts_with('#') {
                         break;
                     }
 
-                    let line = line[1..].trim();
+                    header.push(line);
+                }
 
-                    if first {
-                        first = false;
-                        if shebang_re.is_match(line) {
-                            info.interpreter = Some(shebang_re.replace(line, "").to_string());
-                            continue;
-                        }
-                    }
+                let mut info = ScriptInfo {
+                    path,
+                    relative,
+                    precedence,
+                    category,
+                    ..Default::default()
+                };
 
-                    if let Some(stripped) = line.strip_prefix("name:") {
-                        stripped.trim_start().clone_into(&mut info.name);
-                    } else if let Some(stripped) = line.strip_prefix("description:") {
-                        stripped.trim_start().clone_into(&mut info.description);
-                    } else if let Some(stripped) = line.strip_prefix("icon:") {
-                        info.icon = Some(stripped.trim_start().to_owned());
-                    } else if let Some(stripped) = line.strip_prefix("keywords:") {
-                        info.keywords =
-                            stripped.trim_start().split(' ').map(String::from).collect();
-                    }
-                }
+                info.read_header(header.iter().map(String::as_str));
 
                 let _ = tx.send_async(info).await;
             });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn scripts_override_by_relative_path() {
+        let root =
+            std::env::temp_dir().join(format!("pop-launcher-scripts-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let roots = vec![
+            root.join("local"),
+            root.join("system"),
+            root.join("distribution"),
+        ];
+
+        let script = |root: &Path, relative: &str, name: &str| {
+            let path = root.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, format!("#!/bin/sh\n# name: {name}\n")).unwrap();
+        };
+
+        // Written in the reverse of their precedence, which is not their order of loading.
+        script(&roots[2], "power/suspend.sh", "Suspend (distribution)");
+        script(&roots[1], "power/suspend.sh", "Suspend (system)");
+        script(&roots[0], "power/suspend.sh", "Suspend (local)");
+        script(&roots[2], "reboot.sh", "Reboot (distribution)");
+        script(&roots[1], "reboot.sh", "Reboot (system)");
+        script(&roots[2], "suspend.sh", "Suspend Now (distribution)");
+
+        let scripts = load_scripts(roots.clone()).await;
+        let loaded: Vec<(&str, PathBuf)> = scripts
+            .iter()
+            .map(|script| (script.name.as_str(), script.path.clone()))
+            .collect();
+
+        assert_eq!(
+            loaded,
+            [
+                ("Reboot (system)", roots[1].join("reboot.sh")),
+                ("Suspend (local)", roots[0].join("power/suspend.sh")),
+                ("Suspend Now (distribution)", roots[2].join("suspend.sh")),
+            ]
+        );
+
+        assert_eq!(scripts[1].category.as_deref(), Some("power"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    fn header(lines: &str) -> ScriptInfo {
+        let mut info = ScriptInfo::default();
+        info.read_header(lines.lines());
+        info
+    }
+
+    #[test]
+    fn headers_are_parsed() {
+        let info = header(
+            "#!/usr/bin/env python3\n\
+             # name: Search Files\n\
+             # description: Finds files by name\n\
+             # keywords: find locate\n\
+             # args: Pattern\n\
+             # terminal: true\n\
+             # output: show\n\
+             print('hello')\n\
+             # name: Ignored",
+        );
+
+        assert_eq!(info.interpreter.as_deref(), Some("/usr/bin/env python3"));
+        assert_eq!(info.name, "Search Files");
+        assert_eq!(info.description, "Finds files by name");
+        assert_eq!(info.keywords, ["find", "locate"]);
+        assert_eq!(info.args.as_deref(), Some("Pattern"));
+        assert_eq!(info.confirm, None);
+        assert!(info.terminal);
+        assert_eq!(info.output, ScriptOutput::Show);
+        assert_eq!(info.prefix, None);
+
+        let info = header("# name: Reboot\n# output: notify\n# terminal: yes");
+        assert_eq!(info.interpreter, None);
+        assert_eq!(info.name, "Reboot");
+        assert_eq!(info.args, None);
+        assert!(!info.terminal);
+        assert_eq!(info.output, ScriptOutput::Notify);
+
+        let info = header("# name: Nowhere\n# output: elsewhere");
+        assert_eq!(info.output, ScriptOutput::Discard);
+    }
+
+    #[test]
+    fn confirmations_are_parsed() {
+        let confirm = |value: &str| header(&format!("# name: Reboot\n# confirm: {value}")).confirm;
+
+        assert_eq!(confirm("").as_deref(), Some("Confirm Reboot"));
+        assert_eq!(confirm("true").as_deref(), Some("Confirm Reboot"));
+        assert_eq!(confirm("Reboot Now").as_deref(), Some("Reboot Now"));
+        assert_eq!(confirm("false"), None);
+    }
+
+    #[test]
+    fn dynamic_scripts_default_to_their_name() {
+        let info = header("# name: Emoji\n# mode: dynamic");
+        assert_eq!(info.prefix.as_deref(), Some("emoji"));
+
+        let info = header("# name: Emoji\n# mode: dynamic\n# prefix: em");
+        assert_eq!(info.prefix.as_deref(), Some("em"));
+    }
+
+    #[test]
+    fn input_is_split_as_shell_words() {
+        assert_eq!(split_input("a b  c"), ["a", "b", "c"]);
+        assert_eq!(
+            split_input(r#""My Documents" 'a b' c\ d"#),
+            ["My Documents", "a b", "c d"]
+        );
+        assert_eq!(split_input("it's here"), ["it's", "here"]);
+        assert!(split_input("  ").is_empty());
+    }
+
+    /// The name, description, and icon name of a result that a dynamic script produced.
+    fn item(line: &str) -> Option<(String, String, Option<String>)> {
+        parse_item(line).map(|result| {
+            let icon = result.icon.map(|icon| match icon {
+                IconSource::Name(name) | IconSource::Mime(name) => name.into_owned(),
+            });
+
+            (result.name, result.description, icon)
+        })
+    }
+
+    #[test]
+    fn items_are_parsed_from_tab_separated_fields() {
+        let owned = |name: &str, description: &str, icon: Option<&str>| {
+            Some((
+                name.to_owned(),
+                description.to_owned(),
+                icon.map(ToOwned::to_owned),
+            ))
+        };
+
+        assert_eq!(
+            item("Smile\tgrinning face\temoji-people  "),
+            owned("Smile", "grinning face", Some("emoji-people"))
+        );
+        assert_eq!(
+            item("Smile\tgrinning face"),
+            owned("Smile", "grinning face", None)
+        );
+        assert_eq!(item("Smile\t\t"), owned("Smile", "", None));
+        assert_eq!(item("Smile"), owned("Smile", "", None));
+        assert_eq!(item("\tgrinning face"), None);
+        assert_eq!(item(""), None);
+    }
+
+    #[test]
+    fn items_are_parsed_from_json() {
+        let result = parse_item(
+            r#"{"name": "Smile", "keywords": ["happy"], "icon": {"Name": "emoji-people"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(result.id, 0);
+        assert_eq!(result.name, "Smile");
+        assert_eq!(result.description, "");
+        assert_eq!(result.keywords, Some(vec![String::from("happy")]));
+        assert!(matches!(result.icon, Some(IconSource::Name(name)) if name == "emoji-people"));
+
+        let result =
+            parse_item(r#"{"id": 7, "name": "Smile", "description": "grinning face"}"#).unwrap();
+        assert_eq!(result.id, 7);
+        assert_eq!(result.description, "grinning face");
+
+        assert!(parse_item(r#"{"name": "Smile""#).is_none());
+        assert!(parse_item(r#"{"description": "grinning face"}"#).is_none());
+        assert!(parse_item(r#"{"name": 1}"#).is_none());
+    }
+
+    #[test]
+    fn prefixes_are_stripped() {
+        assert_eq!(strip_prefix("em smile", "em"), Some("smile"));
+        assert_eq!(strip_prefix("EM  smile  ", "em"), Some("smile"));
+        assert_eq!(strip_prefix("em", "em"), Some(""));
+        assert_eq!(strip_prefix("emoji", "em"), None);
+        assert_eq!(strip_prefix("e", "em"), None);
+        assert_eq!(strip_prefix("smile", ""), None);
+        assert_eq!(strip_prefix("\u{e9}a", "e"), None);
+    }
+}
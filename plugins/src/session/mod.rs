@@ -0,0 +1,774 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+use crate::{dbus, send};
+use futures::StreamExt;
+use pop_launcher::*;
+use serde::{Serialize, de::DeserializeOwned};
+use std::borrow::Cow;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use zbus::Connection;
+use zvariant::Type;
+
+const LOGIND: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const MANAGER: &str = "org.freedesktop.login1.Manager";
+/// The session of the caller, as resolved by logind.
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+const SESSION: &str = "org.freedesktop.login1.Session";
+
+const POWER: &str = "com.system76.PowerDaemon";
+const POWER_PATH: &str = "/com/system76/PowerDaemon";
+
+/// Options of the context menu which confirms that an action should run.
+const CONFIRM: u32 = 0;
+const CANCEL: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Graphics {
+    Integrated,
+    Hybrid,
+    Nvidia,
+    Compute,
+}
+
+impl Graphics {
+    const ALL: [Graphics; 4] = [
+        Graphics::Integrated,
+        Graphics::Hybrid,
+        Graphics::Nvidia,
+        Graphics::Compute,
+    ];
+
+    /// Name of the mode within system76-power.
+    fn mode(self) -> &'static str {
+        match self {
+            Graphics::Integrated => "integrated",
+            Graphics::Hybrid => "hybrid",
+            Graphics::Nvidia => "nvidia",
+            Graphics::Compute => "compute",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Graphics::Integrated => "Integrated",
+            Graphics::Hybrid => "Hybrid",
+            Graphics::Nvidia => "NVIDIA",
+            Graphics::Compute => "Compute",
+        }
+    }
+
+    fn from_mode(mode: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|graphics| graphics.mode() == mode)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Profile {
+    Battery,
+    Balanced,
+    Performance,
+}
+
+impl Profile {
+    const ALL: [Profile; 3] = [Profile::Battery, Profile::Balanced, Profile::Performance];
+
+    /// Name of the profile within system76-power, which is also the method that sets it.
+    fn method(self) -> &'static str {
+        match self {
+            Profile::Battery => "Battery",
+            Profile::Balanced => "Balanced",
+            Profile::Performance => "Performance",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Profile::Battery => "Battery Life",
+            Profile::Balanced => "Balanced",
+            Profile::Performance => "High Performance",
+        }
+    }
+
+    fn from_method(method: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.method() == method)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Lock,
+    LogOut,
+    Suspend,
+    Hibernate,
+    Reboot,
+    PowerOff,
+    Firmware,
+    Graphics(Graphics),
+    Profile(Profile),
+}
+
+impl Action {
+    fn name(self) -> Cow<'static, str> {
+        Cow::Borrowed(match self {
+            Action::Lock => "Lock",
+            Action::LogOut => "Log Out",
+            Action::Suspend => "Suspend",
+            Action::Hibernate => "Hibernate",
+            Action::Reboot => "Restart",
+            Action::PowerOff => "Power Off",
+            Action::Firmware => "Enter BIOS",
+            Action::Graphics(graphics) => {
+                return Cow::Owned(format!("Switch to {} Graphics", graphics.label()));
+            }
+            Action::Profile(profile) => {
+                return Cow::Owned(format!("{} Power Profile", profile.label()));
+            }
+        })
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Action::Lock => "Lock the screen",
+            Action::LogOut => "Log out to the login screen",
+            Action::Suspend => "Suspend the system",
+            Action::Hibernate => "Save the session to disk and power off",
+            Action::Reboot => "Reboot the system",
+            Action::PowerOff => "Shut down the system",
+            Action::Firmware => "Reboot into BIOS",
+            Action::Graphics(Graphics::Integrated) => {
+                "Use integrated GPU for display and applications"
+            }
+            Action::Graphics(Graphics::Hybrid) => {
+                "Use integrated GPU for display; applications may request discrete"
+            }
+            Action::Graphics(Graphics::Nvidia) => "Use NVIDIA GPU for display and applications",
+            Action::Graphics(Graphics::Compute) => {
+                "Use integrated GPU for display; discrete for CUDA / OpenCL"
+            }
+            Action::Profile(Profile::Battery) => "Reduce power usage to extend battery life",
+            Action::Profile(Profile::Balanced) => "Balance performance and battery life",
+            Action::Profile(Profile::Performance) => "Raise performance at the cost of power",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Action::Lock => "system-lock-screen",
+            Action::LogOut => "system-log-out",
+            Action::Suspend | Action::Hibernate => "system-suspend",
+            Action::Reboot | Action::Firmware => "system-restart",
+            Action::PowerOff => "system-shutdown",
+            Action::Graphics(_) => "preferences-system-symbolic",
+            Action::Profile(_) => "power-profile-balanced-symbolic",
+        }
+    }
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Action::Lock => &["lock", "screen"],
+            Action::LogOut => &["log out", "logout", "sign out"],
+            Action::Suspend => &["suspend", "sleep"],
+            Action::Hibernate => &["hibernate", "sleep"],
+            Action::Reboot => &["power", "reboot", "restart"],
+            Action::PowerOff => &["power off", "shutdown", "poweroff"],
+            Action::Firmware => &["bios", "uefi", "reboot", "restart"],
+            Action::Graphics(_) => &["graphics", "switch", "gpu", "nvidia", "intel"],
+            Action::Profile(_) => &["power", "profile", "battery", "performance"],
+        }
+    }
+
+    /// Actions which end the session, or require a restart, ask for confirmation first.
+    fn confirm(self) -> bool {
+        matches!(
+            self,
+            Action::LogOut
+                | Action::Reboot
+                | Action::PowerOff
+                | Action::Firmware
+                | Action::Graphics(_)
+        )
+    }
+}
+
+pub async fn main() {
+    let connection = match Connection::system().await {
+        Ok(conn) => conn,
+        Err(_) => {
+            let mut out = async_stdout();
+            let _ = crate::send(&mut out, PluginResponse::Deactivate).await;
+            return;
+        }
+    };
+
+    let mut app = App::new(connection, async_stdout());
+
+    let mut requests = json_input_stream(async_stdin());
+    while let Some(request) = requests.next().await {
+        match request {
+            Ok(request) => match request {
+                Request::Activate(id) => app.activate(id).await,
+                Request::ActivateContext { id, context } => app.activate_context(id, context).await,
+                Request::Search(query) => app.search(&query).await,
+                Request::Exit => break,
+                _ => (),
+            },
+            Err(why) => {
+                tracing::error!("malformed JSON request: {}", why);
+            }
+        }
+    }
+}
+
+struct App<W> {
+    connection: Connection,
+    /// Actions which are available on this system, by the IDs of their results.
+    actions: Vec<Action>,
+    graphics: Option<Graphics>,
+    profile: Option<Profile>,
+    /// The result whose confirmation was requested.
+    confirming: Option<u32>,
+    /// The last query, which later queries of a search extend as they are typed.
+    query: String,
+    /// Whether the actions and their state are reloaded by the next query,
+    /// which happens after an action.
+    stale: bool,
+    tx: W,
+}
+
+impl<W: AsyncWrite + Unpin> App<W> {
+    fn new(connection: Connection, tx: W) -> Self {
+        Self {
+            connection,
+            actions: Vec::new(),
+            graphics: None,
+            profile: None,
+            confirming: None,
+            query: String::new(),
+            stale: true,
+            tx,
+        }
+    }
+
+    async fn call<A, R>(
+        &self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        method: &str,
+        args: &A,
+    ) -> zbus::Result<R>
+    where
+        A: Serialize + Type,
+        R: DeserializeOwned + Type,
+    {
+        dbus::call(&self.connection, destination, path, interface, method, args).await
+    }
+
+    /// Whether logind permits an action, by its `Can*` method.
+    async fn can(&self, method: &str) -> bool {
+        self.call::<_, String>(LOGIND, LOGIND_PATH, MANAGER, method, &())
+            .await
+            .is_ok_and(|answer| answer == "yes" || answer == "challenge")
+    }
+
+    /// Collects the actions which are available, and the current graphics
+    /// mode and power profile from system76-power.
+    async fn reload(&mut self) {
+        let mut actions = vec![Action::Lock, Action::LogOut];
+
+        for (method, action) in [
+            ("CanSuspend", Action::Suspend),
+            ("CanHibernate", Action::Hibernate),
+            ("CanReboot", Action::Reboot),
+            ("CanPowerOff", Action::PowerOff),
+            ("CanRebootToFirmwareSetup", Action::Firmware),
+        ] {
+            if self.can(method).await {
+                actions.push(action);
+            }
+        }
+
+        self.graphics = None;
+
+        let switchable: bool = self
+            .call(POWER, POWER_PATH, POWER, "GetSwitchable", &())
+            .await
+            .unwrap_or(false);
+
+        if switchable {
+            self.graphics = self
+                .call::<_, String>(POWER, POWER_PATH, POWER, "GetGraphics", &())
+                .await
+                .ok()
+                .and_then(|mode| Graphics::from_mode(&mode));
+
+            actions.extend(
+                Graphics::ALL
+                    .into_iter()
+                    .filter(|&graphics| Some(graphics) != self.graphics)
+                    .map(Action::Graphics),
+            );
+        }
+
+        self.profile = self
+            .call::<_, String>(POWER, POWER_PATH, POWER, "GetProfile", &())
+            .await
+            .ok()
+            .and_then(|profile| Profile::from_method(&profile));
+
+        if self.profile.is_some() {
+            actions.extend(Profile::ALL.into_iter().map(Action::Profile));
+        }
+
+        self.actions = actions;
+    }
+
+    /// The description of an action, with the current state that it would change.
+    fn description(&self, action: Action) -> Cow<'static, str> {
+        match action {
+            Action::Graphics(_) => match self.graphics {
+                Some(current) => Cow::Owned(format!(
+                    "{} · Currently {} graphics",
+                    action.description(),
+                    current.label()
+                )),
+                None => Cow::Borrowed(action.description()),
+            },
+            Action::Profile(profile) if self.profile == Some(profile) => {
+                Cow::Owned(format!("{} · Active", action.description()))
+            }
+            _ => Cow::Borrowed(action.description()),
+        }
+    }
+
+    async fn activate(&mut self, id: u32) {
+        self.confirming = None;
+
+        let Some(&action) = self.actions.get(id as usize) else {
+            return;
+        };
+
+        if action.confirm() {
+            let options = vec![
+                ContextOption {
+                    id: CONFIRM,
+                    name: action.name().into_owned(),
+                    ..Default::default()
+                },
+                ContextOption {
+                    id: CANCEL,
+                    name: String::from("Cancel"),
+                    ..Default::default()
+                },
+            ];
+
+            self.confirming = Some(id);
+            send(&mut self.tx, PluginResponse::Context { id, options }).await;
+            return;
+        }
+
+        self.run(action).await;
+    }
+
+    async fn activate_context(&mut self, id: u32, context: u32) {
+        if self.confirming.take() == Some(id)
+            && context == CONFIRM
+            && let Some(&action) = self.actions.get(id as usize)
+        {
+            self.run(action).await;
+        }
+    }
+
+    async fn run(&mut self, action: Action) {
+        send(&mut self.tx, PluginResponse::Close).await;
+        let _ = self.tx.flush().await;
+        self.stale = true;
+
+        let result = match action {
+            Action::Lock => self.call(LOGIND, SESSION_PATH, SESSION, "Lock", &()).await,
+            // Terminating the session is the last resort, as applications cannot save their state.
+            Action::LogOut if log_out_with_desktop() => Ok(()),
+            Action::LogOut => {
+                self.call(LOGIND, SESSION_PATH, SESSION, "Terminate", &())
+                    .await
+            }
+            Action::Suspend => self.manager("Suspend").await,
+            Action::Hibernate => self.manager("Hibernate").await,
+            Action::Reboot => self.manager("Reboot").await,
+            Action::PowerOff => self.manager("PowerOff").await,
+            Action::Firmware => match self.manager("SetRebootToFirmwareSetup").await {
+                Ok(()) => self.manager("Reboot").await,
+                Err(why) => Err(why),
+            },
+            Action::Graphics(graphics) => {
+                // Switching takes a while, as the initramfs is regenerated.
+                let connection = self.connection.clone();
+                tokio::spawn(async move {
+                    let result = connection
+                        .call_method(
+                            Some(POWER),
+                            POWER_PATH,
+                            Some(POWER),
+                            "SetGraphics",
+                            &(graphics.mode(),),
+                        )
+                        .await;
+
+                    let message = match result {
+                        Ok(_) => format!(
+                            "Switched to {} graphics. Restart to apply the change.",
+                            graphics.label()
+                        ),
+                        Err(why) => format!("Failed to switch graphics: {}", why),
+                    };
+
+                    notify(&message).await;
+                });
+
+                Ok(())
+            }
+            Action::Profile(profile) => {
+                self.call(POWER, POWER_PATH, POWER, profile.method(), &())
+                    .await
+            }
+        };
+
+        if let Err(why) = result {
+            tracing::error!("failed to run {}: {}", action.name(), why);
+        }
+    }
+
+    /// Calls a method of the logind manager, which may ask for authorization.
+    async fn manager(&self, method: &str) -> zbus::Result<()> {
+        self.call(LOGIND, LOGIND_PATH, MANAGER, method, &(true,))
+            .await
+    }
+
+    async fn search(&mut self, query: &str) {
+        self.confirming = None;
+
+        let query = query.trim().to_ascii_lowercase();
+
+        // The state is reloaded once per search, rather than for every key
+        // typed, where a search which does not extend the last one is new.
+        if !query.starts_with(&self.query) {
+            self.stale = true;
+        }
+
+        self.query.clone_from(&query);
+
+        if !query.is_empty() {
+            if self.stale {
+                self.reload().await;
+                self.stale = false;
+            }
+
+            for (id, &action) in self.actions.iter().enumerate() {
+                let description = self.description(action);
+
+                if !matches(action, &description, &query) {
+                    continue;
+                }
+
+                let result = PluginSearchResult {
+                    id: id as u32,
+                    name: action.name().into_owned(),
+                    description: description.into_owned(),
+                    icon: Some(IconSource::Name(Cow::Borrowed(action.icon()))),
+                    ..Default::default()
+                };
+
+                send(&mut self.tx, PluginResponse::Append(result)).await;
+            }
+        }
+
+        send(&mut self.tx, PluginResponse::Finished).await;
+        let _ = self.tx.flush().await;
+    }
+}
+
+fn matches(action: Action, description: &str, query: &str) -> bool {
+    action.name().to_ascii_lowercase().contains(query)
+        || description.to_ascii_lowercase().contains(query)
+        || action.keywords().iter().any(|k| k.contains(query))
+}
+
+/// Asks the desktop to log out, which lets applications save their state,
+/// returning whether the desktop was asked.
+fn log_out_with_desktop() -> bool {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+
+    let (program, args): (&str, &[&str]) = if desktop.contains("COSMIC") {
+        ("cosmic-osd", &["log-out"])
+    } else if desktop.contains("GNOME") {
+        // Already confirmed by the launcher.
+        ("gnome-session-quit", &["--logout", "--no-prompt"])
+    } else {
+        return false;
+    };
+
+    tokio::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .is_ok()
+}
+
+async fn notify(message: &str) {
+    let _ = tokio::process::Command::new("notify-send")
+        .args([
+            "--app-name=Pop Launcher",
+            "--icon=preferences-system-symbolic",
+        ])
+        .arg(message)
+        .status()
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::testing::PrivateBus;
+    use std::sync::{Arc, Mutex};
+
+    type Calls = Arc<Mutex<Vec<String>>>;
+
+    struct MockManager {
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.login1.Manager")]
+    impl MockManager {
+        fn can_suspend(&self) -> &str {
+            "yes"
+        }
+
+        fn can_hibernate(&self) -> &str {
+            "no"
+        }
+
+        fn can_reboot(&self) -> &str {
+            "yes"
+        }
+
+        fn can_power_off(&self) -> &str {
+            "challenge"
+        }
+
+        fn can_reboot_to_firmware_setup(&self) -> &str {
+            "na"
+        }
+
+        fn suspend(&self, _interactive: bool) {
+            self.calls.lock().unwrap().push("Suspend".into());
+        }
+
+        fn reboot(&self, _interactive: bool) {
+            self.calls.lock().unwrap().push("Reboot".into());
+        }
+    }
+
+    struct MockPower {
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "com.system76.PowerDaemon")]
+    impl MockPower {
+        fn get_switchable(&self) -> bool {
+            true
+        }
+
+        fn get_graphics(&self) -> &str {
+            "hybrid"
+        }
+
+        fn get_profile(&self) -> &str {
+            self.calls.lock().unwrap().push("GetProfile".into());
+            "Balanced"
+        }
+
+        fn battery(&self) {
+            self.calls.lock().unwrap().push("Battery".into());
+        }
+    }
+
+    fn responses(app: &mut App<Vec<u8>>) -> Vec<PluginResponse> {
+        let output = std::mem::take(&mut app.tx);
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn results(app: &mut App<Vec<u8>>) -> Vec<(u32, String, String)> {
+        responses(app)
+            .into_iter()
+            .filter_map(|response| match response {
+                PluginResponse::Append(result) => {
+                    Some((result.id, result.name, result.description))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn find(app: &App<Vec<u8>>, action: Action) -> u32 {
+        app.actions.iter().position(|&a| a == action).unwrap() as u32
+    }
+
+    fn calls(calls: &Calls) -> Vec<String> {
+        std::mem::take(&mut *calls.lock().unwrap())
+    }
+
+    #[test]
+    fn actions_match_by_name_description_and_keywords() {
+        let matches = |action: Action, query| matches(action, action.description(), query);
+
+        assert!(matches(Action::Reboot, "restart"));
+        assert!(matches(Action::Reboot, "reboot the"));
+        assert!(matches(Action::PowerOff, "shutdown"));
+        assert!(matches(Action::Profile(Profile::Battery), "battery"));
+        assert!(!matches(Action::Lock, "restart"));
+        assert!(!matches(Action::Suspend, "power off"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires dbus-daemon"]
+    async fn actions_on_private_bus() {
+        let bus = PrivateBus::new().await;
+
+        let bus_calls = Calls::default();
+
+        let _logind = bus
+            .builder()
+            .name(LOGIND)
+            .unwrap()
+            .serve_at(
+                LOGIND_PATH,
+                MockManager {
+                    calls: bus_calls.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let _power = bus
+            .builder()
+            .name(POWER)
+            .unwrap()
+            .serve_at(
+                POWER_PATH,
+                MockPower {
+                    calls: bus_calls.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let connection = bus.connect().await;
+
+        let mut app = App::new(connection, Vec::new());
+
+        // Actions which logind does not permit are not offered.
+        app.search("restart").await;
+        let reboot = find(&app, Action::Reboot);
+        assert_eq!(
+            results(&mut app),
+            [(reboot, "Restart".to_owned(), "Reboot the system".to_owned())]
+        );
+        assert!(!app.actions.contains(&Action::Hibernate));
+        assert!(!app.actions.contains(&Action::Firmware));
+        assert!(app.actions.contains(&Action::PowerOff));
+
+        // Descriptions show the current graphics mode and power profile.
+        app.search("graphics").await;
+        let graphics: Vec<(String, String)> = results(&mut app)
+            .into_iter()
+            .map(|(_, name, description)| (name, description))
+            .collect();
+        assert_eq!(
+            graphics,
+            [
+                (
+                    "Switch to Integrated Graphics".to_owned(),
+                    "Use integrated GPU for display and applications · Currently Hybrid graphics"
+                        .to_owned()
+                ),
+                (
+                    "Switch to NVIDIA Graphics".to_owned(),
+                    "Use NVIDIA GPU for display and applications · Currently Hybrid graphics"
+                        .to_owned()
+                ),
+                (
+                    "Switch to Compute Graphics".to_owned(),
+                    "Use integrated GPU for display; discrete for CUDA / OpenCL · Currently Hybrid graphics"
+                        .to_owned()
+                ),
+            ]
+        );
+
+        app.search("balanced").await;
+        assert_eq!(
+            results(&mut app)[0].2,
+            "Balance performance and battery life · Active"
+        );
+
+        // The state is reloaded once per search, rather than for every key typed.
+        calls(&bus_calls);
+        for query in ["b", "ba", "bat"] {
+            app.search(query).await;
+        }
+        assert_eq!(calls(&bus_calls), ["GetProfile"]);
+        responses(&mut app);
+
+        // Restarting is confirmed first, and cancelling does nothing.
+        app.activate(reboot).await;
+        match &responses(&mut app)[0] {
+            PluginResponse::Context { id, options } => {
+                assert_eq!(*id, reboot);
+                assert_eq!(options[0].id, CONFIRM);
+                assert_eq!(options[0].name, "Restart");
+                assert_eq!(options[1].id, CANCEL);
+            }
+            response => panic!("expected a confirmation, not {response:?}"),
+        }
+
+        app.activate_context(reboot, CANCEL).await;
+        app.activate_context(reboot, CONFIRM).await;
+        assert!(responses(&mut app).is_empty());
+        assert!(calls(&bus_calls).is_empty());
+
+        // A confirmation is not carried over to the next search.
+        app.activate(reboot).await;
+        app.search("restart").await;
+        app.activate_context(reboot, CONFIRM).await;
+        responses(&mut app);
+        assert!(!calls(&bus_calls).contains(&"Reboot".to_owned()));
+
+        app.activate(reboot).await;
+        app.activate_context(reboot, CONFIRM).await;
+        assert!(matches!(
+            responses(&mut app).last(),
+            Some(PluginResponse::Close)
+        ));
+        assert_eq!(calls(&bus_calls), ["Reboot"]);
+
+        // Suspending and switching power profiles are not confirmed.
+        app.activate(find(&app, Action::Suspend)).await;
+        app.activate(find(&app, Action::Profile(Profile::Battery)))
+            .await;
+        assert_eq!(calls(&bus_calls), ["Suspend", "Battery"]);
+    }
+}
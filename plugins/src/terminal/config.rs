@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+fn bool_true() -> bool {
+    true
+}
+
+fn default_capture_timeout() -> u64 {
+    5
+}
+
+fn default_capture_lines() -> usize {
+    10
+}
+
+/// Options of the terminal plugin, besides the terminal emulator itself.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Config {
+    /// Keep the terminal open once the command exits, until Enter is pressed.
+    #[serde(default = "bool_true")]
+    pub hold: bool,
+
+    /// Directory which commands are run in.
+    #[serde(default)]
+    pub working_directory: Option<String>,
+
+    /// Run background commands within the launcher, and show their output
+    /// and exit status as results.
+    #[serde(default)]
+    pub capture_output: bool,
+
+    /// Seconds to wait for a captured command to exit before it is killed.
+    #[serde(default = "default_capture_timeout")]
+    pub capture_timeout: u64,
+
+    /// Lines of output which are shown from a captured command.
+    #[serde(default = "default_capture_lines")]
+    pub capture_lines: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hold: true,
+            working_directory: None,
+            capture_output: false,
+            capture_timeout: default_capture_timeout(),
+            capture_lines: default_capture_lines(),
+        }
+    }
+}
+
+impl Config {
+    pub fn working_directory(&self) -> Option<PathBuf> {
+        let dir = self.working_directory.as_deref()?;
+
+        match dir.strip_prefix('~') {
+            Some(rest) => dirs::home_dir().map(|home| home.join(rest.trim_start_matches('/'))),
+            None => Some(PathBuf::from(dir)),
+        }
+    }
+}
+
+/// The terminal emulator and shell are read from the same file by
+/// [`pop_launcher::launch::TerminalConfig`].
+pub fn load() -> Config {
+    pop_launcher::config::load("terminal")
+}
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Persisted history of the commands which were run.
+
+use std::path::{Path, PathBuf};
+
+/// Number of commands which are remembered.
+const CAPACITY: usize = 100;
+
+fn path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".cache/pop-launcher/terminal_history"))
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Commands from the least to the most recently run.
+    commands: Vec<String>,
+}
+
+impl History {
+    pub fn load() -> Self {
+        path().map_or_else(Self::default, |path| Self::load_from(&path))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let commands = std::fs::read_to_string(path)
+            .map(|history| history.lines().map(ToOwned::to_owned).collect())
+            .unwrap_or_default();
+
+        Self { commands }
+    }
+
+    pub fn store(&self) {
+        if let Some(path) = path() {
+            self.store_in(&path);
+        }
+    }
+
+    fn store_in(&self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let mut history = self.commands.join("\n");
+        history.push('\n');
+
+        if let Err(why) = std::fs::write(path, history) {
+            tracing::error!("failed to store terminal history: {}", why);
+        }
+    }
+
+    /// Records a command as the most recently run.
+    pub fn add(&mut self, command: &str) {
+        if command.is_empty() || command.contains('\n') {
+            return;
+        }
+
+        self.commands.retain(|c| c != command);
+        self.commands.push(command.to_owned());
+
+        if self.commands.len() > CAPACITY {
+            self.commands.drain(..self.commands.len() - CAPACITY);
+        }
+    }
+
+    /// Commands which contain the text, from the most recently run.
+    pub fn search<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.commands
+            .iter()
+            .rev()
+            .map(String::as_str)
+            .filter(move |command| command.contains(text))
+    }
+
+    /// The most recently run command which begins with the text.
+    pub fn complete(&self, text: &str) -> Option<&str> {
+        self.commands
+            .iter()
+            .rev()
+            .map(String::as_str)
+            .find(|command| command.len() > text.len() && command.starts_with(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CAPACITY, History};
+
+    #[test]
+    fn commands_are_remembered() {
+        let dir = std::env::temp_dir().join(format!(
+            "pop-launcher-terminal-history-{}",
+            std::process::id()
+        ));
+        let path = dir.join("terminal_history");
+
+        let mut history = History::default();
+        history.add("ls");
+        history.add("cargo build");
+        history.add("");
+        history.add("echo a\necho b");
+        history.store_in(&path);
+
+        let history = History::load_from(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(history.commands, ["ls", "cargo build"]);
+    }
+
+    #[test]
+    fn commands_are_most_recent_first() {
+        let mut history = History::default();
+        history.add("cargo build");
+        history.add("cargo test");
+        history.add("ls");
+        history.add("cargo build");
+
+        assert_eq!(history.commands, ["cargo test", "ls", "cargo build"]);
+
+        let found: Vec<&str> = history.search("cargo").collect();
+        assert_eq!(found, ["cargo build", "cargo test"]);
+
+        assert_eq!(history.complete("cargo "), Some("cargo build"));
+        assert_eq!(history.complete("cargo build"), None);
+        assert_eq!(history.complete("make"), None);
+    }
+
+    #[test]
+    fn oldest_commands_are_forgotten() {
+        let mut history = History::default();
+
+        for number in 0..CAPACITY + 10 {
+            history.add(&format!("echo {}", number));
+        }
+
+        assert_eq!(history.commands.len(), CAPACITY);
+        assert_eq!(history.commands[0], "echo 10");
+        assert_eq!(
+            history.commands.last().map(String::as_str),
+            Some(format!("echo {}", CAPACITY + 9).as_str())
+        );
+    }
+}
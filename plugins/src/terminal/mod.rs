@@ -1,36 +1,47 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
-use freedesktop_desktop_entry::get_languages_from_env;
 use futures::prelude::*;
-use pop_launcher::*;
-use std::path::PathBuf;
+use pop_launcher::{launch::TerminalConfig, *};
+use std::{collections::BTreeSet, ffi::OsStr, os::unix::fs::PermissionsExt, time::Duration};
+use tokio::io::AsyncWrite;
 
-pub struct App {
+mod config;
+mod history;
+
+use config::Config;
+use history::History;
+
+/// Number of commands from the history which are shown.
+const HISTORY_RESULTS: usize = 8;
+
+pub struct App<W> {
     last_query: Option<String>,
-    out: tokio::io::Stdout,
+    /// The prefix of the last query, which completions are filled in after.
+    prefix: String,
+    /// Commands from the history which matched the last query, whose result
+    /// IDs follow the ID of the query itself.
+    matches: Vec<String>,
+    history: History,
+    config: Config,
+    terminal: TerminalConfig,
+    /// Output of the last captured command, whose lines replace the results
+    /// until the next search.
+    captured: Option<Vec<String>>,
+    out: W,
     shell_only: bool,
 }
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            last_query: None,
-            out: async_stdout(),
-            shell_only: false,
-        }
-    }
-}
-
 pub async fn main() {
     let mut requests = json_input_stream(async_stdin());
 
-    let mut app = App::default();
+    let mut app = App::new(async_stdout());
 
     while let Some(result) = requests.next().await {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
+                Request::Complete(id) => app.complete(id).await,
                 Request::Search(query) => app.search(query).await,
                 Request::Exit => break,
                 _ => (),
@@ -42,13 +53,54 @@ pub async fn main() {
     }
 }
 
-impl App {
-    async fn activate(&mut self, _id: u32) {
-        let exe = match self.last_query.take() {
-            Some(cmd) => cmd,
-            None => return,
+impl<W: AsyncWrite + Unpin> App<W> {
+    fn new(out: W) -> Self {
+        Self {
+            last_query: None,
+            prefix: String::new(),
+            matches: Vec::new(),
+            history: History::load(),
+            config: config::load(),
+            terminal: TerminalConfig::load(),
+            captured: None,
+            out,
+            shell_only: false,
+        }
+    }
+
+    async fn activate(&mut self, id: u32) {
+        if let Some(captured) = self.captured.as_ref() {
+            let text = match id.checked_sub(1) {
+                Some(line) => captured.get(line as usize).cloned(),
+                None => Some(captured.join("\n")),
+            };
+
+            if let Some(text) = text {
+                crate::send(&mut self.out, PluginResponse::Copy(text)).await;
+            }
+
+            return;
+        }
+
+        let exe = match id.checked_sub(1) {
+            Some(index) => match self.matches.get(index as usize) {
+                Some(cmd) => cmd.clone(),
+                None => return,
+            },
+            None => match self.last_query.take() {
+                Some(cmd) => cmd,
+                None => return,
+            },
         };
 
+        self.history.add(&exe);
+        self.history.store();
+
+        if self.shell_only && self.config.capture_output {
+            self.capture(&exe).await;
+            return;
+        }
+
         use fork::{Fork, daemon};
 
         crate::send(&mut self.out, PluginResponse::Close).await;
@@ -57,20 +109,26 @@ impl App {
             use std::os::unix::process::CommandExt;
             use std::process::Command;
 
+            let config = &self.config;
+            let shell = &self.terminal.shell;
             let mut cmd;
 
             if self.shell_only {
-                cmd = Command::new("sh");
+                cmd = Command::new(shell);
                 cmd.args(["-c", &exe]);
             } else {
-                let (terminal, arg) = detect_terminal();
-                cmd = Command::new(terminal);
-                cmd.args([
-                    arg,
-                    "sh",
-                    "-c",
-                    &format!("{}; echo \"Press Enter to exit\"; read t", exe),
-                ]);
+                let script = if config.hold {
+                    format!("{}; echo \"Press Enter to exit\"; read t", exe)
+                } else {
+                    exe
+                };
+
+                cmd = self.terminal.command();
+                cmd.args([shell, "-c", &script]);
+            }
+
+            if let Some(dir) = config.working_directory() {
+                cmd.current_dir(dir);
             }
 
             let _ = cmd.exec();
@@ -79,27 +137,125 @@ impl App {
         std::process::exit(0);
     }
 
+    /// Fills the query with a command from the history, or completes the
+    /// command being typed from the history and the executables in `$PATH`.
+    async fn complete(&mut self, id: u32) {
+        let completion = match id.checked_sub(1) {
+            Some(index) => self.matches.get(index as usize).cloned(),
+            None => self.last_query.as_deref().and_then(|typed| {
+                if typed.contains(char::is_whitespace) {
+                    self.history.complete(typed).map(ToOwned::to_owned)
+                } else {
+                    let path = std::env::var_os("PATH")?;
+                    complete_executable(typed, &path)
+                }
+            }),
+        };
+
+        if let Some(completion) = completion {
+            let fill = [self.prefix.as_str(), &completion].concat();
+            crate::send(&mut self.out, PluginResponse::Fill(fill)).await;
+        }
+    }
+
+    /// Runs a background command within the launcher, and replaces the
+    /// results with its exit status and the first lines of its output.
+    async fn capture(&mut self, exe: &str) {
+        let config = &self.config;
+
+        let mut cmd = tokio::process::Command::new(&self.terminal.shell);
+        cmd.args(["-c", exe]);
+
+        if let Some(dir) = config.working_directory() {
+            cmd.current_dir(dir);
+        }
+
+        let timeout = Duration::from_secs(config.capture_timeout);
+
+        let (status, lines) = match crate::capture_output(cmd, timeout).await {
+            Ok(Some(output)) => {
+                let status = match output.status.code() {
+                    Some(code) => format!("exited with status {}", code),
+                    None => String::from("terminated by a signal"),
+                };
+
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+
+                let lines = stdout
+                    .lines()
+                    .chain(stderr.lines())
+                    .filter(|line| !line.trim().is_empty())
+                    .take(config.capture_lines)
+                    .map(ToOwned::to_owned)
+                    .collect();
+
+                (status, lines)
+            }
+            Ok(None) => (
+                format!("timed out after {}s", config.capture_timeout),
+                Vec::new(),
+            ),
+            Err(why) => (format!("failed to run: {}", why), Vec::new()),
+        };
+
+        crate::send(&mut self.out, PluginResponse::Clear).await;
+
+        crate::send(
+            &mut self.out,
+            PluginResponse::Append(PluginSearchResult {
+                id: 0,
+                name: status,
+                description: exe.to_owned(),
+                ..Default::default()
+            }),
+        )
+        .await;
+
+        for (id, line) in lines.iter().enumerate() {
+            crate::send(
+                &mut self.out,
+                PluginResponse::Append(PluginSearchResult {
+                    id: id as u32 + 1,
+                    name: line.clone(),
+                    description: String::from("copy to clipboard"),
+                    ..Default::default()
+                }),
+            )
+            .await;
+        }
+
+        crate::send(&mut self.out, PluginResponse::Finished).await;
+
+        self.captured = Some(lines);
+    }
+
     async fn search(&mut self, query: String) {
         self.splice_input(&query).await;
         crate::send(&mut self.out, PluginResponse::Finished).await;
     }
 
     async fn splice_input(&mut self, mut query: &str) {
+        self.matches.clear();
+        self.captured = None;
+
         if let Some(q) = query.strip_prefix(':') {
             self.shell_only = true;
+            self.prefix = String::from(":");
             query = q.trim();
             self.last_query = Some(query.to_owned());
         } else {
             self.shell_only = false;
 
-            let query = if let Some(query) = query.strip_prefix("t:") {
-                query.trim()
+            let (prefix, query) = if let Some(query) = query.strip_prefix("t:") {
+                ("t:", query.trim())
             } else if let Some(pos) = query.find(' ') {
-                query[pos + 1..].trim()
+                (&query[..=pos], query[pos + 1..].trim())
             } else {
                 return;
             };
 
+            self.prefix = prefix.to_owned();
             self.last_query = Some(query.to_owned());
         }
 
@@ -113,58 +269,208 @@ impl App {
             }),
         )
         .await;
+
+        let typed = self.last_query.as_deref().unwrap_or_default();
+
+        self.matches = self
+            .history
+            .search(typed)
+            .filter(|command| *command != typed)
+            .take(HISTORY_RESULTS)
+            .map(ToOwned::to_owned)
+            .collect();
+
+        for (id, command) in self.matches.iter().enumerate() {
+            crate::send(
+                &mut self.out,
+                PluginResponse::Append(PluginSearchResult {
+                    id: id as u32 + 1,
+                    name: command.clone(),
+                    description: String::from("run command from history"),
+                    ..Default::default()
+                }),
+            )
+            .await;
+        }
     }
 }
 
-fn detect_terminal() -> (PathBuf, &'static str) {
-    use freedesktop_desktop_entry::DesktopEntry;
-    use std::fs::read_link;
-
-    let terminal_apps: Vec<_> =
-        freedesktop_desktop_entry::Iter::new(freedesktop_desktop_entry::default_paths())
-            .filter_map(|path| {
-                std::fs::read_to_string(&path).ok().and_then(|input| {
-                    DesktopEntry::from_str(&path, &input, Some(&get_languages_from_env()))
-                        .ok()
-                        .and_then(|de| {
-                            if de.no_display()
-                                || de
-                                    .categories()
-                                    .map(|c| c.iter().all(|c| *c != "TerminalEmulator"))
-                                    .unwrap_or(true)
-                                || de.exec().is_none()
-                            {
-                                return None;
-                            }
-
-                            Some((de.id().to_owned(), de.exec().unwrap().to_owned()))
-                        })
-                })
-            })
-            .collect();
+/// Completes the name of an executable in the directories of a `$PATH`
+/// value, up to the longest prefix which all of the matching executables share.
+fn complete_executable(word: &str, path: &OsStr) -> Option<String> {
+    if word.is_empty() {
+        return None;
+    }
 
-    for id in ["com.system76.CosmicTerm"] {
-        for (terminal_id, exec) in &terminal_apps {
-            if terminal_id.as_str() == id {
-                return (PathBuf::from(exec), "-e");
-            }
+    let matches: BTreeSet<String> = std::env::split_paths(path)
+        .filter_map(|dir| dir.read_dir().ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok().map(|n| (entry, n)))
+        .filter(|(_, name)| name.starts_with(word))
+        .filter(|(entry, _)| {
+            std::fs::metadata(entry.path())
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+        .map(|(_, name)| name)
+        .collect();
+
+    let mut names = matches.iter();
+    let first = names.next()?;
+
+    if matches.len() == 1 {
+        return Some([first, " "].concat());
+    }
+
+    let common = names.fold(first.as_str(), |common, name| {
+        let len = common
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(common.len().min(name.len()), |((index, _), _)| index);
+        &common[..len]
+    });
+
+    (common.len() > word.len()).then(|| common.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An app which captures the output of background commands.
+    fn capturing_app(capture_timeout: u64, capture_lines: usize) -> App<Vec<u8>> {
+        App {
+            last_query: None,
+            prefix: String::new(),
+            matches: Vec::new(),
+            history: History::default(),
+            config: Config {
+                capture_output: true,
+                capture_timeout,
+                capture_lines,
+                ..Config::default()
+            },
+            terminal: TerminalConfig::default(),
+            captured: None,
+            out: Vec::new(),
+            shell_only: false,
         }
     }
 
-    if let Some((id, exec)) = terminal_apps.first() {
-        let arg = if id == "org.gnome.Terminal" {
-            "--"
-        } else {
-            "-e"
-        };
-        return (PathBuf::from(exec), arg);
+    fn responses(out: &[u8]) -> Vec<PluginResponse> {
+        String::from_utf8_lossy(out)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn results(out: &[u8]) -> Vec<String> {
+        responses(out)
+            .into_iter()
+            .filter_map(|response| match response {
+                PluginResponse::Append(result) => Some(result.name),
+                _ => None,
+            })
+            .collect()
     }
 
-    const SYMLINK: &str = "/usr/bin/x-terminal-emulator";
+    #[tokio::test]
+    async fn captured_output_is_truncated() {
+        let mut app = capturing_app(5, 3);
 
-    if let Ok(found) = read_link(SYMLINK) {
-        return (read_link(&found).unwrap_or(found), "-e");
+        app.capture("printf 'one\\n\\ntwo\\n'; echo three >&2; echo four >&2; exit 2")
+            .await;
+
+        assert!(matches!(
+            responses(&app.out).first(),
+            Some(PluginResponse::Clear)
+        ));
+        assert!(matches!(
+            responses(&app.out).last(),
+            Some(PluginResponse::Finished)
+        ));
+        assert_eq!(
+            results(&app.out),
+            ["exited with status 2", "one", "two", "three"]
+        );
     }
 
-    (PathBuf::from("/usr/bin/gnome-terminal"), "--")
+    #[tokio::test]
+    async fn captured_commands_time_out() {
+        let mut app = capturing_app(1, 10);
+
+        app.capture("echo started; sleep 10").await;
+
+        assert_eq!(results(&app.out), ["timed out after 1s"]);
+        assert_eq!(app.captured.as_deref(), Some(&[][..]));
+    }
+
+    /// Text copied by activating a result.
+    async fn copied(app: &mut App<Vec<u8>>, id: u32) -> Option<String> {
+        app.out.clear();
+        app.activate(id).await;
+
+        match responses(&app.out).as_slice() {
+            [PluginResponse::Copy(text)] => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn captured_lines_are_copied() {
+        let mut app = capturing_app(5, 10);
+
+        app.capture("echo one; echo two").await;
+
+        assert_eq!(copied(&mut app, 2).await.as_deref(), Some("two"));
+        assert_eq!(copied(&mut app, 0).await.as_deref(), Some("one\ntwo"));
+        assert_eq!(copied(&mut app, 3).await, None);
+    }
+
+    #[test]
+    fn executables_are_completed() {
+        let dir =
+            std::env::temp_dir().join(format!("pop-launcher-terminal-path-{}", std::process::id()));
+        let (bin, sbin) = (dir.join("bin"), dir.join("sbin"));
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::create_dir_all(&sbin).unwrap();
+
+        for (dir, name, mode) in [
+            (&bin, "cargo", 0o755),
+            (&bin, "cargo-clippy", 0o755),
+            (&sbin, "cargo-fmt", 0o755),
+            (&bin, "firefox", 0o755),
+            (&bin, "firewall", 0o644),
+        ] {
+            let file = dir.join(name);
+            std::fs::write(&file, "").unwrap();
+            std::fs::set_permissions(&file, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        let path = std::env::join_paths([&bin, &sbin]).unwrap();
+
+        let completed = [
+            complete_executable("car", &path),
+            complete_executable("cargo-", &path),
+            complete_executable("cargo-c", &path),
+            complete_executable("fire", &path),
+            complete_executable("vim", &path),
+            complete_executable("", &path),
+        ];
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            completed,
+            [
+                Some(String::from("cargo")),
+                None,
+                Some(String::from("cargo-clippy ")),
+                Some(String::from("firefox ")),
+                None,
+                None,
+            ]
+        );
+    }
 }
@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Notifications of changes to the files within watched directories.
+
+use futures::StreamExt;
+use inotify::{Event, EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Changes which occur within this duration of each other are reported together.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+const MASK: WatchMask = WatchMask::CLOSE_WRITE
+    .union(WatchMask::CREATE)
+    .union(WatchMask::DELETE)
+    .union(WatchMask::MOVED_FROM)
+    .union(WatchMask::MOVED_TO);
+
+/// Added to the nearest existing ancestor of a directory which does not
+/// exist, to learn when it is created.
+const ANCESTOR_MASK: WatchMask = WatchMask::CREATE
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::ONLYDIR)
+    .union(WatchMask::MASK_ADD);
+
+/// Watches directories and their subdirectories, sending the paths which
+/// were changed once a burst of changes has settled.
+///
+/// Directories which do not exist are watched once they are created, which
+/// is reported as a change of the directory. Must be called from within
+/// the tokio runtime, where the watcher is spawned as a task which lives for
+/// as long as the receiver does.
+pub fn watch<I: IntoIterator<Item = PathBuf>>(
+    directories: I,
+) -> Option<flume::Receiver<HashSet<PathBuf>>> {
+    let inotify = match Inotify::init() {
+        Ok(inotify) => inotify,
+        Err(why) => {
+            tracing::error!("failed to initialize inotify: {}", why);
+            return None;
+        }
+    };
+
+    let mut watcher = Watcher {
+        watches: inotify.watches(),
+        watched: HashMap::new(),
+        directories: directories.into_iter().collect(),
+    };
+
+    watcher.sync(&mut HashSet::new());
+
+    let mut events = match inotify.into_event_stream([0u8; 4096]) {
+        Ok(events) => events,
+        Err(why) => {
+            tracing::error!("failed to stream inotify events: {}", why);
+            return None;
+        }
+    };
+
+    let (tx, rx) = flume::unbounded();
+
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let mut event = match event {
+                Ok(event) => Some(event),
+                Err(why) => {
+                    tracing::error!("failed to read inotify event: {}", why);
+                    continue;
+                }
+            };
+
+            let mut changed = HashSet::new();
+
+            while let Some(next) = event.take() {
+                watcher.handle(next, &mut changed);
+
+                match tokio::time::timeout(DEBOUNCE, events.next()).await {
+                    Ok(Some(Ok(next))) => event = Some(next),
+                    Ok(Some(Err(why))) => tracing::error!("failed to read inotify event: {}", why),
+                    _ => (),
+                }
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            if tx.send_async(changed).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Some(rx)
+}
+
+struct Watcher {
+    watches: Watches,
+    /// Directories which are watched for changes, by their watch descriptors.
+    watched: HashMap<WatchDescriptor, PathBuf>,
+    /// Directories which were requested to be watched.
+    directories: Vec<PathBuf>,
+}
+
+impl Watcher {
+    fn handle(&mut self, event: Event<OsString>, changed: &mut HashSet<PathBuf>) {
+        let Event { wd, mask, name, .. } = event;
+
+        if let Some(name) = name
+            && let Some(directory) = self.watched.get(&wd)
+        {
+            let path = directory.join(name);
+
+            if mask.contains(EventMask::ISDIR | EventMask::CREATE)
+                || mask.contains(EventMask::ISDIR | EventMask::MOVED_TO)
+            {
+                self.add_recursive(path.clone());
+            }
+
+            changed.insert(path);
+        }
+
+        if mask.contains(EventMask::IGNORED) {
+            self.watched.remove(&wd);
+        }
+
+        // A created directory may lead to one which does not exist yet, and
+        // a removed directory is watched again once it is created again.
+        if mask.intersects(EventMask::ISDIR | EventMask::IGNORED) {
+            self.sync(changed);
+        }
+    }
+
+    /// Watches the requested directories which are not watched, or the
+    /// nearest existing ancestor of those which do not exist.
+    fn sync(&mut self, changed: &mut HashSet<PathBuf>) {
+        for directory in self.directories.clone() {
+            if self.watched.values().any(|watched| *watched == directory) {
+                continue;
+            }
+
+            let mut watching = None;
+
+            // Repeated in case the directory is created before its ancestor is watched.
+            while !directory.is_dir() {
+                let Some(ancestor) = directory.ancestors().skip(1).find(|path| path.is_dir())
+                else {
+                    break;
+                };
+
+                if watching == Some(ancestor) {
+                    break;
+                }
+
+                if let Err(why) = self.watches.add(ancestor, ANCESTOR_MASK) {
+                    tracing::error!("failed to watch {}: {}", ancestor.display(), why);
+                    break;
+                }
+
+                watching = Some(ancestor);
+            }
+
+            if directory.is_dir() && self.add_recursive(directory.clone()) {
+                changed.insert(directory);
+            }
+        }
+    }
+
+    /// Watches the directory and its subdirectories, returning whether the
+    /// directory is watched.
+    fn add_recursive(&mut self, directory: PathBuf) -> bool {
+        let Ok(entries) = directory.read_dir() else {
+            return false;
+        };
+
+        match self.watches.add(&directory, MASK) {
+            Ok(wd) => {
+                self.watched.insert(wd, directory);
+            }
+            Err(why) => {
+                tracing::error!("failed to watch {}: {}", directory.display(), why);
+                return false;
+            }
+        }
+
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                self.add_recursive(path);
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    /// Whether the path is reported as changed before the changes settle.
+    async fn reports(changes: &flume::Receiver<HashSet<PathBuf>>, path: &Path) -> bool {
+        let wait = Duration::from_secs(5);
+
+        while let Ok(Ok(changed)) = tokio::time::timeout(wait, changes.recv_async()).await {
+            if changed.contains(path) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    #[tokio::test]
+    async fn directories_are_watched_once_created() {
+        let root =
+            std::env::temp_dir().join(format!("pop-launcher-watcher-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let existing = root.join("existing");
+        let missing = root.join("missing/applications");
+        fs::create_dir_all(&existing).unwrap();
+
+        let changes = watch([existing.clone(), missing.clone()]).unwrap();
+
+        let entry = existing.join("existing.desktop");
+        fs::write(&entry, "").unwrap();
+        assert!(reports(&changes, &entry).await);
+
+        fs::create_dir_all(&missing).unwrap();
+        assert!(reports(&changes, &missing).await);
+
+        let entry = missing.join("created.desktop");
+        fs::write(&entry, "").unwrap();
+        assert!(reports(&changes, &entry).await);
+
+        // Removed directories are watched again once they are created again.
+        fs::remove_dir_all(&existing).unwrap();
+        fs::create_dir_all(&existing).unwrap();
+        assert!(reports(&changes, &existing).await);
+
+        let entry = existing.join("recreated.desktop");
+        fs::write(&entry, "").unwrap();
+        assert!(reports(&changes, &entry).await);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,498 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+//! Windows of compositors which support `zwlr_foreign_toplevel_manager_v1`,
+//! such as sway, Hyprland, and river.
+//!
+//! Compositors which only support `ext_foreign_toplevel_list_v1` are not
+//! handled, because that protocol may list windows, but not activate or
+//! close them, so the plugin deactivates itself there.
+
+mod toplevel_handler;
+
+use fde::DesktopEntry;
+use freedesktop_desktop_entry as fde;
+use sctk::reexports::{
+    calloop, client::Connection,
+    protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+};
+use tracing::{debug, error, warn};
+
+use crate::desktop_entries::utils::get_description;
+use crate::handle_table::{self, HandleTable};
+use crate::send;
+use futures::{
+    StreamExt,
+    channel::mpsc,
+    future::{Either, select},
+};
+use pop_launcher::{
+    IconSource, PluginResponse, PluginSearchResult, Request, async_stdin, async_stdout,
+    json_input_stream,
+};
+use std::borrow::Cow;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use self::toplevel_handler::{ToplevelAction, ToplevelInfo, ToplevelUpdate, toplevel_handler};
+
+pub async fn main() {
+    let mut tx = async_stdout();
+
+    let Ok(conn) = Connection::connect_to_env() else {
+        send(&mut tx, PluginResponse::Deactivate).await;
+        return;
+    };
+
+    let (mut app, mut toplevel_rx) = App::new(conn, tx);
+
+    let mut requests = json_input_stream(async_stdin());
+    let mut next_request = requests.next();
+    let mut next_event = toplevel_rx.next();
+    loop {
+        let event = select(next_request, next_event).await;
+        match event {
+            Either::Left((Some(request), second_to_next_event)) => {
+                next_event = second_to_next_event;
+                next_request = requests.next();
+                match request {
+                    Ok(request) => match request {
+                        Request::Activate(id) => app.activate(id),
+                        Request::Quit(id) => app.quit(id),
+                        Request::Search(query) => {
+                            debug!("searching {query}");
+                            app.search(&query).await;
+                            // the ids of windows removed before this search are no longer shown
+                            app.handles.recycle();
+                        }
+                        Request::Exit => break,
+                        _ => (),
+                    },
+                    Err(why) => {
+                        error!("malformed JSON request: {}", why);
+                    }
+                };
+            }
+            Either::Right((Some(updates), second_to_next_request)) => {
+                next_event = toplevel_rx.next();
+                next_request = second_to_next_request;
+
+                for update in updates {
+                    app.update(update);
+                }
+            }
+            // The compositor has no wlr toplevel manager, or is COSMIC.
+            Either::Right((None, _)) => {
+                send(&mut app.tx, PluginResponse::Deactivate).await;
+                break;
+            }
+            _ => break,
+        }
+    }
+}
+
+struct App<W> {
+    locales: Vec<String>,
+    desktop_entries: Vec<DesktopEntry>,
+    handles: HandleTable<ZwlrForeignToplevelHandleV1>,
+    /// Windows in the order they were last activated in.
+    toplevels: Vec<(ZwlrForeignToplevelHandleV1, ToplevelInfo)>,
+    calloop_tx: calloop::channel::Sender<ToplevelAction>,
+    tx: W,
+}
+
+impl<W: AsyncWrite + Unpin> App<W> {
+    fn new(conn: Connection, tx: W) -> (Self, mpsc::UnboundedReceiver<Vec<ToplevelUpdate>>) {
+        let (toplevels_tx, toplevel_rx) = mpsc::unbounded();
+        let (calloop_tx, calloop_rx) = calloop::channel::channel();
+        let _handle = std::thread::spawn(move || toplevel_handler(conn, toplevels_tx, calloop_rx));
+
+        let locales = fde::get_languages_from_env();
+
+        let desktop_entries = fde::Iter::new(fde::default_paths())
+            .map(|path| DesktopEntry::from_path(path, Some(&locales)))
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        (
+            Self {
+                locales,
+                desktop_entries,
+                handles: HandleTable::default(),
+                toplevels: Vec::new(),
+                calloop_tx,
+                tx,
+            },
+            toplevel_rx,
+        )
+    }
+
+    fn activate(&mut self, id: u32) {
+        if let Some(handle) = self.handles.get(id) {
+            let _res = self
+                .calloop_tx
+                .send(ToplevelAction::Activate(handle.clone()));
+        }
+    }
+
+    fn quit(&mut self, id: u32) {
+        if let Some(handle) = self.handles.get(id) {
+            let _res = self.calloop_tx.send(ToplevelAction::Close(handle.clone()));
+        }
+    }
+
+    fn update(&mut self, update: ToplevelUpdate) {
+        match update {
+            ToplevelUpdate::Info(handle, info) => {
+                self.handles.insert(handle.clone());
+                let pos = self.toplevels.iter().position(|(h, _)| *h == handle);
+
+                match pos {
+                    Some(pos) if info.activated => {
+                        self.toplevels.remove(pos);
+                        self.toplevels.push((handle, info));
+                    }
+                    Some(pos) => self.toplevels[pos].1 = info,
+                    None => self.toplevels.push((handle, info)),
+                }
+            }
+            ToplevelUpdate::Remove(handle) => {
+                self.handles.remove(&handle);
+
+                if let Some(pos) = self.toplevels.iter().position(|(h, _)| *h == handle) {
+                    self.toplevels.remove(pos);
+                } else {
+                    warn!("no toplevel to remove");
+                }
+            }
+        }
+    }
+
+    async fn search(&mut self, query: &str) {
+        fn contains_pattern(needle: &str, haystack: &[&str]) -> bool {
+            let needle = needle.to_ascii_lowercase();
+            haystack.iter().all(|h| needle.contains(h))
+        }
+
+        let query = query.to_ascii_lowercase();
+        let haystack = query.split_ascii_whitespace().collect::<Vec<&str>>();
+
+        for (handle, info) in &self.toplevels {
+            let retain = query.is_empty()
+                || contains_pattern(&info.app_id, &haystack)
+                || contains_pattern(&info.title, &haystack);
+
+            if !retain {
+                continue;
+            }
+
+            let appid = fde::unicase::Ascii::new(info.app_id.as_str());
+
+            let entry = fde::find_app_by_id(&self.desktop_entries, appid)
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| fde::DesktopEntry::from_appid(appid.to_string()).to_owned());
+
+            let icon_name = if let Some(icon) = entry.icon() {
+                Cow::Owned(icon.to_owned())
+            } else {
+                Cow::Borrowed("application-x-executable")
+            };
+
+            let Some(key) = self.handles.key(handle) else {
+                continue;
+            };
+
+            let response = PluginResponse::Append(PluginSearchResult {
+                id: handle_table::result_id(key),
+                window: Some(key),
+                description: info.title.clone(),
+                name: get_description(&entry, &self.locales),
+                icon: Some(IconSource::Name(icon_name)),
+                app_id: Some(entry.appid.clone()),
+                ..Default::default()
+            });
+
+            send(&mut self.tx, response).await;
+        }
+
+        send(&mut self.tx, PluginResponse::Finished).await;
+        let _ = self.tx.flush().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use wayland_protocols::ext::foreign_toplevel_list::v1::server::ext_foreign_toplevel_list_v1::{
+        self, ExtForeignToplevelListV1 as List,
+    };
+    use wayland_protocols_wlr::foreign_toplevel::v1::server::{
+        zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1 as Toplevel},
+        zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1 as Manager,
+    };
+    use wayland_server::{
+        Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource,
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::wl_seat::{self, WlSeat},
+    };
+
+    type Requests = Arc<Mutex<Vec<String>>>;
+
+    /// A headless compositor with two windows, which records the requests
+    /// made of them.
+    struct Compositor {
+        requests: Requests,
+    }
+
+    struct TestClient(Arc<AtomicBool>);
+
+    impl ClientData for TestClient {
+        fn disconnected(&self, _: ClientId, _: DisconnectReason) {
+            self.0.store(false, Ordering::SeqCst);
+        }
+    }
+
+    impl Compositor {
+        fn run(stream: UnixStream, requests: Requests, with_manager: bool, with_list: bool) {
+            let mut display = Display::<Self>::new().unwrap();
+            let mut handle = display.handle();
+
+            handle.create_global::<Self, WlSeat, ()>(1, ());
+
+            if with_manager {
+                handle.create_global::<Self, Manager, ()>(3, ());
+            }
+
+            if with_list {
+                handle.create_global::<Self, List, ()>(1, ());
+            }
+
+            let connected = Arc::new(AtomicBool::new(true));
+            handle
+                .insert_client(stream, Arc::new(TestClient(connected.clone())))
+                .unwrap();
+
+            let mut compositor = Self { requests };
+
+            while connected.load(Ordering::SeqCst) {
+                let _ = display.dispatch_clients(&mut compositor);
+                let _ = display.flush_clients();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        }
+    }
+
+    impl GlobalDispatch<Manager, ()> for Compositor {
+        fn bind(
+            _: &mut Self,
+            handle: &DisplayHandle,
+            client: &Client,
+            resource: New<Manager>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            let manager = data_init.init(resource, ());
+
+            for (title, app_id, activated) in [
+                ("Terminal", "foot", false),
+                ("Notes", "org.gnome.TextEditor", true),
+            ] {
+                let toplevel = client
+                    .create_resource::<Toplevel, String, Self>(
+                        handle,
+                        manager.version(),
+                        title.to_owned(),
+                    )
+                    .unwrap();
+
+                manager.toplevel(&toplevel);
+                toplevel.title(title.to_owned());
+                toplevel.app_id(app_id.to_owned());
+
+                let state = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                toplevel.state(if activated {
+                    state.to_ne_bytes().to_vec()
+                } else {
+                    Vec::new()
+                });
+
+                toplevel.done();
+            }
+        }
+    }
+
+    impl Dispatch<Manager, ()> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            _: &Manager,
+            _: <Manager as Resource>::Request,
+            _: &(),
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<Toplevel, String> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            toplevel: &Toplevel,
+            request: zwlr_foreign_toplevel_handle_v1::Request,
+            title: &String,
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+            let mut requests = state.requests.lock().unwrap();
+
+            match request {
+                zwlr_foreign_toplevel_handle_v1::Request::Activate { .. } => {
+                    requests.push(format!("activate {title}"));
+                }
+                zwlr_foreign_toplevel_handle_v1::Request::Close => {
+                    requests.push(format!("close {title}"));
+                    toplevel.closed();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    impl GlobalDispatch<List, ()> for Compositor {
+        fn bind(
+            _: &mut Self,
+            _: &DisplayHandle,
+            _: &Client,
+            resource: New<List>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            data_init.init(resource, ());
+        }
+    }
+
+    impl Dispatch<List, ()> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            _: &List,
+            _: ext_foreign_toplevel_list_v1::Request,
+            _: &(),
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    impl GlobalDispatch<WlSeat, ()> for Compositor {
+        fn bind(
+            _: &mut Self,
+            _: &DisplayHandle,
+            _: &Client,
+            resource: New<WlSeat>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            data_init.init(resource, ());
+        }
+    }
+
+    impl Dispatch<WlSeat, ()> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            _: &WlSeat,
+            _: wl_seat::Request,
+            _: &(),
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    fn connect(requests: &Requests, with_manager: bool, with_list: bool) -> Connection {
+        let (client, server) = UnixStream::pair().unwrap();
+        let requests = requests.clone();
+        std::thread::spawn(move || Compositor::run(server, requests, with_manager, with_list));
+        Connection::from_socket(client).unwrap()
+    }
+
+    async fn next_updates(
+        updates: &mut mpsc::UnboundedReceiver<Vec<ToplevelUpdate>>,
+    ) -> Option<Vec<ToplevelUpdate>> {
+        tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await
+            .expect("compositor did not respond")
+    }
+
+    fn results(out: &[u8]) -> Vec<(u32, String)> {
+        String::from_utf8_lossy(out)
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line).unwrap() {
+                PluginResponse::Append(result) => Some((result.id, result.description)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn switches_windows_on_mock_compositor() {
+        let requests = Requests::default();
+        // The wlr manager is used where the ext list is also advertised.
+        let (mut app, mut updates) = App::new(connect(&requests, true, true), Vec::new());
+
+        while app.toplevels.len() < 2 {
+            for update in next_updates(&mut updates).await.unwrap() {
+                app.update(update);
+            }
+        }
+
+        app.search("").await;
+        let windows = results(&app.tx);
+        let titles: Vec<_> = windows.iter().map(|(_, title)| title.as_str()).collect();
+        assert_eq!(titles, ["Terminal", "Notes"]);
+
+        app.tx.clear();
+        app.search("term").await;
+        let [(terminal, _)] = results(&app.tx)[..] else {
+            panic!("expected one result");
+        };
+
+        app.activate(terminal);
+        app.quit(terminal);
+
+        while app.toplevels.len() > 1 {
+            for update in next_updates(&mut updates).await.unwrap() {
+                app.update(update);
+            }
+        }
+
+        // The id of the closed window is not given to another window until
+        // the results which show it are replaced.
+        assert!(app.handles.get(terminal).is_none());
+
+        app.tx.clear();
+        app.search("").await;
+        assert_eq!(results(&app.tx).len(), 1);
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["activate Terminal", "close Terminal"]
+        );
+    }
+
+    #[tokio::test]
+    async fn deactivates_without_toplevel_manager() {
+        let requests = Requests::default();
+
+        // The ext list alone cannot activate or close the windows it lists.
+        for with_list in [false, true] {
+            let (_app, mut updates) = App::new(connect(&requests, false, with_list), Vec::new());
+            assert!(next_updates(&mut updates).await.is_none());
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright © 2021 System76
+
+use std::collections::HashMap;
+
+use futures::channel::mpsc::UnboundedSender;
+use sctk::{
+    self,
+    reexports::{
+        calloop,
+        calloop_wayland_source::WaylandSource,
+        client::{
+            Connection, Dispatch, QueueHandle, event_created_child, globals::registry_queue_init,
+            protocol::wl_seat::WlSeat,
+        },
+        protocols_wlr::foreign_toplevel::v1::client::{
+            zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+            zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+        },
+    },
+    registry::{ProvidesRegistryState, RegistryState},
+    seat::{SeatHandler, SeatState},
+};
+use tracing::{info, warn};
+
+/// Windows in COSMIC are left to the `cosmic_toplevel` plugin, which has more
+/// actions than the wlr protocol offers.
+const COSMIC_TOPLEVEL_MANAGER: &str = "zcosmic_toplevel_manager_v1";
+
+const WLR_TOPLEVEL_MANAGER: &str = "zwlr_foreign_toplevel_manager_v1";
+
+/// Lists windows, but has no requests to activate or close them.
+const EXT_TOPLEVEL_LIST: &str = "ext_foreign_toplevel_list_v1";
+
+#[derive(Debug, Clone)]
+pub enum ToplevelAction {
+    Activate(ZwlrForeignToplevelHandleV1),
+    Close(ZwlrForeignToplevelHandleV1),
+}
+
+pub enum ToplevelUpdate {
+    Info(ZwlrForeignToplevelHandleV1, ToplevelInfo),
+    Remove(ZwlrForeignToplevelHandleV1),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ToplevelInfo {
+    pub title: String,
+    pub app_id: String,
+    pub activated: bool,
+}
+
+struct AppData {
+    exit: bool,
+    tx: UnboundedSender<Vec<ToplevelUpdate>>,
+    registry_state: RegistryState,
+    seat_state: SeatState,
+    /// Window properties received since the last `done` event of each window.
+    toplevels: HashMap<ZwlrForeignToplevelHandleV1, ToplevelInfo>,
+}
+
+impl AppData {
+    fn send(&self, update: ToplevelUpdate) {
+        if let Err(err) = self.tx.unbounded_send(vec![update]) {
+            warn!("{err}");
+        }
+    }
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!(SeatState);
+}
+
+impl SeatHandler for AppData {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlSeat) {}
+
+    fn new_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: WlSeat,
+        _: sctk::seat::Capability,
+    ) {
+    }
+
+    fn remove_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: WlSeat,
+        _: sctk::seat::Capability,
+    ) {
+    }
+
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlSeat) {}
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                state.toplevels.insert(toplevel, ToplevelInfo::default());
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => state.exit = true,
+            _ => (),
+        }
+    }
+
+    event_created_child!(AppData, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_handle_v1::Event::Closed = event {
+            state.toplevels.remove(handle);
+            handle.destroy();
+            state.send(ToplevelUpdate::Remove(handle.clone()));
+            return;
+        }
+
+        let Some(info) = state.toplevels.get_mut(handle) else {
+            return;
+        };
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => info.title = title,
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => info.app_id = app_id,
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                // The states are an array of native endian `u32` values.
+                let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                info.activated = states
+                    .chunks_exact(4)
+                    .any(|value| value == activated.to_ne_bytes());
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                let update = ToplevelUpdate::Info(handle.clone(), info.clone());
+                state.send(update);
+            }
+            _ => (),
+        }
+    }
+}
+
+pub(crate) fn toplevel_handler(
+    conn: Connection,
+    tx: UnboundedSender<Vec<ToplevelUpdate>>,
+    rx: calloop::channel::Channel<ToplevelAction>,
+) -> anyhow::Result<()> {
+    let (globals, event_queue) = registry_queue_init(&conn)?;
+
+    let advertised = |interface: &str| {
+        globals
+            .contents()
+            .with_list(|list| list.iter().any(|global| global.interface == interface))
+    };
+
+    if advertised(COSMIC_TOPLEVEL_MANAGER) {
+        return Ok(());
+    }
+
+    // Windows which could be neither activated nor closed are not listed.
+    if !advertised(WLR_TOPLEVEL_MANAGER) && advertised(EXT_TOPLEVEL_LIST) {
+        info!(
+            "{EXT_TOPLEVEL_LIST} cannot activate or close windows without {WLR_TOPLEVEL_MANAGER}"
+        );
+        return Ok(());
+    }
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new()?;
+    let qh = event_queue.handle();
+
+    // Without the manager, dropping `tx` tells the plugin to deactivate.
+    let _manager: ZwlrForeignToplevelManagerV1 = globals.bind(&qh, 1..=3, ())?;
+
+    let wayland_source = WaylandSource::new(conn, event_queue);
+    let handle = event_loop.handle();
+
+    handle.insert_source(wayland_source, |_, q, state| q.dispatch_pending(state))?;
+
+    let _ = handle.insert_source(rx, |event, _, state| match event {
+        calloop::channel::Event::Msg(ToplevelAction::Activate(handle)) => {
+            for seat in state.seat_state.seats() {
+                handle.activate(&seat);
+            }
+        }
+        calloop::channel::Event::Msg(ToplevelAction::Close(handle)) => handle.close(),
+        calloop::channel::Event::Closed => state.exit = true,
+    });
+
+    let mut app_data = AppData {
+        exit: false,
+        tx,
+        seat_state: SeatState::new(&globals, &qh),
+        registry_state: RegistryState::new(&globals),
+        toplevels: HashMap::new(),
+    };
+
+    loop {
+        if app_data.exit {
+            break Ok(());
+        }
+        event_loop.dispatch(None, &mut app_data)?;
+    }
+}
+
+sctk::delegate_seat!(AppData);
+sctk::delegate_registry!(AppData);
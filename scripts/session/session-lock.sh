@@ -1,10 +0,0 @@
-#!/usr/bin/env sh
-#
-# name: Lock
-# icon: locked
-# description: Lock the screen
-# keywords: lock
-
-set -eu
-
-xdg-screensaver lock
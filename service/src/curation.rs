@@ -0,0 +1,157 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use pop_launcher::PluginSearchResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Identifies a search result across searches by the plugin that produced it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct ResultKey {
+    pub plugin: String,
+    pub id: String,
+}
+
+impl ResultKey {
+    /// Results are recognized in later searches by the application that they
+    /// launch, or else their command, or else their name. Windows are not, as
+    /// they are not found again once closed.
+    pub fn new(plugin: &str, result: &PluginSearchResult) -> Option<Self> {
+        if result.window.is_some() {
+            return None;
+        }
+
+        let id = result
+            .app_id
+            .as_ref()
+            .or(result.exec.as_ref())
+            .unwrap_or(&result.name);
+
+        Some(Self {
+            plugin: plugin.to_owned(),
+            id: id.clone(),
+        })
+    }
+}
+
+/// A favorite, with the query that its plugin finds it by.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Favorite {
+    #[serde(flatten)]
+    pub key: ResultKey,
+    pub query: String,
+}
+
+/// Search results which the user has pinned, hidden, or added to their favorites.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CurationStorage {
+    #[serde(default)]
+    favorites: Vec<Favorite>,
+    #[serde(default)]
+    pinned: Vec<ResultKey>,
+    #[serde(default)]
+    hidden: HashSet<ResultKey>,
+    /// File that the curation is stored in, if it was loaded from one.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl CurationStorage {
+    /// Favorites in the order that they are displayed.
+    pub fn favorites(&self) -> &[Favorite] {
+        &self.favorites
+    }
+
+    pub fn favorite_position(&self, key: &ResultKey) -> Option<usize> {
+        self.favorites
+            .iter()
+            .position(|favorite| favorite.key == *key)
+    }
+
+    pub fn is_pinned(&self, key: &ResultKey) -> bool {
+        self.pinned.contains(key)
+    }
+
+    pub fn is_hidden(&self, key: &ResultKey) -> bool {
+        self.hidden.contains(key)
+    }
+
+    pub fn toggle_pin(&mut self, key: ResultKey) {
+        match self.pinned.iter().position(|pinned| *pinned == key) {
+            Some(pos) => {
+                self.pinned.remove(pos);
+            }
+            None => self.pinned.push(key),
+        }
+    }
+
+    /// Adds a favorite which is found again by searching for `query`, or
+    /// removes the favorite.
+    pub fn toggle_favorite(&mut self, key: ResultKey, query: String) {
+        match self.favorite_position(&key) {
+            Some(pos) => {
+                self.favorites.remove(pos);
+            }
+            None => self.favorites.push(Favorite { key, query }),
+        }
+    }
+
+    /// Swaps a favorite with its neighbor, moving it up or down the list.
+    pub fn move_favorite(&mut self, key: &ResultKey, up: bool) {
+        let Some(pos) = self.favorite_position(key) else {
+            return;
+        };
+
+        let other = if up {
+            pos.checked_sub(1)
+        } else {
+            Some(pos + 1).filter(|&other| other < self.favorites.len())
+        };
+
+        if let Some(other) = other {
+            self.favorites.swap(pos, other);
+        }
+    }
+
+    /// Hides a result from all future searches, which also unpins it and
+    /// removes it from the favorites.
+    pub fn hide(&mut self, key: ResultKey) {
+        self.favorites.retain(|favorite| favorite.key != key);
+        self.pinned.retain(|pinned| *pinned != key);
+        self.hidden.insert(key);
+    }
+}
+
+pub fn load() -> CurationStorage {
+    let Ok(path) = crate::ensure_cache_dir().map(|dir| dir.join("curation")) else {
+        return CurationStorage::default();
+    };
+
+    let read_curation = || -> Result<CurationStorage, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(&path)?;
+        Ok(serde_json::from_reader(file)?)
+    };
+
+    let mut storage = read_curation().unwrap_or_default();
+    storage.path = Some(path);
+    storage
+}
+
+/// Writes the curation to the file that it was loaded from.
+pub fn store(storage: &CurationStorage) {
+    let Some(path) = storage.path.as_ref() else {
+        return;
+    };
+
+    let write_curation = || -> Result<(), Box<dyn std::error::Error>> {
+        Ok(serde_json::to_writer(
+            std::fs::File::create(path)?,
+            storage,
+        )?)
+    };
+
+    if let Err(e) = write_curation() {
+        eprintln!("could not write to curation file\n{}", e);
+    }
+}
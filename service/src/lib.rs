@@ -2,51 +2,70 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod client;
+mod curation;
 mod plugins;
 mod priority;
 mod recent;
+mod settings;
 
 pub use client::*;
 pub use plugins::config;
 pub use plugins::external::load;
 
+use crate::curation::{CurationStorage, Favorite, ResultKey};
 use crate::plugins::{
     ExternalPlugin, HelpPlugin, Plugin, PluginConfig, PluginConnector, PluginPriority, PluginQuery,
 };
 use crate::priority::Priority;
 use crate::recent::RecentUseStorage;
+use crate::settings::Settings;
 use clap::Parser;
 use flume::{Receiver, Sender};
+use freedesktop_desktop_entry::DesktopEntry;
 use futures::{SinkExt, Stream, StreamExt, future};
 use pop_launcher::{
-    ContextOption, IconSource, Indice, PluginResponse, PluginSearchResult, Request, Response,
-    SearchResult, json_input_stream, plugin_paths,
+    ContextOption, FrontendOptions, GpuPreference, IconSource, Indice, PluginResponse,
+    PluginSearchResult, Request, Response, SERVICE_CONTEXT_OFFSET, SearchResult, json_input_stream,
+    launch, plugin_paths,
 };
 use regex::Regex;
 use slab::Slab;
-use std::usize;
 use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     io::{self, Write},
     path::PathBuf,
 };
 
 pub type PluginKey = usize;
 
+const CONTEXT_PIN: Indice = SERVICE_CONTEXT_OFFSET;
+const CONTEXT_HIDE: Indice = SERVICE_CONTEXT_OFFSET + 1;
+const CONTEXT_COPY_NAME: Indice = SERVICE_CONTEXT_OFFSET + 2;
+const CONTEXT_COPY_DESCRIPTION: Indice = SERVICE_CONTEXT_OFFSET + 3;
+const CONTEXT_FAVORITE: Indice = SERVICE_CONTEXT_OFFSET + 4;
+const CONTEXT_MOVE_UP: Indice = SERVICE_CONTEXT_OFFSET + 5;
+const CONTEXT_MOVE_DOWN: Indice = SERVICE_CONTEXT_OFFSET + 6;
+const CONTEXT_FOCUS_WINDOW: Indice = SERVICE_CONTEXT_OFFSET + 7;
+const CONTEXT_NEW_INSTANCE: Indice = SERVICE_CONTEXT_OFFSET + 8;
+
+/// Section of the favorites which are displayed on an empty query.
+pub const FAVORITES_SECTION: &str = "Favorites";
+
 /// Bounds for the number of service responses.
+///
+/// These apply to each plugin that does not define its own `max_results`.
 #[derive(Parser, Debug, Clone, Copy)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Max number of files in a response.
+    /// Max number of files per plugin in a response.
     #[arg(short, long, default_value_t = 100)]
     pub max_files: usize,
 
-    /// Max number of open windows in a response.
+    /// Max number of open windows per plugin in a response.
     #[arg(short, long, default_value_t = 8)]
     pub max_open: usize,
 
-    /// Max number of generic items in a response.
+    /// Max number of generic items per plugin in a response.
     #[arg(short, long, default_value_t = 8)]
     pub max_search: usize,
 }
@@ -61,6 +80,9 @@ impl Default for Args {
     }
 }
 
+// Responses carry search results, and are the most common event, so boxing
+// them would allocate for nearly every event.
+#[allow(clippy::large_enum_variant)]
 pub enum Event {
     Request(Request),
     Response((PluginKey, PluginResponse)),
@@ -74,12 +96,16 @@ pub struct PluginHelp {
     pub help: Option<String>,
 }
 
-pub fn ensure_cache_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+pub fn ensure_cache_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
     let cachepath = dirs::home_dir()
         .ok_or("failed to find home dir")?
         .join(".cache/pop-launcher");
     std::fs::create_dir_all(&cachepath)?;
-    Ok(cachepath.join("recent"))
+    Ok(cachepath)
+}
+
+pub fn ensure_cache_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(ensure_cache_dir()?.join("recent"))
 }
 
 pub fn store_cache(storage: &RecentUseStorage) {
@@ -141,16 +167,52 @@ pub async fn main() {
     futures::future::join(service, responder).await;
 }
 
+/// A request for a search result, which is sent once its plugin has found the
+/// result again.
+struct Research {
+    plugin: PluginKey,
+    key: ResultKey,
+    /// Position of the result in the active search.
+    position: Indice,
+    request: Request,
+    /// ID of the result in the plugin's new search.
+    found: Option<Indice>,
+}
+
 pub struct Service<O> {
     active_search: Vec<(PluginKey, PluginSearchResult)>,
-    associated_list: HashMap<Indice, Indice>,
+    associated_list: HashMap<(PluginKey, Indice), Indice>,
     awaiting_results: HashSet<PluginKey>,
+    curation: CurationStorage,
+    /// Favorites that remain to be searched for, per plugin.
+    favorite_queue: HashMap<PluginKey, VecDeque<Favorite>>,
+    frontend: FrontendOptions,
+    /// GPUs reported by switcheroo-control, which are queried on first use.
+    gpus: Option<Vec<launch::Gpu>>,
     last_query: String,
+    /// A `LoadMore` was requested while searching.
+    load_more_scheduled: bool,
+    /// Results of the active search are beyond the loaded page.
+    more: bool,
     no_sort: bool,
     output: O,
+    page: usize,
+    pending_context: Option<Indice>,
+    /// The query that each plugin last searched for, which the IDs of its results refer to.
+    plugin_queries: HashMap<PluginKey, String>,
     plugins: Slab<PluginConnector>,
+    /// A result which its plugin is searching for again.
+    researching: Option<Research>,
+    /// Favorites which were found by searching for them, rather than by the
+    /// active search, with the queries that found them.
+    resolved: HashMap<ResultKey, String>,
+    /// The favorite that each plugin is currently searching for.
+    resolving: HashMap<PluginKey, Favorite>,
     search_scheduled: bool,
     recent: RecentUseStorage,
+    /// Open windows by the lowercased ID of their application.
+    running: HashMap<String, (PluginKey, Indice)>,
+    settings: Settings,
     args: Args,
 }
 
@@ -160,12 +222,26 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             active_search: Vec::new(),
             associated_list: HashMap::new(),
             awaiting_results: HashSet::new(),
+            curation: curation::load(),
+            favorite_queue: HashMap::new(),
+            frontend: FrontendOptions::default(),
+            gpus: None,
             last_query: String::new(),
+            load_more_scheduled: false,
+            more: false,
             output,
             no_sort: false,
+            page: 0,
+            pending_context: None,
+            plugin_queries: HashMap::new(),
             plugins: Slab::new(),
+            researching: None,
+            resolved: HashMap::new(),
+            resolving: HashMap::new(),
             search_scheduled: false,
             recent,
+            running: HashMap::new(),
+            settings: settings::load(),
             args: Args::default(),
         }
     }
@@ -220,7 +296,10 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             match event {
                 Event::Request(request) => {
                     match request {
-                        Request::Search(query) => self.search(query).await,
+                        Request::Search(query) => {
+                            self.load_more_scheduled = false;
+                            self.search(query).await;
+                        }
                         Request::Interrupt => self.interrupt().await,
                         Request::Activate(id) => self.activate(id).await,
                         Request::ActivateContext { id, context } => {
@@ -228,6 +307,8 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
                         }
                         Request::Complete(id) => self.complete(id).await,
                         Request::Context(id) => self.context(id).await,
+                        Request::LoadMore => self.load_more().await,
+                        Request::Configure(options) => self.frontend = options,
                         Request::Quit(id) => self.quit(id).await,
 
                         // When requested to exit, the service will forward that
@@ -254,11 +335,12 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
 
                 Event::Response((plugin, response)) => match response {
                     PluginResponse::Append(item) => self.append(plugin, item),
-                    PluginResponse::Clear => self.clear(),
+                    PluginResponse::Clear => self.clear(plugin),
                     PluginResponse::Close => self.close().await,
                     PluginResponse::Context { id, options } => {
-                        self.context_response(id, options).await;
+                        self.context_response(plugin, id, options).await;
                     }
+                    PluginResponse::Copy(text) => self.respond(Response::Copy(text)).await,
                     PluginResponse::Fill(text) => self.fill(text).await,
                     PluginResponse::Finished => self.finished(plugin).await,
                     PluginResponse::DesktopEntry {
@@ -266,15 +348,20 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
                         gpu_preference,
                         action_name,
                     } => {
-                        self.respond(Response::DesktopEntry {
-                            path,
-                            gpu_preference,
-                            action_name,
-                        })
-                        .await;
+                        if self.frontend.launch_desktop_entries {
+                            self.launch(path, gpu_preference, action_name).await;
+                        } else {
+                            self.respond(Response::DesktopEntry {
+                                path,
+                                gpu_preference,
+                                action_name,
+                            })
+                            .await;
+                        }
                     }
                     // Report the plugin as finished and remove it from future polling
                     PluginResponse::Deactivate => {
+                        self.favorite_queue.remove(&plugin);
                         self.finished(plugin).await;
                         let _res = self.plugins.remove(plugin);
                     }
@@ -337,14 +424,26 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
     }
 
     async fn activate(&mut self, id: Indice) {
-        let mut ex = None;
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            ex = meta.cache_identifier();
-            let _res = plugin
-                .sender_exec()
-                .send_async(Request::Activate(meta.id))
-                .await;
+        if self.settings.focus_running_apps && self.focus_running(id).await {
+            self.record_use(id);
+            return;
         }
+
+        self.activate_result(id).await;
+    }
+
+    /// Activates the result in its plugin, and records its use.
+    async fn activate_result(&mut self, id: Indice) {
+        self.record_use(id);
+        self.send_for_result(id, Request::Activate).await;
+    }
+
+    /// Records the use of a result, if its plugin keeps a history.
+    fn record_use(&mut self, id: Indice) {
+        let ex = self
+            .search_result(id as usize)
+            .and_then(|(plugin, meta)| meta.cache_identifier().filter(|_| plugin.config.history));
+
         if let Some(e) = ex {
             self.recent.add(&e);
             store_cache(&self.recent);
@@ -352,58 +451,296 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
     }
 
     async fn activate_context(&mut self, id: Indice, context: Indice) {
-        let mut ex = None;
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            ex = meta.cache_identifier();
-            let _res = plugin
-                .sender_exec()
-                .send_async(Request::ActivateContext {
-                    id: meta.id,
-                    context,
-                })
-                .await;
+        if context >= SERVICE_CONTEXT_OFFSET {
+            self.activate_service_context(id, context).await;
+            return;
         }
-        if let Some(e) = ex {
-            self.recent.add(&e);
-            store_cache(&self.recent);
+
+        self.record_use(id);
+        self.send_for_result(id, |id| Request::ActivateContext { id, context })
+            .await;
+    }
+
+    /// Handles the context options that the service provides for every result.
+    async fn activate_service_context(&mut self, id: Indice, context: Indice) {
+        let Some((plugin, meta)) = self.active_search.get(id as usize) else {
+            return;
+        };
+
+        match context {
+            CONTEXT_COPY_NAME => {
+                let name = meta.name.clone();
+                self.respond(Response::Copy(name)).await;
+            }
+
+            CONTEXT_COPY_DESCRIPTION => {
+                let description = meta.description.clone();
+                self.respond(Response::Copy(description)).await;
+            }
+
+            CONTEXT_PIN | CONTEXT_HIDE => {
+                let Some(key) = self.result_key(*plugin, meta) else {
+                    return;
+                };
+
+                if context == CONTEXT_PIN {
+                    self.curation.toggle_pin(key);
+                } else {
+                    self.curation.hide(key);
+                }
+
+                curation::store(&self.curation);
+
+                self.sort();
+                self.respond_results().await;
+            }
+
+            CONTEXT_FAVORITE => {
+                let Some(key) = self.result_key(*plugin, meta) else {
+                    return;
+                };
+
+                // The favorite is found again by the query that found it, or
+                // by its name where it was found on an empty query.
+                let query = if self.last_query.is_empty() {
+                    meta.name.clone()
+                } else {
+                    self.last_query.clone()
+                };
+
+                self.curation.toggle_favorite(key, query);
+                curation::store(&self.curation);
+
+                // The favorites are only displayed on an empty query.
+                if self.last_query.is_empty() {
+                    self.search(String::new()).await;
+                }
+            }
+
+            CONTEXT_FOCUS_WINDOW => {
+                if self.focus_running(id).await {
+                    self.record_use(id);
+                } else {
+                    self.activate_result(id).await;
+                }
+            }
+
+            CONTEXT_NEW_INSTANCE => self.activate_result(id).await,
+
+            CONTEXT_MOVE_UP | CONTEXT_MOVE_DOWN => {
+                let Some(key) = self.result_key(*plugin, meta) else {
+                    return;
+                };
+
+                self.curation
+                    .move_favorite(&key, context == CONTEXT_MOVE_UP);
+                curation::store(&self.curation);
+
+                self.sort();
+                self.respond_results().await;
+            }
+
+            _ => (),
         }
     }
 
     fn append(&mut self, plugin: PluginKey, append: PluginSearchResult) {
+        // A search for a result again only finds the result's new ID.
+        if self
+            .researching
+            .as_ref()
+            .is_some_and(|research| research.plugin == plugin)
+        {
+            let key = self.result_key(plugin, &append);
+
+            if let Some(research) = self.researching.as_mut()
+                && key.as_ref() == Some(&research.key)
+            {
+                research.found.get_or_insert(append.id);
+            }
+
+            return;
+        }
+
+        // Searches for a favorite may only contribute the favorite itself, once.
+        if let Some(favorite) = self.resolving.get(&plugin) {
+            let is_favorite = |meta: &PluginSearchResult| {
+                self.result_key(plugin, meta).as_ref() == Some(&favorite.key)
+            };
+
+            if !is_favorite(&append)
+                || self
+                    .active_search
+                    .iter()
+                    .any(|(key, meta)| *key == plugin && is_favorite(meta))
+            {
+                return;
+            }
+
+            self.resolved
+                .insert(favorite.key.clone(), favorite.query.clone());
+        }
+
         self.active_search.push((plugin, append));
     }
 
-    fn clear(&mut self) {
+    /// Clears the results, which the plugin replaces with results of its own.
+    ///
+    /// These are only sorted if the plugin's results would be. Plugins which
+    /// are searching for a favorite, or searching for a result again, cannot
+    /// clear the results of the others.
+    fn clear(&mut self, plugin: PluginKey) {
+        if self.resolving.contains_key(&plugin)
+            || self
+                .researching
+                .as_ref()
+                .is_some_and(|research| research.plugin == plugin)
+        {
+            return;
+        }
+
         self.active_search.clear();
+
+        if let Some(plugin) = self.plugins.get(plugin) {
+            self.no_sort = plugin.config.query.no_sort;
+        }
     }
 
     async fn close(&mut self) {
         self.respond(Response::Close).await;
     }
 
-    async fn context_response(&mut self, id: Indice, options: Vec<ContextOption>) {
-        if let Some(id) = self.associated_list.get(&id) {
+    async fn context_response(
+        &mut self,
+        plugin: PluginKey,
+        id: Indice,
+        mut options: Vec<ContextOption>,
+    ) {
+        if let Some(id) = self.associated_list.get(&(plugin, id)) {
             let id = *id;
+
+            // Options requested by the frontend are completed with the service's options.
+            if self
+                .pending_context
+                .take_if(|pending| *pending == id)
+                .is_some()
+            {
+                options.extend(self.service_context(id));
+            }
+
             self.respond(Response::Context { id, options }).await;
         }
     }
 
     async fn complete(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _res = plugin
-                .sender_exec()
-                .send_async(Request::Complete(meta.id))
-                .await;
-        }
+        self.send_for_result(id, Request::Complete).await;
     }
 
+    /// Responds with the service's context options, and requests the plugin's options.
+    ///
+    /// Plugins which have options of their own will cause the response to be
+    /// sent again with their options included.
     async fn context(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _res = plugin
-                .sender_exec()
-                .send_async(Request::Context(meta.id))
-                .await;
+        self.send_for_result(id, Request::Context).await;
+
+        self.pending_context = Some(id);
+
+        let options = self.service_context(id);
+        if !options.is_empty() {
+            self.respond(Response::Context { id, options }).await;
+        }
+    }
+
+    /// Context options which the service provides for every search result.
+    fn service_context(&self, id: Indice) -> Vec<ContextOption> {
+        let mut options = Vec::new();
+
+        let Some((plugin, meta)) = self.active_search.get(id as usize) else {
+            return options;
+        };
+
+        if self.running_window(id).is_some() {
+            options.push(ContextOption {
+                id: CONTEXT_FOCUS_WINDOW,
+                name: "Focus Existing Window".to_owned(),
+                ..Default::default()
+            });
+
+            options.push(ContextOption {
+                id: CONTEXT_NEW_INSTANCE,
+                name: "Launch New Instance".to_owned(),
+                ..Default::default()
+            });
+        }
+
+        if let Some(key) = self.result_key(*plugin, meta) {
+            options.push(ContextOption {
+                id: CONTEXT_PIN,
+                name: if self.curation.is_pinned(&key) {
+                    "Unpin"
+                } else {
+                    "Pin to Top"
+                }
+                .to_owned(),
+                ..Default::default()
+            });
+
+            options.push(ContextOption {
+                id: CONTEXT_HIDE,
+                name: "Hide from Results".to_owned(),
+                ..Default::default()
+            });
+
+            let position = self.curation.favorite_position(&key);
+
+            options.push(ContextOption {
+                id: CONTEXT_FAVORITE,
+                name: if position.is_some() {
+                    "Remove from Favorites"
+                } else {
+                    "Add to Favorites"
+                }
+                .to_owned(),
+                ..Default::default()
+            });
+
+            // Favorites may be reordered where they are displayed.
+            if let Some(position) = position
+                && self.last_query.is_empty()
+            {
+                if position > 0 {
+                    options.push(ContextOption {
+                        id: CONTEXT_MOVE_UP,
+                        name: "Move Up".to_owned(),
+                        ..Default::default()
+                    });
+                }
+
+                if position + 1 < self.curation.favorites().len() {
+                    options.push(ContextOption {
+                        id: CONTEXT_MOVE_DOWN,
+                        name: "Move Down".to_owned(),
+                        ..Default::default()
+                    });
+                }
+            }
         }
+
+        options.push(ContextOption {
+            id: CONTEXT_COPY_NAME,
+            name: "Copy Name".to_owned(),
+            ..Default::default()
+        });
+
+        if !meta.description.is_empty() {
+            options.push(ContextOption {
+                id: CONTEXT_COPY_DESCRIPTION,
+                name: "Copy Description".to_owned(),
+                ..Default::default()
+            });
+        }
+
+        options
     }
 
     async fn fill(&mut self, text: String) {
@@ -411,6 +748,20 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
     }
 
     async fn finished(&mut self, plugin: PluginKey) {
+        if let Some(research) = self
+            .researching
+            .take_if(|research| research.plugin == plugin)
+        {
+            self.researched(research).await;
+            return;
+        }
+
+        self.resolving.remove(&plugin);
+
+        if !self.search_scheduled && self.resolve_next_favorite(plugin).await {
+            return;
+        }
+
         self.awaiting_results.remove(&plugin);
         if !self.awaiting_results.is_empty() {
             return;
@@ -421,9 +772,105 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             return;
         }
 
-        let search_list = self.sort();
+        self.update_running();
+        self.sort();
 
-        self.respond(Response::Update(search_list)).await;
+        if std::mem::take(&mut self.load_more_scheduled) {
+            self.results();
+            if self.more {
+                self.page += 1;
+            }
+        }
+
+        self.respond_results().await;
+    }
+
+    /// Focuses the open window of an application instead of launching it,
+    /// returning `true` if the window's plugin was requested to focus it.
+    async fn focus_running(&mut self, id: Indice) -> bool {
+        let Some((plugin, window)) = self.running_window(id) else {
+            return false;
+        };
+
+        let Some(plugin) = self.plugins.get_mut(plugin) else {
+            return false;
+        };
+
+        plugin
+            .sender_exec()
+            .send_async(Request::Activate(window))
+            .await
+            .is_ok()
+    }
+
+    /// The open window of the application which a result launches.
+    fn running_window(&self, id: Indice) -> Option<(PluginKey, Indice)> {
+        let (_, meta) = self.active_search.get(id as usize)?;
+
+        if meta.window.is_some() {
+            return None;
+        }
+
+        let app_id = meta.app_id.as_ref()?.to_ascii_lowercase();
+        let window = self.running.get(&app_id).copied()?;
+
+        // The window is gone once its plugin no longer lists it.
+        self.active_search
+            .iter()
+            .any(|(plugin, meta)| meta.window.is_some() && (*plugin, meta.id) == window)
+            .then_some(window)
+    }
+
+    /// Records the windows of the finished search by their application,
+    /// replacing those of the previous search, whose IDs are no longer valid.
+    ///
+    /// Applications are only focused while their windows are listed by the
+    /// window plugins, and are otherwise launched. Window plugins only list
+    /// the windows that match the query, so an application found by a query
+    /// that its windows do not match, such as one of its keywords, is not
+    /// known to be running.
+    fn update_running(&mut self) {
+        self.running.clear();
+
+        for (plugin, meta) in &self.active_search {
+            if meta.window.is_some()
+                && let Some(app_id) = meta.app_id.as_ref()
+            {
+                self.running
+                    .entry(app_id.to_ascii_lowercase())
+                    .or_insert((*plugin, meta.id));
+            }
+        }
+    }
+
+    /// Searches for the next favorite of a plugin, returning `true` if a search was sent.
+    ///
+    /// Favorites are searched for one at a time, so that the results of each
+    /// search can be matched against the favorite that was searched for.
+    async fn resolve_next_favorite(&mut self, plugin_key: PluginKey) -> bool {
+        let Some(queue) = self.favorite_queue.get_mut(&plugin_key) else {
+            return false;
+        };
+
+        let Some(plugin) = self.plugins.get_mut(plugin_key) else {
+            return false;
+        };
+
+        while let Some(favorite) = queue.pop_front() {
+            if plugin
+                .sender_exec()
+                .send_async(Request::Search(favorite.query.clone()))
+                .await
+                .is_ok()
+            {
+                self.plugin_queries
+                    .insert(plugin_key, favorite.query.clone());
+                self.resolving.insert(plugin_key, favorite);
+                return true;
+            }
+        }
+
+        false
     }
 
     async fn interrupt(&mut self) {
@@ -434,19 +881,208 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
         }
     }
 
+    /// Launches a desktop entry on behalf of the frontend.
+    ///
+    /// Applications which are D-Bus activatable are activated through D-Bus,
+    /// with their Exec key as the fallback.
+    async fn launch(
+        &mut self,
+        path: PathBuf,
+        gpu_preference: GpuPreference,
+        action: Option<String>,
+    ) {
+        let entry = match DesktopEntry::from_path(&path, None::<&[&str]>) {
+            Ok(entry) => entry,
+            Err(why) => {
+                tracing::error!("failed to read {}: {}", path.display(), why);
+                return;
+            }
+        };
+
+        let environment = self.gpu_environment(gpu_preference).await;
+
+        // D-Bus activation waits for the application to start, which the
+        // service does not wait on before responding to the frontend.
+        tokio::spawn(async move {
+            let action = action.as_deref();
+
+            if launch::dbus_activate(&entry, action).await {
+                return;
+            }
+
+            let Some(mut command) = launch::command(&entry, action, environment) else {
+                tracing::error!("{} has no command to execute", path.display());
+                return;
+            };
+
+            // Detach the application from the service's process group.
+            std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+            if let Err(why) = tokio::process::Command::from(command).spawn() {
+                tracing::error!("failed to launch {}: {}", path.display(), why);
+            }
+        });
+
+        self.respond(Response::Close).await;
+    }
+
+    /// Environment variables which select the GPU that an application renders
+    /// with, as reported by switcheroo-control.
+    async fn gpu_environment(&mut self, gpu_preference: GpuPreference) -> Vec<(String, String)> {
+        let index = match gpu_preference {
+            GpuPreference::Default => return Vec::new(),
+            GpuPreference::NonDefault => None,
+            GpuPreference::SpecificIdx(index) => Some(index as usize),
+        };
+
+        if self.gpus.is_none() {
+            self.gpus = Some(launch::gpus().await.unwrap_or_default());
+        }
+
+        let gpus = self.gpus.as_deref().unwrap_or_default();
+
+        let gpu = match index {
+            Some(index) => gpus.get(index),
+            None => gpus.iter().find(|gpu| !gpu.default),
+        };
+
+        gpu.map(|gpu| {
+            gpu.environment
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Extends the displayed results with the next page of the active search.
+    ///
+    /// Requested while searching, the page is loaded once the search has finished.
+    async fn load_more(&mut self) {
+        if !self.awaiting_results.is_empty() {
+            self.load_more_scheduled = true;
+            return;
+        }
+
+        // The page only advances while there are results beyond it.
+        if self.more {
+            self.page += 1;
+        }
+
+        self.respond_results().await;
+    }
+
     async fn quit(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _res = plugin
-                .sender_exec()
-                .send_async(Request::Quit(meta.id))
-                .await;
+        self.send_for_result(id, Request::Quit).await;
+    }
+
+    /// Sends a request for a search result to its plugin.
+    ///
+    /// The IDs of a plugin's results refer to the last query that it searched
+    /// for, which may be another favorite than the result's. The plugin then
+    /// searches for the result's query again, and the request is sent with
+    /// the result's new ID once the search has finished.
+    async fn send_for_result(&mut self, id: Indice, request: impl FnOnce(Indice) -> Request) {
+        let Some((plugin_key, meta)) = self.active_search.get(id as usize) else {
+            return;
+        };
+
+        let plugin_key = *plugin_key;
+        let request = request(meta.id);
+
+        let research = self.result_key(plugin_key, meta).and_then(|key| {
+            let query = match self.resolved.get(&key) {
+                Some(query) => query.clone(),
+                None => self.last_query.clone(),
+            };
+
+            let stale = self.plugin_queries.get(&plugin_key) != Some(&query)
+                && !self.awaiting_results.contains(&plugin_key);
+
+            stale.then_some((key, query))
+        });
+
+        let Some(plugin) = self.plugins.get_mut(plugin_key) else {
+            return;
+        };
+
+        let Some((key, query)) = research else {
+            let _res = plugin.sender_exec().send_async(request).await;
+            return;
+        };
+
+        if plugin
+            .sender_exec()
+            .send_async(Request::Search(query.clone()))
+            .await
+            .is_ok()
+        {
+            self.plugin_queries.insert(plugin_key, query);
+            self.awaiting_results.insert(plugin_key);
+            self.researching = Some(Research {
+                plugin: plugin_key,
+                key,
+                position: id,
+                request,
+                found: None,
+            });
+        }
+    }
+
+    /// Sends the request of a result which its plugin has searched for again.
+    async fn researched(&mut self, research: Research) {
+        self.awaiting_results.remove(&research.plugin);
+
+        match research.found {
+            Some(found) => {
+                self.associated_list
+                    .insert((research.plugin, found), research.position);
+
+                if let Some(plugin) = self.plugins.get_mut(research.plugin) {
+                    let request = match research.request {
+                        Request::Activate(_) => Request::Activate(found),
+                        Request::ActivateContext { context, .. } => {
+                            Request::ActivateContext { id: found, context }
+                        }
+                        Request::Complete(_) => Request::Complete(found),
+                        Request::Context(_) => Request::Context(found),
+                        Request::Quit(_) => Request::Quit(found),
+                        request => request,
+                    };
+
+                    let _res = plugin.sender_exec().send_async(request).await;
+                }
+            }
+
+            None => tracing::warn!("{} is no longer found by its plugin", research.key.id),
         }
+
+        if self.awaiting_results.is_empty() && self.search_scheduled {
+            self.search(String::new()).await;
+        }
+    }
+
+    /// Identifies a search result by its plugin, for pinning and hiding.
+    fn result_key(&self, plugin: PluginKey, meta: &PluginSearchResult) -> Option<ResultKey> {
+        let plugin = self.plugins.get(plugin)?;
+        ResultKey::new(&plugin.config.name, meta)
     }
 
     async fn respond(&mut self, event: Response) {
         let _res = self.output.send(event).await;
     }
 
+    /// Responds with the results of the active search, up to the loaded page.
+    async fn respond_results(&mut self) {
+        let search_list = self.results();
+        self.respond(Response::Update(search_list)).await;
+
+        if self.frontend.has_more {
+            let more = self.more;
+            self.respond(Response::HasMore(more)).await;
+        }
+    }
+
     async fn search(&mut self, query: String) {
         if !self.awaiting_results.is_empty() {
             tracing::debug!("backing off from search until plugins are ready");
@@ -460,6 +1096,9 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
         }
 
         self.active_search.clear();
+        self.favorite_queue.clear();
+        self.resolved.clear();
+        self.page = 0;
 
         if !self.search_scheduled {
             self.last_query = query;
@@ -475,10 +1114,10 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
 
         for (key, plugin) in self.plugins.iter_mut() {
             // Avoid sending queries to plugins which are not matched
-            if let Some(regex) = plugin.regex.as_ref() {
-                if !regex.is_match(query) {
-                    continue;
-                }
+            if let Some(regex) = plugin.regex.as_ref()
+                && !regex.is_match(query)
+            {
+                continue;
             }
 
             if requires_persistence && !plugin.config.query.persistent {
@@ -490,43 +1129,76 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
                 break;
             }
 
-            if let Some(regex) = plugin.isolate_regex.as_ref() {
-                if regex.is_match(query) {
-                    isolated = Some(key);
-                    break;
-                }
+            if let Some(regex) = plugin.isolate_regex.as_ref()
+                && regex.is_match(query)
+            {
+                isolated = Some(key);
+                break;
             }
 
             query_queue.push(key);
         }
 
         if let Some(isolated) = isolated {
-            if let Some(plugin) = self.plugins.get_mut(isolated) {
-                if plugin
+            if let Some(plugin) = self.plugins.get_mut(isolated)
+                && plugin
                     .sender_exec()
                     .send_async(Request::Search(query.to_owned()))
                     .await
                     .is_ok()
-                {
-                    self.awaiting_results.insert(isolated);
-                    self.no_sort = plugin.config.query.no_sort;
-                }
+            {
+                self.awaiting_results.insert(isolated);
+                self.plugin_queries.insert(isolated, query.to_owned());
+                self.no_sort = plugin.config.query.no_sort;
             }
         } else {
             self.no_sort = query.is_empty();
 
             for plugin_id in query_queue {
-                if let Some(plugin) = self.plugins.get_mut(plugin_id) {
-                    if plugin
+                if let Some(plugin) = self.plugins.get_mut(plugin_id)
+                    && plugin
                         .sender_exec()
                         .send_async(Request::Search(query.to_owned()))
                         .await
                         .is_ok()
-                    {
-                        self.awaiting_results.insert(plugin_id);
-                    }
+                {
+                    self.awaiting_results.insert(plugin_id);
+                    self.plugin_queries.insert(plugin_id, query.to_owned());
                 }
             }
+
+            if requires_persistence {
+                self.search_favorites().await;
+            }
+        }
+    }
+
+    /// Requests the favorites from the plugins that they were found by.
+    ///
+    /// Plugins which are already searching will search for their favorites
+    /// once they have finished.
+    async fn search_favorites(&mut self) {
+        for favorite in self.curation.favorites() {
+            let plugin = self
+                .plugins
+                .iter()
+                .find(|(_, plugin)| plugin.config.name == favorite.key.plugin);
+
+            if let Some((key, _)) = plugin {
+                self.favorite_queue
+                    .entry(key)
+                    .or_default()
+                    .push_back(favorite.clone());
+            }
+        }
+
+        let plugins: Vec<PluginKey> = self.favorite_queue.keys().copied().collect();
+
+        for plugin in plugins {
+            if !self.awaiting_results.contains(&plugin) && self.resolve_next_favorite(plugin).await
+            {
+                self.awaiting_results.insert(plugin);
+            }
         }
     }
 
@@ -548,50 +1220,101 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             })
     }
 
-    #[allow(clippy::too_many_lines)]
-    fn sort(&mut self) -> Vec<SearchResult> {
+    fn sort(&mut self) {
         let &mut Self {
             ref mut active_search,
-            ref mut associated_list,
-            ref mut no_sort,
+            no_sort,
             ref last_query,
             ref plugins,
             ref recent,
+            ref curation,
             ..
         } = self;
 
         let query = &last_query.to_ascii_lowercase();
 
-        if *no_sort {
-            *no_sort = false;
+        if no_sort {
+            // Results stay in the order of their plugin, besides those which are pinned.
+            active_search.sort_by_cached_key(|(key, meta)| {
+                !plugins
+                    .get(*key)
+                    .and_then(|plugin| ResultKey::new(&plugin.config.name, meta))
+                    .is_some_and(|key| curation.is_pinned(&key))
+            });
         } else {
-            active_search.sort_by(|a, b| {
-                // Weight is calculated between 0.0 and 1.0, with higher values being most similar
+            let get_prio = |sr: &PluginSearchResult, plg: &PluginConnector| -> Priority {
+                // Only plugins which keep a history are ranked by recent use.
+                let ex = sr.cache_identifier().filter(|_| plg.config.history);
+                Priority {
+                    plugin_priority: plg.config.query.priority,
+                    pinned: ResultKey::new(&plg.config.name, sr)
+                        .is_some_and(|key| curation.is_pinned(&key)),
+                    match_score: calculate_weight(sr, query),
+                    recent_score: ex.as_ref().map(|s| recent.get_recent(s)).unwrap_or(0.),
+                    freq_score: ex.as_ref().map(|s| recent.get_freq(s)).unwrap_or(0.),
+                    execlen: sr.name.len(),
+                }
+            };
 
-                let plug1 = match plugins.get(a.0) {
-                    Some(plug) => plug,
-                    None => return Ordering::Greater,
-                };
+            // Weight is calculated between 0.0 and 1.0, with higher values being most similar.
+            // Results of unknown plugins are ranked last.
+            let priorities: Vec<Option<Priority>> = active_search
+                .iter()
+                .map(|(key, meta)| plugins.get(*key).map(|plugin| get_prio(meta, plugin)))
+                .collect();
+
+            // Ordered results are ranked by the best of them from their plugin, and
+            // keep the order that they were sent in.
+            let mut best: HashMap<PluginKey, usize> = HashMap::new();
+            for (position, (key, meta)) in active_search.iter().enumerate() {
+                if meta.ordered {
+                    best.entry(*key)
+                        .and_modify(|best| {
+                            if priorities[position] > priorities[*best] {
+                                *best = position;
+                            }
+                        })
+                        .or_insert(position);
+                }
+            }
 
-                let plug2 = match plugins.get(b.0) {
-                    Some(plug) => plug,
-                    None => return Ordering::Less,
-                };
+            let mut ranked: Vec<(usize, (PluginKey, PluginSearchResult))> =
+                active_search.drain(..).enumerate().collect();
 
-                let get_prio = |sr: &PluginSearchResult, plg: &PluginConnector| -> Priority {
-                    let ex = sr.cache_identifier();
-                    Priority {
-                        plugin_priority: plg.config.query.priority,
-                        match_score: calculate_weight(sr, query),
-                        recent_score: ex.as_ref().map(|s| recent.get_recent(s)).unwrap_or(0.),
-                        freq_score: ex.as_ref().map(|s| recent.get_freq(s)).unwrap_or(0.),
-                        execlen: sr.name.len(),
-                    }
-                };
+            let rank = |(position, (key, meta)): &(usize, (PluginKey, PluginSearchResult))| {
+                let position = if meta.ordered { best[key] } else { *position };
+                &priorities[position]
+            };
+
+            ranked.sort_by(|a, b| rank(b).cmp(rank(a)).then(a.0.cmp(&b.0)));
 
-                get_prio(&b.1, plug2).cmp(&get_prio(&a.1, plug1))
+            active_search.extend(ranked.into_iter().map(|(_, result)| result));
+        }
+
+        // Favorites are displayed in the order that the user has arranged them.
+        if last_query.is_empty() {
+            active_search.sort_by_cached_key(|(key, meta)| {
+                plugins
+                    .get(*key)
+                    .and_then(|plugin| ResultKey::new(&plugin.config.name, meta))
+                    .and_then(|key| curation.favorite_position(&key))
+                    .unwrap_or(usize::MAX)
             });
         }
+    }
+
+    /// Groups the sorted search results into sections, up to the currently loaded page.
+    fn results(&mut self) -> Vec<SearchResult> {
+        let &mut Self {
+            ref active_search,
+            ref mut associated_list,
+            ref last_query,
+            ref plugins,
+            ref curation,
+            ref running,
+            page,
+            ..
+        } = self;
 
         let take = if last_query.is_empty() {
             self.args.max_open
@@ -601,39 +1324,72 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             self.args.max_search
         };
 
-        let mut windows = Vec::with_capacity(take);
-        let mut non_windows = Vec::with_capacity(take);
+        let mut sections: Vec<(&str, Vec<SearchResult>)> = Vec::new();
+        let mut taken: HashMap<PluginKey, usize> = HashMap::new();
+        let mut more = false;
         associated_list.clear();
 
-        let search_results =
-            active_search
-                .iter()
-                .take(take)
-                .enumerate()
-                .map(|(id, (plugin, meta))| {
-                    associated_list.insert(meta.id, id as u32);
-                    SearchResult {
-                        id: id as u32,
-                        name: meta.name.clone(),
-                        description: meta.description.clone(),
-                        icon: meta.icon.clone(),
-                        category_icon: plugins
-                            .get(*plugin)
-                            .and_then(|conn| conn.config.icon.clone()),
-                        window: meta.window,
-                    }
-                });
+        for (id, (plugin_key, meta)) in active_search.iter().enumerate() {
+            let Some(plugin) = plugins.get(*plugin_key) else {
+                continue;
+            };
 
-        for result in search_results {
-            if result.window.is_some() {
-                windows.push(result);
+            if ResultKey::new(&plugin.config.name, meta).is_some_and(|key| curation.is_hidden(&key))
+            {
+                continue;
+            }
+
+            let favorite = last_query.is_empty()
+                && ResultKey::new(&plugin.config.name, meta)
+                    .is_some_and(|key| curation.favorite_position(&key).is_some());
+
+            // Favorites are not limited, as the user has chosen each of them.
+            let section = if favorite {
+                FAVORITES_SECTION
             } else {
-                non_windows.push(result);
+                let count = taken.entry(*plugin_key).or_insert(0);
+                if *count >= plugin.config.query.max_results.unwrap_or(take) * (page + 1) {
+                    more = true;
+                    continue;
+                }
+
+                *count += 1;
+
+                plugin.config.section()
+            };
+
+            associated_list.insert((*plugin_key, meta.id), id as u32);
+
+            let result = SearchResult {
+                id: id as u32,
+                name: meta.name.clone(),
+                description: meta.description.clone(),
+                icon: meta.icon.clone(),
+                category_icon: plugin.config.icon.clone(),
+                window: meta.window,
+                section: Some(section.to_owned()),
+                alternate: meta.alternate.clone(),
+                running: meta.window.is_none()
+                    && meta
+                        .app_id
+                        .as_ref()
+                        .is_some_and(|app_id| running.contains_key(&app_id.to_ascii_lowercase())),
+            };
+
+            match sections.iter_mut().find(|(name, _)| *name == section) {
+                Some((_, results)) => results.push(result),
+                None => sections.push((section, vec![result])),
             }
         }
 
-        windows.append(&mut non_windows);
-        windows
+        // Stable sort keeps unlisted sections in the order of their best result.
+        sections.sort_by_key(|(name, _)| self.settings.section_order(name));
+        self.more = more;
+
+        sections
+            .into_iter()
+            .flat_map(|(_, results)| results)
+            .collect()
     }
 }
 
@@ -707,6 +1463,23 @@ fn calculate_weight(meta: &PluginSearchResult, query: &str) -> f64 {
                 }),
             None => 0.0,
         })
+        // untranslated names and application IDs rank just below the name
+        .max(match meta.alt_names.as_ref() {
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    let name = name.to_ascii_lowercase();
+                    let mut words =
+                        name.split(|c: char| c.is_ascii_whitespace() || "._-".contains(c));
+                    if words.any(|word| word.starts_with(query)) {
+                        0.95
+                    } else {
+                        strsim::jaro_winkler(query, &name) - 0.05
+                    }
+                })
+                .fold(0.0, f64::max),
+            None => 0.0,
+        })
         // deprioritize description matches the most
         .max(strsim::jaro_winkler(&description, query) * 0.9 - 0.1)
 }
@@ -716,10 +1489,409 @@ mod tests {
     use super::*;
     use pop_launcher::PluginSearchResult;
 
+    type TestService = Service<flume::r#async::SendSink<'static, Response>>;
+
+    /// A service without plugins, curation, or settings of the user's own.
+    fn service() -> (TestService, Receiver<Response>) {
+        let (tx, rx) = flume::unbounded();
+        let mut service = Service::new(tx.into_sink(), RecentUseStorage::default());
+        service.curation = CurationStorage::default();
+        service.settings = Settings::default();
+        (service, rx)
+    }
+
+    /// Registers a plugin, whose requests from the service are received instead of handled.
+    fn plugin(service: &mut TestService, config: PluginConfig) -> (PluginKey, Receiver<Request>) {
+        let (tx, rx) = flume::unbounded();
+        let key = service.plugins.insert(PluginConnector::new(
+            config,
+            None,
+            None,
+            Box::new(move || tx.clone()),
+        ));
+
+        (key, rx)
+    }
+
+    fn config(name: &'static str) -> PluginConfig {
+        PluginConfig {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    fn result(id: Indice, name: &str) -> PluginSearchResult {
+        PluginSearchResult {
+            id,
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Searches with the results that each plugin responds with.
+    async fn search(
+        service: &mut TestService,
+        query: &str,
+        results: Vec<(PluginKey, Vec<PluginSearchResult>)>,
+    ) {
+        service.search(query.to_owned()).await;
+
+        for (plugin, results) in results {
+            for result in results {
+                service.append(plugin, result);
+            }
+
+            service.finished(plugin).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn plugin_context_options_are_forwarded() {
+        let (mut service, _responses) = service();
+        let (plugin, requests) = plugin(&mut service, config("Applications"));
+
+        search(
+            &mut service,
+            "fire",
+            vec![(plugin, vec![result(7, "Firefox")])],
+        )
+        .await;
+        requests.drain();
+
+        // Such as the confirmation of an uninstallation, which precedes the service's options.
+        let context = SERVICE_CONTEXT_OFFSET - 2;
+        service.activate_context(0, context).await;
+
+        assert!(matches!(
+            requests.try_recv(),
+            Ok(Request::ActivateContext { id: 7, context: c }) if c == context
+        ));
+
+        // Options of the service are handled by the service itself.
+        service.activate_context(0, CONTEXT_COPY_NAME).await;
+        assert!(requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn favorites_are_found_by_their_query() {
+        let (mut service, responses) = service();
+        let (plugin, requests) = plugin(&mut service, config("Calculator"));
+
+        search(&mut service, "= 2+2", vec![(plugin, vec![result(0, "4")])]).await;
+        service.activate_context(0, CONTEXT_FAVORITE).await;
+
+        let favorite = &service.curation.favorites()[0];
+        assert_eq!(favorite.key.id, "4");
+        assert_eq!(favorite.query, "= 2+2");
+
+        requests.drain();
+        responses.drain();
+
+        // The prefix of the query selects the plugin, so the name alone would not be found.
+        service.search(String::new()).await;
+        assert!(matches!(requests.try_recv(), Ok(Request::Search(query)) if query == "= 2+2"));
+
+        service.append(plugin, result(3, "5"));
+        service.append(plugin, result(4, "4"));
+        service.finished(plugin).await;
+
+        let Ok(Response::Update(results)) = responses.try_recv() else {
+            panic!("expected the favorites");
+        };
+
+        let names: Vec<_> = results.iter().map(|result| result.name.as_str()).collect();
+        assert_eq!(names, ["4"]);
+    }
+
+    /// Handles events as if they were received from the frontend and plugins.
+    async fn handle(service: &mut TestService, events: Vec<Event>) {
+        let (tx, rx) = flume::unbounded();
+
+        for event in events {
+            tx.send(event).unwrap();
+        }
+
+        drop(tx);
+        service.response_handler(rx).await;
+    }
+
+    /// The events of a plugin which responds with results, and finishes.
+    fn appended(plugin: PluginKey, results: Vec<PluginSearchResult>) -> Vec<Event> {
+        results
+            .into_iter()
+            .map(PluginResponse::Append)
+            .chain([PluginResponse::Finished])
+            .map(|response| Event::Response((plugin, response)))
+            .collect()
+    }
+
+    /// The names of the displayed results, by their section.
+    fn sections(results: &[SearchResult]) -> Vec<(&str, &str)> {
+        results
+            .iter()
+            .map(|result| (result.section.as_deref().unwrap(), result.name.as_str()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn results_are_paged_by_section() {
+        let (mut service, responses) = service();
+        service.args.max_search = 2;
+        service.settings.sections = vec!["Web".to_owned()];
+
+        let (apps, _apps) = plugin(&mut service, config("Applications"));
+        let mut web = config("Web");
+        web.query.max_results = Some(1);
+        let (web, _web) = plugin(&mut service, web);
+
+        let mut events = vec![
+            Event::Request(Request::Configure(FrontendOptions {
+                has_more: true,
+                ..Default::default()
+            })),
+            Event::Request(Request::Search("a".to_owned())),
+        ];
+
+        events.extend(appended(
+            apps,
+            vec![result(0, "a1"), result(1, "a2"), result(2, "a3")],
+        ));
+        events.extend(appended(web, vec![result(0, "ab1"), result(1, "ab2")]));
+        events.push(Event::Request(Request::LoadMore));
+        events.push(Event::Request(Request::LoadMore));
+        handle(&mut service, events).await;
+
+        let responses: Vec<_> = responses.drain().collect();
+        let [
+            Response::Update(first),
+            Response::HasMore(true),
+            Response::Update(second),
+            Response::HasMore(false),
+            Response::Update(third),
+            Response::HasMore(false),
+        ] = &responses[..]
+        else {
+            panic!("unexpected responses: {responses:?}");
+        };
+
+        // Sections are ordered by the settings, and each plugin is limited to its own maximum.
+        assert_eq!(
+            sections(first),
+            [
+                ("Web", "ab1"),
+                ("Applications", "a1"),
+                ("Applications", "a2")
+            ]
+        );
+
+        let all = [
+            ("Web", "ab1"),
+            ("Web", "ab2"),
+            ("Applications", "a1"),
+            ("Applications", "a2"),
+            ("Applications", "a3"),
+        ];
+
+        assert_eq!(sections(second), all);
+        assert_eq!(sections(third), all);
+    }
+
+    /// The position of a result in the active search.
+    fn position(service: &TestService, name: &str) -> Indice {
+        let position = service
+            .active_search
+            .iter()
+            .position(|(_, meta)| meta.name == name);
+        position.unwrap() as Indice
+    }
+
+    #[tokio::test]
+    async fn results_are_curated() {
+        let (mut service, responses) = service();
+        let (plugin, _requests) = plugin(&mut service, config("Applications"));
+
+        let found = || vec![result(0, "Files"), result(1, "Fonts"), result(2, "Firefox")];
+        search(&mut service, "f", vec![(plugin, found())]).await;
+
+        // Firefox is pinned above the others, and Fonts is hidden.
+        service
+            .activate_context(position(&service, "Firefox"), CONTEXT_PIN)
+            .await;
+        service
+            .activate_context(position(&service, "Fonts"), CONTEXT_HIDE)
+            .await;
+        service
+            .activate_context(position(&service, "Files"), CONTEXT_FAVORITE)
+            .await;
+
+        // The curation is the same once it is read again.
+        let stored = serde_json::to_string(&service.curation).unwrap();
+        service.curation = serde_json::from_str(&stored).unwrap();
+
+        responses.drain();
+        search(&mut service, "f", vec![(plugin, found())]).await;
+
+        let Ok(Response::Update(results)) = responses.try_recv() else {
+            panic!("expected the results");
+        };
+
+        let names: Vec<_> = results.iter().map(|result| result.name.as_str()).collect();
+        assert_eq!(names, ["Firefox", "Files"]);
+
+        let key = service.result_key(plugin, &result(0, "Files")).unwrap();
+        assert_eq!(service.curation.favorite_position(&key), Some(0));
+
+        // Hiding a favorite also removes it from the favorites.
+        service
+            .activate_context(position(&service, "Files"), CONTEXT_HIDE)
+            .await;
+        assert!(service.curation.favorites().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ordered_results_keep_their_order() {
+        let (mut service, _responses) = service();
+        let (scripts, _scripts) = plugin(&mut service, config("Scripts"));
+        let (apps, _apps) = plugin(&mut service, config("Applications"));
+
+        let ordered = |id, name| PluginSearchResult {
+            ordered: true,
+            ..result(id, name)
+        };
+
+        search(
+            &mut service,
+            "b",
+            vec![
+                (
+                    scripts,
+                    vec![ordered(0, "alpha"), ordered(1, "beta"), ordered(2, "gamma")],
+                ),
+                (apps, vec![result(0, "Calendar")]),
+            ],
+        )
+        .await;
+
+        // They are ranked together by the best match among them, which is beta.
+        let names: Vec<_> = service
+            .active_search
+            .iter()
+            .map(|(_, meta)| meta.name.as_str())
+            .collect();
+        assert_eq!(names, ["alpha", "beta", "gamma", "Calendar"]);
+    }
+
+    #[tokio::test]
+    async fn running_applications_are_focused() {
+        let (mut service, responses) = service();
+        let (windows, window_requests) = plugin(&mut service, config("Windows"));
+        let (apps, app_requests) = plugin(&mut service, config("Applications"));
+
+        let window = PluginSearchResult {
+            window: Some((0, 5)),
+            app_id: Some("org.mozilla.firefox".to_owned()),
+            ..result(5, "Firefox")
+        };
+
+        let app = PluginSearchResult {
+            app_id: Some("org.mozilla.Firefox".to_owned()),
+            ..result(0, "Firefox")
+        };
+
+        search(
+            &mut service,
+            "firefox",
+            vec![(windows, vec![window]), (apps, vec![app])],
+        )
+        .await;
+
+        let Some(Response::Update(results)) = responses.drain().last() else {
+            panic!("expected the results");
+        };
+
+        let app = results
+            .iter()
+            .find(|result| result.window.is_none())
+            .unwrap();
+        assert!(app.running);
+
+        window_requests.drain();
+        app_requests.drain();
+
+        // The application's window is focused instead of launching it again.
+        service.activate(app.id).await;
+        assert!(matches!(
+            window_requests.try_recv(),
+            Ok(Request::Activate(5))
+        ));
+        assert!(app_requests.is_empty());
+
+        service.settings.focus_running_apps = false;
+        service.activate(app.id).await;
+        assert!(matches!(app_requests.try_recv(), Ok(Request::Activate(0))));
+        assert!(window_requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn running_applications_are_matched_within_the_search() {
+        let (mut service, responses) = service();
+        let (windows, window_requests) = plugin(&mut service, config("Windows"));
+        let (apps, app_requests) = plugin(&mut service, config("Applications"));
+
+        let app = PluginSearchResult {
+            app_id: Some("org.mozilla.firefox".to_owned()),
+            keywords: Some(vec!["Browser".to_owned()]),
+            ..result(0, "Firefox")
+        };
+
+        // The window of Firefox is open, but its title does not match the keyword.
+        search(
+            &mut service,
+            "browser",
+            vec![(windows, Vec::new()), (apps, vec![app])],
+        )
+        .await;
+
+        let Some(Response::Update(results)) = responses.drain().last() else {
+            panic!("expected the results");
+        };
+
+        assert!(!results[0].running);
+
+        window_requests.drain();
+        app_requests.drain();
+
+        service.activate(results[0].id).await;
+        assert!(matches!(app_requests.try_recv(), Ok(Request::Activate(0))));
+        assert!(window_requests.is_empty());
+    }
+
+    #[test]
+    fn test_alt_names_and_keywords_weight() {
+        // The generic name and categories of a desktop entry are among its keywords.
+        let image_editor = PluginSearchResult {
+            name: "GNU Image Manipulation Program".to_string(),
+            alt_names: Some(vec!["org.gimp.GIMP".to_string()]),
+            keywords: Some(vec!["Image Editor".to_string(), "Graphics".to_string()]),
+            ..Default::default()
+        };
+
+        let text_editor = PluginSearchResult {
+            name: "Text Editor".to_string(),
+            ..Default::default()
+        };
+
+        assert!(
+            calculate_weight(&text_editor, "editor") > calculate_weight(&image_editor, "editor")
+        );
+        assert!(calculate_weight(&image_editor, "editor") > 0.8);
+        assert!(calculate_weight(&image_editor, "gimp") > 0.9);
+    }
+
     #[test]
     fn test_script_calculate_weight() {
         // Test queries for each of the prompt's entries
-        let entries = vec![
+        let entries = [
             (
                 "Enter BIOS",
                 PluginSearchResult {
@@ -729,6 +1901,10 @@ mod tests {
                     icon: None,
                     window: None,
                     exec: None,
+                    alternate: None,
+                    app_id: None,
+                    alt_names: None,
+                    ordered: false,
                     keywords: Some(vec![
                         "bios".to_string(),
                         "uefi".to_string(),
@@ -746,6 +1922,10 @@ mod tests {
                     icon: None,
                     window: None,
                     exec: None,
+                    alternate: None,
+                    app_id: None,
+                    alt_names: None,
+                    ordered: false,
                     keywords: Some(vec![
                         "power".to_string(),
                         "reboot".to_string(),
@@ -8,7 +8,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-#[derive(Debug, Default, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct PluginConfig {
     pub name: Cow<'static, str>,
     pub description: Cow<'static, str>,
@@ -25,16 +25,47 @@ pub struct PluginConfig {
     )]
     pub icon: Option<crate::IconSource>,
 
+    /// Heading of the section that results from this plugin are grouped under.
+    ///
+    /// Defaults to the name of the plugin.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub section: Option<Cow<'static, str>>,
+
     #[serde(default)]
     pub query: PluginQuery,
 
-    #[serde(default)]
+    /// Results are ranked by how recently and often they were used, unless
+    /// the plugin opts out.
+    #[serde(default = "default_history")]
     pub history: bool,
 
     #[serde(default)]
     pub long_lived: bool,
 }
 
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            name: Cow::Borrowed(""),
+            description: Cow::Borrowed(""),
+            bin: None,
+            icon: None,
+            section: None,
+            query: PluginQuery::default(),
+            history: default_history(),
+            long_lived: false,
+        }
+    }
+}
+
+fn default_history() -> bool {
+    true
+}
+
 #[derive(Debug, Default, Deserialize, Clone)]
 pub struct PluginBinary {
     path: Cow<'static, str>,
@@ -63,6 +94,14 @@ pub struct PluginQuery {
     )]
     pub isolate_with: Option<Cow<'static, str>>,
 
+    /// Max number of results from this plugin in a response.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub max_results: Option<usize>,
+
     #[serde(default)]
     pub no_sort: bool,
 
@@ -80,19 +119,21 @@ pub struct PluginQuery {
     pub regex: Option<Cow<'static, str>>,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, PartialOrd, Ord)]
+impl PluginConfig {
+    /// The heading of the section that this plugin's results are displayed under.
+    pub fn section(&self) -> &str {
+        self.section.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, PartialOrd, Ord)]
 pub enum PluginPriority {
     High = 0,
+    #[default]
     Default = 1,
     Low = 2,
 }
 
-impl Default for PluginPriority {
-    fn default() -> Self {
-        Self::Default
-    }
-}
-
 pub fn load(source: &Path, config_path: &Path) -> Option<(PathBuf, PluginConfig, Option<Regex>)> {
     if let Ok(config_bytes) = std::fs::read_to_string(config_path) {
         let config = match ron::from_str::<PluginConfig>(&config_bytes) {
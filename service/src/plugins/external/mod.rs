@@ -66,68 +66,68 @@ impl ExternalPlugin {
             .spawn()
             .ok();
 
-        if let Some(mut child) = child {
-            if let Some(stdout) = child.stdout.take() {
-                let detached = self.detached.clone();
-                let searching = self.searching.clone();
-                let (trip_tx, trip_rx) = oneshot::<()>();
-                let tx = self.tx.clone();
-                let name = self.name().to_owned();
-                let id = self.id;
-
-                // Spawn a background task to forward JSON responses from the child process.
-                let task = tokio::spawn(async move {
-                    let tx_ = tx.clone();
-                    let searching_ = searching.clone();
-                    let name_ = name.clone();
-
-                    // Future for directly handling the JSON output from the process.
-                    let responder = async move {
-                        let mut requests = crate::json_input_stream(stdout);
-
-                        while let Some(result) = requests.next().await {
-                            match result {
-                                Ok(response) => {
-                                    if let PluginResponse::Finished = response {
-                                        searching_.store(false, Ordering::SeqCst);
-                                    }
-
-                                    let _ = tx_.send_async(Event::Response((id, response))).await;
-                                }
-                                Err(why) => {
-                                    tracing::error!("{}: serde error: {:?}", name_, why);
+        if let Some(mut child) = child
+            && let Some(stdout) = child.stdout.take()
+        {
+            let detached = self.detached.clone();
+            let searching = self.searching.clone();
+            let (trip_tx, trip_rx) = oneshot::<()>();
+            let tx = self.tx.clone();
+            let name = self.name().to_owned();
+            let id = self.id;
+
+            // Spawn a background task to forward JSON responses from the child process.
+            let task = tokio::spawn(async move {
+                let tx_ = tx.clone();
+                let searching_ = searching.clone();
+                let name_ = name.clone();
+
+                // Future for directly handling the JSON output from the process.
+                let responder = async move {
+                    let mut requests = crate::json_input_stream(stdout);
+
+                    while let Some(result) = requests.next().await {
+                        match result {
+                            Ok(response) => {
+                                if let PluginResponse::Finished = response {
+                                    searching_.store(false, Ordering::SeqCst);
                                 }
+
+                                let _ = tx_.send_async(Event::Response((id, response))).await;
+                            }
+                            Err(why) => {
+                                tracing::error!("{}: serde error: {:?}", name_, why);
                             }
                         }
+                    }
 
-                        tracing::debug!("{}: exiting from responder", name_);
-                    };
+                    tracing::debug!("{}: exiting from responder", name_);
+                };
 
-                    let trip = async move {
-                        let _ = trip_rx.await;
-                    };
+                let trip = async move {
+                    let _ = trip_rx.await;
+                };
 
-                    futures::pin_mut!(responder);
-                    futures::pin_mut!(trip);
+                futures::pin_mut!(responder);
+                futures::pin_mut!(trip);
 
-                    futures::future::select(responder, trip)
-                        .await
-                        .factor_first();
+                futures::future::select(responder, trip)
+                    .await
+                    .factor_first();
 
-                    // Ensure that a task that was searching sends a finished signal if it dies.
-                    if searching.swap(false, Ordering::SeqCst) {
-                        let _ = tx
-                            .send_async(Event::Response((id, PluginResponse::Finished)))
-                            .await;
-                    }
+                // Ensure that a task that was searching sends a finished signal if it dies.
+                if searching.swap(false, Ordering::SeqCst) {
+                    let _ = tx
+                        .send_async(Event::Response((id, PluginResponse::Finished)))
+                        .await;
+                }
 
-                    detached.store(true, Ordering::SeqCst);
+                detached.store(true, Ordering::SeqCst);
 
-                    event!(Level::DEBUG, "{}: detached plugin", name);
-                });
+                event!(Level::DEBUG, "{}: detached plugin", name);
+            });
 
-                self.process = Some((task, child, trip_tx));
-            }
+            self.process = Some((task, child, trip_tx));
         }
 
         self.process.as_mut()
@@ -156,16 +156,16 @@ impl ExternalPlugin {
             self.launch();
         }
 
-        if let Some((_, child, _)) = self.process.as_mut() {
-            if let Some(stdin) = child.stdin.as_mut() {
-                if let Ok(mut serialized) = serde_json::to_vec(event) {
-                    serialized.push(b'\n');
-                    stdin.write_all(&serialized).await?;
-                    tracing::debug!("{}: sent message to external process", self.name());
-                }
-
-                return Ok(());
+        if let Some((_, child, _)) = self.process.as_mut()
+            && let Some(stdin) = child.stdin.as_mut()
+        {
+            if let Ok(mut serialized) = serde_json::to_vec(event) {
+                serialized.push(b'\n');
+                stdin.write_all(&serialized).await?;
+                tracing::debug!("{}: sent message to external process", self.name());
             }
+
+            return Ok(());
         }
 
         Err(io::Error::new(
@@ -17,12 +17,14 @@ pub const CONFIG: PluginConfig = PluginConfig {
         help: None,
         isolate: true,
         isolate_with: None,
+        max_results: None,
         no_sort: true,
         persistent: false,
         priority: PluginPriority::Default,
         regex: None,
     },
     icon: Some(IconSource::Name(Cow::Borrowed("system-help-symbolic"))),
+    section: None,
     history: false,
     long_lived: false,
 };
@@ -51,16 +53,16 @@ impl HelpPlugin {
 #[async_trait::async_trait]
 impl Plugin for HelpPlugin {
     async fn activate(&mut self, id: u32) {
-        if let Some(detail) = self.details.get(id as usize) {
-            if let Some(help) = detail.help.as_ref() {
-                let _ = self
-                    .tx
-                    .send_async(Event::Response((
-                        self.id,
-                        PluginResponse::Fill(help.clone()),
-                    )))
-                    .await;
-            }
+        if let Some(detail) = self.details.get(id as usize)
+            && let Some(help) = detail.help.as_ref()
+        {
+            let _ = self
+                .tx
+                .send_async(Event::Response((
+                    self.id,
+                    PluginResponse::Fill(help.clone()),
+                )))
+                .await;
         }
     }
 
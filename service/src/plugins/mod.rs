@@ -63,6 +63,8 @@ where
                 Request::Close => {
                     self.exit();
                 }
+                // Pagination and frontend options are handled by the service.
+                Request::LoadMore | Request::Configure(_) => (),
             }
         }
 
@@ -5,6 +5,7 @@ use crate::PluginPriority;
 // holds all values used for ordering search results
 pub struct Priority {
     pub plugin_priority: PluginPriority,
+    pub pinned: bool,
     pub match_score: f64,
     pub recent_score: f64,
     pub freq_score: f64,
@@ -29,6 +30,7 @@ impl Priority {
 impl PartialEq for Priority {
     fn eq(&self, other: &Self) -> bool {
         self.plugin_priority == other.plugin_priority
+            && self.pinned == other.pinned
             && self.compute_value() == other.compute_value()
             && self.execlen == other.execlen
     }
@@ -45,8 +47,11 @@ impl PartialOrd for Priority {
 impl Ord for Priority {
     fn cmp(&self, other: &Self) -> Ordering {
         match other.plugin_priority.cmp(&self.plugin_priority) {
-            Ordering::Equal => match self.compute_value().total_cmp(&other.compute_value()) {
-                Ordering::Equal => self.execlen.cmp(&other.execlen),
+            Ordering::Equal => match self.pinned.cmp(&other.pinned) {
+                Ordering::Equal => match self.compute_value().total_cmp(&other.compute_value()) {
+                    Ordering::Equal => self.execlen.cmp(&other.execlen),
+                    p => p,
+                },
                 p => p,
             },
             p => p,
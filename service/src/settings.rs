@@ -0,0 +1,55 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use serde::Deserialize;
+
+/// User preferences for the launcher service, read from `config.ron`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Settings {
+    /// Section headings in the order that they should be displayed.
+    ///
+    /// Sections which are not listed are placed after these, in the order
+    /// of their highest ranked result.
+    #[serde(default = "default_sections")]
+    pub sections: Vec<String>,
+
+    /// Activating an application which already has an open window focuses
+    /// that window, instead of launching a new instance.
+    #[serde(default = "default_true")]
+    pub focus_running_apps: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sections: default_sections(),
+            focus_running_apps: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_sections() -> Vec<String> {
+    vec![
+        String::from("Windows"),
+        String::from(crate::FAVORITES_SECTION),
+    ]
+}
+
+impl Settings {
+    /// Position of a section in the display order.
+    pub fn section_order(&self, section: &str) -> usize {
+        self.sections
+            .iter()
+            .position(|name| name == section)
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// Loads the service config with the highest priority.
+pub fn load() -> Settings {
+    pop_launcher::config::load_service()
+}
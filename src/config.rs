@@ -1,6 +1,7 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
+use serde::de::DeserializeOwned;
 use std::path::PathBuf;
 
 pub fn find(name: &'_ str) -> impl Iterator<Item = PathBuf> + '_ {
@@ -20,3 +21,43 @@ pub fn find(name: &'_ str) -> impl Iterator<Item = PathBuf> + '_ {
             })
         })
 }
+
+/// Reads the `config.ron` of a plugin with the highest priority, or the
+/// default config if none can be read.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    load_first(find(name))
+}
+
+/// Reads the launcher service's own `config.ron` with the highest priority,
+/// or the default config if none can be read.
+pub fn load_service<T: DeserializeOwned + Default>() -> T {
+    load_first(find_service())
+}
+
+fn load_first<T: DeserializeOwned + Default>(paths: impl Iterator<Item = PathBuf>) -> T {
+    for path in paths {
+        let string = match std::fs::read_to_string(&path) {
+            Ok(string) => string,
+            Err(why) => {
+                tracing::error!("failed to read config: {}", why);
+                continue;
+            }
+        };
+
+        match ron::from_str::<T>(&string) {
+            Ok(config) => return config,
+            Err(why) => {
+                tracing::error!("failed to deserialize config: {}", why);
+            }
+        }
+    }
+
+    T::default()
+}
+
+/// Locates the launcher service's own `config.ron`, from highest to least priority.
+pub fn find_service() -> impl Iterator<Item = PathBuf> {
+    crate::base_paths()
+        .map(|path| path.join("config.ron"))
+        .filter(|path| path.exists())
+}
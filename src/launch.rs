@@ -0,0 +1,424 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Launching of desktop entries, for frontends which do not launch them themselves.
+
+use freedesktop_desktop_entry::{self as fde, DesktopEntry};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+pub use switcheroo_control::Gpu;
+use zbus::zvariant::Value;
+
+/// The Exec key of the entry, or of one of its desktop actions.
+pub fn exec<'a>(entry: &'a DesktopEntry, action: Option<&str>) -> Option<&'a str> {
+    match action {
+        Some(action) => entry.action_exec(action),
+        None => entry.exec(),
+    }
+}
+
+/// Activates the application through D-Bus, if it supports it, returning
+/// `true` once the application has been activated.
+pub async fn dbus_activate(entry: &DesktopEntry, action: Option<&str>) -> bool {
+    if entry.desktop_entry("DBusActivatable") != Some("true") {
+        return false;
+    }
+
+    let id = entry.id();
+    let object_path = ["/", &id.replace('.', "/").replace('-', "_")].concat();
+
+    let Ok(connection) = zbus::Connection::session().await else {
+        return false;
+    };
+
+    let path = object_path.as_str();
+    let interface = Some("org.freedesktop.Application");
+    let platform_data = HashMap::<&str, Value>::new();
+
+    let reply = match action {
+        Some(action) => {
+            let body = (action, Vec::<Value>::new(), platform_data);
+            let method = "ActivateAction";
+            connection
+                .call_method(Some(id), path, interface, method, &body)
+                .await
+        }
+        None => {
+            let body = (platform_data,);
+            connection
+                .call_method(Some(id), path, interface, "Activate", &body)
+                .await
+        }
+    };
+
+    reply.is_ok()
+}
+
+/// The GPUs reported by switcheroo-control, if the system has more than one.
+pub async fn gpus() -> Option<Vec<Gpu>> {
+    let connection = zbus::Connection::system().await.ok()?;
+    let proxy = switcheroo_control::SwitcherooControlProxy::new(&connection)
+        .await
+        .ok()?;
+
+    if !proxy.has_dual_gpu().await.ok()? {
+        return None;
+    }
+
+    let gpus = proxy.get_gpus().await.ok()?;
+    if gpus.is_empty() {
+        return None;
+    }
+    Some(gpus)
+}
+
+/// Command which executes the entry, or one of its desktop actions.
+///
+/// The `environment` selects the GPU that the application renders with, as
+/// reported by switcheroo-control for each GPU.
+pub fn command<K, V>(
+    entry: &DesktopEntry,
+    action: Option<&str>,
+    environment: impl IntoIterator<Item = (K, V)>,
+) -> Option<Command>
+where
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let mut args = parse_exec(exec(entry, action)?, entry)?.into_iter();
+
+    let mut command = if entry.terminal() {
+        let mut command = TerminalConfig::load().command();
+        command.args(args);
+        command
+    } else {
+        let mut command = Command::new(args.next()?);
+        command.args(args);
+        command
+    };
+
+    if let Some(path) = entry.desktop_entry("Path").filter(|path| !path.is_empty()) {
+        command.current_dir(path);
+    }
+
+    command.envs(environment);
+    command.stdin(Stdio::null()).stdout(Stdio::null());
+
+    Some(command)
+}
+
+/// Splits an Exec value into arguments, and expands its field codes.
+///
+/// As nothing is passed to the launched application, file and URL field codes
+/// are removed. Returns `None` if a quoted argument is left unterminated.
+pub fn parse_exec(exec: &str, entry: &DesktopEntry) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+
+    for (arg, quoted) in split_exec(&unescape(exec))? {
+        if !quoted {
+            match arg.as_str() {
+                "%f" | "%F" | "%u" | "%U" => continue,
+                "%i" => {
+                    if let Some(icon) = entry.icon() {
+                        args.push(String::from("--icon"));
+                        args.push(icon.to_owned());
+                    }
+
+                    continue;
+                }
+                _ => (),
+            }
+        }
+
+        let mut expanded = String::with_capacity(arg.len());
+        let mut chars = arg.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('%') => expanded.push('%'),
+                Some('c') => expanded.push_str(entry.desktop_entry("Name").unwrap_or_default()),
+                Some('k') => expanded.push_str(&entry.path.to_string_lossy()),
+                Some('i') => expanded.push_str(entry.icon().unwrap_or_default()),
+                // Deprecated and file field codes expand to nothing.
+                _ => (),
+            }
+        }
+
+        if quoted || !expanded.is_empty() {
+            args.push(expanded);
+        }
+    }
+
+    Some(args)
+}
+
+/// Splits arguments by whitespace, where quoted arguments may contain
+/// whitespace and escaped characters.
+fn split_exec(exec: &str) -> Option<Vec<(String, bool)>> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            '\\' if in_quotes => arg.push(chars.next()?),
+            c if c.is_whitespace() && !in_quotes => {
+                if quoted || !arg.is_empty() {
+                    args.push((std::mem::take(&mut arg), quoted));
+                }
+
+                quoted = false;
+            }
+            c => arg.push(c),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+
+    if quoted || !arg.is_empty() {
+        args.push((arg, quoted));
+    }
+
+    Some(args)
+}
+
+/// Unescapes a string value of a desktop entry.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            // Escapes of the Exec key are handled when it is split.
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// The terminal emulator that commands are run in, which is shared with the
+/// `config.ron` of the terminal plugin.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TerminalConfig {
+    /// Terminal emulator to run commands in, by its executable name or path.
+    /// Detected from the installed terminal emulators if unset.
+    #[serde(default)]
+    pub terminal: Option<String>,
+
+    /// Arguments which precede the command, for terminal emulators whose
+    /// conventions are not known.
+    #[serde(default)]
+    pub exec_args: Option<Vec<String>>,
+
+    /// Shell which commands are run with.
+    #[serde(default = "default_shell")]
+    pub shell: String,
+}
+
+fn default_shell() -> String {
+    String::from("sh")
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            terminal: None,
+            exec_args: None,
+            shell: default_shell(),
+        }
+    }
+}
+
+impl TerminalConfig {
+    pub fn load() -> Self {
+        crate::config::load("terminal")
+    }
+
+    /// Command which runs the arguments that are added to it in the terminal.
+    pub fn command(&self) -> Command {
+        let terminal = match self.terminal.as_deref() {
+            Some(terminal) => Path::new(terminal),
+            None => detect_terminal(),
+        };
+
+        let mut command = Command::new(terminal);
+
+        match self.exec_args.as_ref() {
+            Some(args) => command.args(args),
+            None => command.args(terminal_exec_args(terminal)),
+        };
+
+        command
+    }
+}
+
+/// Arguments which precede the command that a terminal emulator is to run.
+pub fn terminal_exec_args(terminal: &Path) -> &'static [&'static str] {
+    let name = terminal.file_name().and_then(|name| name.to_str());
+
+    match name.unwrap_or_default() {
+        "foot" | "kitty" => &[],
+        "wezterm" => &["start", "--"],
+        "gnome-terminal" | "kgx" | "ptyxis" => &["--"],
+        _ => &["-e"],
+    }
+}
+
+/// Finds the terminal emulator to run commands in.
+///
+/// The installed desktop entries are only searched once.
+pub fn detect_terminal() -> &'static Path {
+    static TERMINAL: OnceLock<PathBuf> = OnceLock::new();
+    TERMINAL.get_or_init(find_terminal)
+}
+
+fn find_terminal() -> PathBuf {
+    use std::fs::read_link;
+
+    let terminal_apps: Vec<_> = fde::Iter::new(fde::default_paths())
+        .filter_map(|path| {
+            let de = DesktopEntry::from_path(path, None::<&[&str]>).ok()?;
+
+            if de.no_display()
+                || !de
+                    .categories()
+                    .is_some_and(|c| c.contains(&"TerminalEmulator"))
+            {
+                return None;
+            }
+
+            let exec = parse_exec(de.exec()?, &de)?.into_iter().next()?;
+
+            Some((de.id().to_owned(), exec))
+        })
+        .collect();
+
+    for id in ["com.system76.CosmicTerm"] {
+        for (terminal_id, exec) in &terminal_apps {
+            if terminal_id.as_str() == id {
+                return PathBuf::from(exec);
+            }
+        }
+    }
+
+    if let Some((_, exec)) = terminal_apps.first() {
+        return PathBuf::from(exec);
+    }
+
+    const SYMLINK: &str = "/usr/bin/x-terminal-emulator";
+
+    if let Ok(found) = read_link(SYMLINK) {
+        return read_link(&found).unwrap_or(found);
+    }
+
+    PathBuf::from("/usr/bin/gnome-terminal")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desktop_file(input: &str) -> DesktopEntry {
+        DesktopEntry::from_str(
+            "/usr/share/applications/org.example.App.desktop",
+            input,
+            None::<&[&str]>,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn exec_field_codes() {
+        let file = desktop_file(
+            "[Desktop Entry]\nName=Example App\nIcon=example\nExec=example --name %c %i %U\n",
+        );
+
+        assert_eq!(
+            parse_exec(exec(&file, None).unwrap(), &file).unwrap(),
+            ["example", "--name", "Example App", "--icon", "example"]
+        );
+
+        let file = desktop_file("[Desktop Entry]\nExec=example %k --open=%f 100%%\n");
+
+        assert_eq!(
+            parse_exec(exec(&file, None).unwrap(), &file).unwrap(),
+            [
+                "example",
+                "/usr/share/applications/org.example.App.desktop",
+                "--open=",
+                "100%"
+            ]
+        );
+    }
+
+    #[test]
+    fn exec_quoting() {
+        let file = desktop_file(
+            r#"[Desktop Entry]
+Exec=sh -c "echo \\$HOME \"quoted\"" ""
+"#,
+        );
+
+        assert_eq!(
+            parse_exec(exec(&file, None).unwrap(), &file).unwrap(),
+            ["sh", "-c", r#"echo $HOME "quoted""#, ""]
+        );
+
+        assert_eq!(parse_exec(r#"sh -c "unterminated"#, &file), None);
+    }
+
+    #[test]
+    fn terminal_conventions() {
+        assert_eq!(terminal_exec_args(Path::new("/usr/bin/alacritty")), ["-e"]);
+        assert_eq!(terminal_exec_args(Path::new("gnome-terminal")), ["--"]);
+        assert_eq!(terminal_exec_args(Path::new("wezterm")), ["start", "--"]);
+        assert!(terminal_exec_args(Path::new("kitty")).is_empty());
+    }
+
+    #[test]
+    fn desktop_actions() {
+        let file = desktop_file(
+            "[Desktop Entry]\nExec=example\n\n[Desktop Action new-window]\nExec=example --new-window\n",
+        );
+
+        assert_eq!(
+            exec(&file, Some("new-window")),
+            Some("example --new-window")
+        );
+        assert_eq!(exec(&file, Some("missing")), None);
+    }
+}
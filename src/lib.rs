@@ -3,6 +3,7 @@
 
 mod codec;
 pub mod config;
+pub mod launch;
 
 pub use self::codec::*;
 
@@ -24,18 +25,26 @@ pub const DISTRIBUTION_PLUGINS: &str = concatcp!(DISTRIBUTION, "/plugins");
 
 pub const PLUGIN_PATHS: &[&str] = &[LOCAL_PLUGINS, SYSTEM_PLUGINS, DISTRIBUTION_PLUGINS];
 
+pub const BASE_PATHS: &[&str] = &[LOCAL, SYSTEM, DISTRIBUTION];
+
 pub fn plugin_paths() -> impl Iterator<Item = Cow<'static, Path>> {
-    PLUGIN_PATHS.iter().map(|path| {
-        #[allow(deprecated)]
-        if let Some(path) = path.strip_prefix("~/") {
-            let path = dirs::home_dir()
-                .expect("user does not have home dir")
-                .join(path);
-            Cow::Owned(path)
-        } else {
-            Cow::Borrowed(Path::new(path))
-        }
-    })
+    PLUGIN_PATHS.iter().copied().map(expand_home)
+}
+
+pub fn base_paths() -> impl Iterator<Item = Cow<'static, Path>> {
+    BASE_PATHS.iter().copied().map(expand_home)
+}
+
+fn expand_home(path: &'static str) -> Cow<'static, Path> {
+    #[allow(deprecated)]
+    if let Some(path) = path.strip_prefix("~/") {
+        let path = dirs::home_dir()
+            .expect("user does not have home dir")
+            .join(path);
+        Cow::Owned(path)
+    } else {
+        Cow::Borrowed(Path::new(path))
+    }
 }
 
 /// u32 value defining the generation of an indice.
@@ -44,10 +53,22 @@ pub type Generation = u32;
 /// u32 value defining the indice of a slot.
 pub type Indice = u32;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Context option IDs from this value onward are reserved for options that the
+/// launcher service adds to every search result.
+pub const SERVICE_CONTEXT_OFFSET: Indice = Indice::MAX - 0xFF;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct ContextOption {
     pub id: Indice,
     pub name: String,
+    /// Key combination that the frontend is suggested to bind to this option,
+    /// such as `Ctrl+Q` or `Shift+Return`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator: Option<String>,
+    /// Designates this option as the alternate action of its result, which
+    /// frontends may activate in place of the primary action.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default_alternate: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
@@ -66,6 +87,8 @@ pub enum IconSource {
 }
 
 /// Sent from a plugin to the launcher service.
+// Search results are the most common response, so boxing them gains nothing.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum PluginResponse {
     /// Append a new search item to the launcher.
@@ -88,6 +111,8 @@ pub enum PluginResponse {
         #[serde(skip_serializing_if = "Option::is_none", default)]
         action_name: Option<String>,
     },
+    /// Copy text to the clipboard, which is forwarded to the frontend.
+    Copy(String),
     /// Update the text in the launcher.
     Fill(String),
     /// Indicates that a plugin is finished with its queries.
@@ -105,12 +130,26 @@ pub struct PluginSearchResult {
     pub description: String,
     /// Extra words to match when sorting and filtering.
     pub keywords: Option<Vec<String>>,
+    /// Other names of the item, such as untranslated names and application IDs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_names: Option<Vec<String>>,
     /// Icon to display in the frontend.
     pub icon: Option<IconSource>,
     /// Command that is executed by this result, used for sorting and filtering.
     pub exec: Option<String>,
     /// Designates that this search item refers to a window.
     pub window: Option<(Generation, Indice)>,
+    /// ID of the application that this item launches, or that this window belongs to.
+    ///
+    /// The service uses this to match applications with their open windows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    /// Context option to activate as the alternate action of this item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alternate: Option<ContextOption>,
+    /// Keeps this item in the order it was sent, among the other ordered items of its plugin.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ordered: bool,
 }
 
 impl PluginSearchResult {
@@ -142,12 +181,31 @@ pub enum Request {
     Close,
     /// Requests to cancel any active searches.
     Interrupt,
+    /// Request the next page of results for the active search.
+    ///
+    /// The service responds with an update that appends the next page to the
+    /// results already displayed, without searching again.
+    LoadMore,
     /// Request to close the selected item.
     Quit(Indice),
+    /// Set options for the remainder of the frontend's session.
+    Configure(FrontendOptions),
     /// Perform a search in our database.
     Search(String),
 }
 
+/// Options which a frontend sets for how the service responds to it.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct FrontendOptions {
+    /// The service launches desktop entries itself, instead of responding
+    /// with `Response::DesktopEntry`.
+    #[serde(default)]
+    pub launch_desktop_entries: bool,
+    /// The service follows each `Response::Update` with `Response::HasMore`.
+    #[serde(default)]
+    pub has_more: bool,
+}
+
 /// Sent from the launcher service to a frontend.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum Response {
@@ -158,6 +216,8 @@ pub enum Response {
         id: Indice,
         options: Vec<ContextOption>,
     },
+    // Text that the frontend should copy to the clipboard.
+    Copy(String),
     // Notifies that a .desktop entry should be launched by the frontend.
     DesktopEntry {
         path: PathBuf,
@@ -166,6 +226,8 @@ pub enum Response {
     },
     // The frontend should clear its search results and display a new list.
     Update(Vec<SearchResult>),
+    // Whether `Request::LoadMore` would display more results than the last update.
+    HasMore(bool),
     // An item was selected that resulted in a need to autofill the launcher.
     Fill(String),
 }
@@ -203,4 +265,28 @@ pub struct SearchResult {
     )]
     /// Designates that this search item refers to a window.
     pub window: Option<(Generation, Indice)>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    /// Heading of the section this item is grouped under.
+    ///
+    /// Items of the same section are always adjacent to each other.
+    pub section: Option<String>,
+
+    /// Designates that this item launches an application which already has
+    /// an open window, which activating this item will focus.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub running: bool,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    /// Context option to activate with `ActivateContext` as the alternate
+    /// action of this item.
+    pub alternate: Option<ContextOption>,
 }
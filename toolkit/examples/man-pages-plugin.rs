@@ -97,10 +97,14 @@ impl PluginExt for WhatIsPlugin {
                     id: idx as u32,
                     name: format!("{cmd} - {description}"),
                     keywords: None,
+                    alt_names: None,
                     description: description.clone(),
                     icon: None,
                     exec: None,
                     window: None,
+                    app_id: None,
+                    alternate: None,
+                    ordered: false,
                 }))
                 .await;
             }
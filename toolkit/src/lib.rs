@@ -65,9 +65,13 @@
 //!             name: search_result.clone(),
 //!             description: "".to_string(),
 //!             keywords: None,
+//!             alt_names: None,
 //!             icon: None,
 //!             exec: None,
 //!             window: None,
+//!             app_id: None,
+//!             alternate: None,
+//!             ordered: false,
 //!         })).await;
 //!      }
 //!
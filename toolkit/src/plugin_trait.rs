@@ -93,6 +93,7 @@ where
                         self.exit();
                         break;
                     }
+                    Request::LoadMore | Request::Configure(_) => (),
                 },
                 Err(why) => tracing::error!("Malformed json request: {why}"),
             }